@@ -1,5 +1,7 @@
 export type RenderContext = {
     title: string,
     registries: [number],
-    pixels: [boolean],
-}
\ No newline at end of file
+    pixels: [number],
+    sound_playing: boolean,
+    audio: { pattern: number[], pitch: number },
+}
@@ -0,0 +1,1046 @@
+use crate::disassembler::PROGRAM_START;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// highest address a program can be assembled to
+const MEMORY_SIZE: usize = 0x10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblerError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for AssemblerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AssemblerError {}
+
+/// an assembled program
+#[derive(Debug, Clone)]
+pub struct Assembly {
+    /// the bytes to load at `0x200`
+    pub rom: Vec<u8>,
+    /// address of every instruction and the source line it was written on
+    pub source_map: BTreeMap<u16, usize>,
+    pub labels: BTreeMap<String, u16>,
+}
+
+/// assembles Octo source into a rom
+///
+/// unless the source starts with `: main`, the program starts with a `jump main`
+pub fn assemble(source: &str) -> Result<Assembly, AssemblerError> {
+    let mut assembler = Assembler::new(tokenize(source));
+    assembler.run()?;
+
+    let end = assembler.end.max(PROGRAM_START as usize);
+    Ok(Assembly {
+        rom: assembler.memory[PROGRAM_START as usize..end].to_vec(),
+        source_map: assembler.source_map,
+        labels: assembler.labels.into_iter().collect(),
+    })
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+    /// the macros whose expansion produced the token, innermost last
+    macros: Vec<String>,
+}
+
+impl Token {
+    fn error(&self, message: impl Into<String>) -> AssemblerError {
+        AssemblerError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+/// splits the source at whitespace, `#` starts a comment and braces and parentheses are tokens on their own
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let mut current: Option<Token> = None;
+        for (column_index, char) in line.chars().enumerate() {
+            if char == '#' && current.is_none() {
+                break;
+            }
+
+            let single = matches!(char, '{' | '}' | '(' | ')');
+            if char.is_whitespace() || single {
+                tokens.extend(current.take());
+            }
+
+            if single {
+                tokens.push_back(Token {
+                    text: char.to_string(),
+                    line: line_index + 1,
+                    column: column_index + 1,
+                    macros: vec![],
+                });
+            } else if !char.is_whitespace() {
+                current
+                    .get_or_insert_with(|| Token {
+                        text: String::new(),
+                        line: line_index + 1,
+                        column: column_index + 1,
+                        macros: vec![],
+                    })
+                    .text
+                    .push(char);
+            }
+        }
+        tokens.extend(current.take());
+    }
+
+    tokens
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+
+    let value = if let Some(hex) = text.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = text.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else {
+        text.parse().ok()?
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix('v').or(text.strip_prefix('V'))?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+#[derive(Debug, Clone)]
+struct Macro {
+    arguments: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// a value patched in once all labels are known
+#[derive(Debug, Copy, Clone)]
+enum FixupKind {
+    /// the low 12 bits of an instruction
+    Address,
+    /// a full 16 bit word
+    LongAddress,
+    /// the low byte of `:unpack`'s first instruction, `nibble` becomes the high nibble
+    UnpackHigh { nibble: u8 },
+    /// the low byte of `:unpack`'s second instruction
+    UnpackLow,
+}
+
+#[derive(Debug, Clone)]
+struct Fixup {
+    address: usize,
+    kind: FixupKind,
+    label: Token,
+}
+
+/// open `if ... begin`, `else` and `loop` blocks
+#[derive(Debug, Clone)]
+enum Block {
+    /// the address of the jump to the `else` or `end`
+    If {
+        token: Token,
+        jump: usize,
+    },
+    Else {
+        token: Token,
+        jump: usize,
+    },
+    Loop {
+        token: Token,
+        start: usize,
+        breaks: Vec<usize>,
+    },
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Condition {
+    Compare(u8, Comparison, Operand),
+    /// `vx key` holds while the key is pressed
+    Key(u8),
+    /// `vx -key` holds while the key is released
+    NotKey(u8),
+}
+
+impl Condition {
+    fn negate(self) -> Condition {
+        match self {
+            Condition::Compare(x, comparison, operand) => {
+                let comparison = match comparison {
+                    Comparison::Equal => Comparison::NotEqual,
+                    Comparison::NotEqual => Comparison::Equal,
+                    Comparison::Less => Comparison::GreaterEqual,
+                    Comparison::Greater => Comparison::LessEqual,
+                    Comparison::LessEqual => Comparison::Greater,
+                    Comparison::GreaterEqual => Comparison::Less,
+                };
+                Condition::Compare(x, comparison, operand)
+            }
+            Condition::Key(x) => Condition::NotKey(x),
+            Condition::NotKey(x) => Condition::Key(x),
+        }
+    }
+}
+
+struct Assembler {
+    tokens: VecDeque<Token>,
+    /// the last token taken, used to report a missing token at the end of the source
+    last: Token,
+    memory: Vec<u8>,
+    here: usize,
+    end: usize,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    source_map: BTreeMap<u16, usize>,
+}
+
+impl Assembler {
+    fn new(tokens: VecDeque<Token>) -> Assembler {
+        Assembler {
+            tokens,
+            last: Token {
+                text: String::new(),
+                line: 1,
+                column: 1,
+                macros: vec![],
+            },
+            memory: vec![0; MEMORY_SIZE],
+            here: PROGRAM_START as usize,
+            end: PROGRAM_START as usize,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: vec![],
+            blocks: vec![],
+            source_map: BTreeMap::new(),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), AssemblerError> {
+        let starts_with_main = self.tokens.front().is_some_and(|token| token.text == ":")
+            && self.tokens.get(1).is_some_and(|token| token.text == "main");
+        if !starts_with_main {
+            let token = Token {
+                text: "main".to_string(),
+                line: 1,
+                column: 1,
+                macros: vec![],
+            };
+            self.emit_jump(0x1000, &token)?;
+        }
+
+        while let Some(token) = self.tokens.pop_front() {
+            self.last = token.clone();
+            self.statement(token)?;
+        }
+
+        if let Some(block) = self.blocks.last() {
+            let (token, expected) = match block {
+                Block::If { token, .. } | Block::Else { token, .. } => (token, "end"),
+                Block::Loop { token, .. } => (token, "again"),
+            };
+            return Err(token.error(format!("missing `{}`", expected)));
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            let Some(&value) = self.labels.get(&fixup.label.text) else {
+                return Err(fixup
+                    .label
+                    .error(format!("undefined label `{}`", fixup.label.text)));
+            };
+
+            match fixup.kind {
+                FixupKind::Address => {
+                    if value > 0xFFF {
+                        return Err(fixup.label.error(format!(
+                            "`{}` at {:#06x} is out of reach, use `i := long`",
+                            fixup.label.text, value
+                        )));
+                    }
+                    self.memory[fixup.address] |= (value >> 8) as u8;
+                    self.memory[fixup.address + 1] = value as u8;
+                }
+                FixupKind::LongAddress => {
+                    self.memory[fixup.address..fixup.address + 2]
+                        .copy_from_slice(&value.to_be_bytes());
+                }
+                FixupKind::UnpackHigh { nibble } => {
+                    self.memory[fixup.address + 1] = (nibble << 4) | (value >> 8) as u8 & 0xF;
+                }
+                FixupKind::UnpackLow => {
+                    self.memory[fixup.address + 1] = value as u8;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<Token, AssemblerError> {
+        let token = self.tokens.pop_front().ok_or_else(|| {
+            self.last.error(format!(
+                "unexpected end of source after `{}`",
+                self.last.text
+            ))
+        })?;
+        self.last = token.clone();
+        Ok(token)
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.tokens.front().is_some_and(|token| token.text == text)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, AssemblerError> {
+        let token = self.next()?;
+        if token.text != text {
+            return Err(token.error(format!("expected `{}` but found `{}`", text, token.text)));
+        }
+        Ok(token)
+    }
+
+    fn emit_u8(&mut self, value: u8, token: &Token) -> Result<(), AssemblerError> {
+        if self.here >= MEMORY_SIZE {
+            return Err(token.error("program does not fit into memory"));
+        }
+
+        self.memory[self.here] = value;
+        self.here += 1;
+        self.end = self.end.max(self.here);
+        Ok(())
+    }
+
+    fn emit_u16(&mut self, value: u16, token: &Token) -> Result<(), AssemblerError> {
+        self.source_map.insert(self.here as u16, token.line);
+        let [high, low] = value.to_be_bytes();
+        self.emit_u8(high, token)?;
+        self.emit_u8(low, token)
+    }
+
+    /// emits `opcode` with its low 12 bits set to the address of `label`
+    fn emit_jump(&mut self, opcode: u16, label: &Token) -> Result<(), AssemblerError> {
+        let target = match self.labels.get(&label.text) {
+            Some(&target) => target,
+            None => {
+                self.fixups.push(Fixup {
+                    address: self.here,
+                    kind: FixupKind::Address,
+                    label: label.clone(),
+                });
+                0
+            }
+        };
+
+        if target > 0xFFF {
+            return Err(label.error(format!(
+                "`{}` at {:#06x} is out of reach, use `i := long`",
+                label.text, target
+            )));
+        }
+        self.emit_u16(opcode | target, label)
+    }
+
+    /// emits `opcode` with its low 12 bits set to the address named by the next token
+    fn emit_address(&mut self, opcode: u16, token: &Token) -> Result<(), AssemblerError> {
+        let target = self.next()?;
+        match self.constant(&target) {
+            Some(value) => {
+                let value = self.check_range(&target, value, 0, 0xFFF)?;
+                self.emit_u16(opcode | value as u16, token)
+            }
+            None => self.emit_jump(opcode, &target),
+        }
+    }
+
+    /// value of a number or a constant
+    fn constant(&self, token: &Token) -> Option<i64> {
+        parse_number(&token.text).or_else(|| {
+            self.constants
+                .get(&token.text)
+                .map(|value| value.floor() as i64)
+        })
+    }
+
+    fn check_range(
+        &self,
+        token: &Token,
+        value: i64,
+        min: i64,
+        max: i64,
+    ) -> Result<i64, AssemblerError> {
+        if value < min || value > max {
+            return Err(token.error(format!(
+                "{} does not fit between {} and {}",
+                value, min, max
+            )));
+        }
+        Ok(value)
+    }
+
+    fn byte(&mut self) -> Result<u8, AssemblerError> {
+        let token = self.next()?;
+        let value = self
+            .constant(&token)
+            .ok_or_else(|| token.error(format!("expected a number but found `{}`", token.text)))?;
+        Ok(self.check_range(&token, value, -128, 255)? as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u8, AssemblerError> {
+        let token = self.next()?;
+        let value = self
+            .constant(&token)
+            .ok_or_else(|| token.error(format!("expected a number but found `{}`", token.text)))?;
+        Ok(self.check_range(&token, value, 0, 15)? as u8)
+    }
+
+    fn register_of(&self, token: &Token) -> Option<u8> {
+        parse_register(&token.text).or_else(|| self.aliases.get(&token.text).copied())
+    }
+
+    fn register(&mut self) -> Result<u8, AssemblerError> {
+        let token = self.next()?;
+        self.register_of(&token)
+            .ok_or_else(|| token.error(format!("expected a register but found `{}`", token.text)))
+    }
+
+    fn operand(&mut self) -> Result<Operand, AssemblerError> {
+        match self
+            .tokens
+            .front()
+            .and_then(|token| self.register_of(token))
+        {
+            Some(register) => {
+                self.next()?;
+                Ok(Operand::Register(register))
+            }
+            None => Ok(Operand::Byte(self.byte()?)),
+        }
+    }
+
+    fn name(&mut self) -> Result<Token, AssemblerError> {
+        let token = self.next()?;
+        let reserved = parse_number(&token.text).is_some()
+            || parse_register(&token.text).is_some()
+            || matches!(token.text.as_str(), ":" | "{" | "}" | "(" | ")");
+        if reserved {
+            return Err(token.error(format!("`{}` can not be used as a name", token.text)));
+        }
+        Ok(token)
+    }
+
+    /// tokens up to the matching `}`, the opening `{` is expected next
+    fn braced(&mut self) -> Result<Vec<Token>, AssemblerError> {
+        self.expect("{")?;
+
+        let mut depth = 1;
+        let mut body = vec![];
+        loop {
+            let token = self.next()?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(body);
+                    }
+                }
+                _ => {}
+            }
+            body.push(token);
+        }
+    }
+
+    fn statement(&mut self, token: Token) -> Result<(), AssemblerError> {
+        match token.text.as_str() {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.contains_key(&name.text) {
+                    return Err(name.error(format!("label `{}` is already defined", name.text)));
+                }
+                self.labels.insert(name.text, self.here as u16);
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.next()?;
+                let value = match self.constant(&value) {
+                    Some(value) => value as f64,
+                    None => self
+                        .labels
+                        .get(&value.text)
+                        .map(|&v| v as f64)
+                        .ok_or_else(|| {
+                            value.error(format!("expected a number but found `{}`", value.text))
+                        })?,
+                };
+                self.constants.insert(name.text, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name.text, register);
+            }
+            ":macro" => {
+                let name = self.name()?;
+                let mut arguments = vec![];
+                while !self.peek_is("{") {
+                    arguments.push(self.name()?.text);
+                }
+                let body = self.braced()?;
+                self.macros.insert(name.text, Macro { arguments, body });
+            }
+            ":calc" => {
+                let name = self.name()?;
+                let expression = self.braced()?;
+                let value = self.calculate(&token, expression)?;
+                self.constants.insert(name.text, value);
+            }
+            ":byte" => {
+                if self.peek_is("{") {
+                    let expression = self.braced()?;
+                    let value = self.calculate(&token, expression)?.floor() as i64;
+                    let value = self.check_range(&token, value, -128, 255)?;
+                    self.emit_u8(value as u8, &token)?;
+                } else {
+                    let value = self.byte()?;
+                    self.emit_u8(value, &token)?;
+                }
+            }
+            ":org" => {
+                let target = self.next()?;
+                let value = match self.constant(&target) {
+                    Some(value) => value,
+                    None if target.text == "{" => {
+                        self.tokens.push_front(target.clone());
+                        let expression = self.braced()?;
+                        self.calculate(&token, expression)?.floor() as i64
+                    }
+                    None => {
+                        return Err(target
+                            .error(format!("expected an address but found `{}`", target.text)))
+                    }
+                };
+                self.here =
+                    self.check_range(&target, value, PROGRAM_START as i64, MEMORY_SIZE as i64 - 1)?
+                        as usize;
+            }
+            ":call" => self.emit_address(0x2000, &token)?,
+            ":unpack" => {
+                let nibble = self.nibble()?;
+                let label = self.next()?;
+                let value = match self.constant(&label) {
+                    Some(value) => self.check_range(&label, value, 0, 0xFFFF)? as u16,
+                    None => {
+                        self.fixups.push(Fixup {
+                            address: self.here,
+                            kind: FixupKind::UnpackHigh { nibble },
+                            label: label.clone(),
+                        });
+                        self.fixups.push(Fixup {
+                            address: self.here + 2,
+                            kind: FixupKind::UnpackLow,
+                            label: label.clone(),
+                        });
+                        0
+                    }
+                };
+                let high = (nibble as u16) << 4 | (value >> 8) & 0xF;
+                self.emit_u16(0x6000 | high, &token)?;
+                self.emit_u16(0x6100 | (value & 0xFF), &token)?;
+            }
+            ";" | "return" => self.emit_u16(0x00EE, &token)?,
+            "clear" => self.emit_u16(0x00E0, &token)?,
+            "exit" => self.emit_u16(0x00FD, &token)?,
+            "lores" => self.emit_u16(0x00FE, &token)?,
+            "hires" => self.emit_u16(0x00FF, &token)?,
+            "scroll-right" => self.emit_u16(0x00FB, &token)?,
+            "scroll-left" => self.emit_u16(0x00FC, &token)?,
+            "audio" => self.emit_u16(0xF002, &token)?,
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit_u16(0x00C0 | n as u16, &token)?;
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit_u16(0x00D0 | n as u16, &token)?;
+            }
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit_u16(0xF001 | (n as u16) << 8, &token)?;
+            }
+            "bcd" => self.emit_x(0xF033, &token)?,
+            "saveflags" => self.emit_x(0xF075, &token)?,
+            "loadflags" => self.emit_x(0xF085, &token)?,
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                if self.peek_is("-") {
+                    self.next()?;
+                    let y = self.register()? as u16;
+                    let opcode = if token.text == "save" { 0x5002 } else { 0x5003 };
+                    self.emit_u16(opcode | x << 8 | y << 4, &token)?;
+                } else {
+                    let opcode = if token.text == "save" { 0xF055 } else { 0xF065 };
+                    self.emit_u16(opcode | x << 8, &token)?;
+                }
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble()? as u16;
+                self.emit_u16(0xD000 | x << 8 | y << 4 | n, &token)?;
+            }
+            "jump" => self.emit_address(0x1000, &token)?,
+            "jump0" => self.emit_address(0xB000, &token)?,
+            "i" => self.index(token)?,
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let opcode = match token.text.as_str() {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.emit_x(opcode, &token)?;
+            }
+            "if" => {
+                let condition = self.condition()?;
+                let keyword = self.next()?;
+                match keyword.text.as_str() {
+                    "then" => self.then(condition, &token)?,
+                    "begin" => {
+                        self.then(condition.negate(), &token)?;
+                        let jump = self.here;
+                        self.emit_u16(0x1000, &token)?;
+                        self.blocks.push(Block::If { token, jump });
+                    }
+                    _ => {
+                        return Err(keyword.error(format!(
+                            "expected `then` or `begin` but found `{}`",
+                            keyword.text
+                        )))
+                    }
+                }
+            }
+            "else" => {
+                let Some(Block::If { token: block, jump }) = self.blocks.pop() else {
+                    return Err(token.error("`else` without `if ... begin`"));
+                };
+                let else_jump = self.here;
+                self.emit_u16(0x1000, &token)?;
+                self.patch_jump(jump, &token)?;
+                self.blocks.push(Block::Else {
+                    token: block,
+                    jump: else_jump,
+                });
+            }
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) | Some(Block::Else { jump, .. }) => {
+                    self.patch_jump(jump, &token)?
+                }
+                _ => return Err(token.error("`end` without `if ... begin`")),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                token,
+                start: self.here,
+                breaks: vec![],
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                self.then(condition.negate(), &token)?;
+                let jump = self.here;
+                self.emit_u16(0x1000, &token)?;
+
+                let Some(Block::Loop { breaks, .. }) = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|block| matches!(block, Block::Loop { .. }))
+                else {
+                    return Err(token.error("`while` outside of a `loop`"));
+                };
+                breaks.push(jump);
+            }
+            "again" => {
+                let Some(Block::Loop { start, breaks, .. }) = self.blocks.pop() else {
+                    return Err(token.error("`again` without `loop`"));
+                };
+                if start > 0xFFF {
+                    return Err(token.error("loop starts out of reach of a jump"));
+                }
+                self.emit_u16(0x1000 | start as u16, &token)?;
+                for jump in breaks {
+                    self.patch_jump(jump, &token)?;
+                }
+            }
+            _ => {
+                if let Some(x) = self.register_of(&token) {
+                    return self.assignment(x, &token);
+                }
+
+                if let Some(value) = parse_number(&token.text) {
+                    let value = self.check_range(&token, value, -128, 255)?;
+                    return self.emit_u8(value as u8, &token);
+                }
+
+                if let Some(definition) = self.macros.get(&token.text).cloned() {
+                    return self.expand(&token, definition);
+                }
+
+                if token.text.starts_with(':') || self.constants.contains_key(&token.text) {
+                    return Err(token.error(format!("unexpected `{}`", token.text)));
+                }
+
+                // every other name calls a subroutine
+                self.emit_jump(0x2000, &token)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_x(&mut self, opcode: u16, token: &Token) -> Result<(), AssemblerError> {
+        let x = self.register()? as u16;
+        self.emit_u16(opcode | x << 8, token)
+    }
+
+    /// points the jump at `address` to the current address
+    fn patch_jump(&mut self, address: usize, token: &Token) -> Result<(), AssemblerError> {
+        if self.here > 0xFFF {
+            return Err(token.error("block ends out of reach of a jump"));
+        }
+        self.memory[address] = 0x10 | (self.here >> 8) as u8;
+        self.memory[address + 1] = self.here as u8;
+        Ok(())
+    }
+
+    fn expand(&mut self, token: &Token, definition: Macro) -> Result<(), AssemblerError> {
+        if token.macros.contains(&token.text) {
+            return Err(token.error(format!("macro `{}` expands itself", token.text)));
+        }
+        let mut macros = token.macros.clone();
+        macros.push(token.text.clone());
+
+        let mut arguments = HashMap::new();
+        for name in &definition.arguments {
+            arguments.insert(name.clone(), self.next()?);
+        }
+
+        for token in definition.body.into_iter().rev() {
+            let token = match arguments.get(&token.text) {
+                Some(argument) => argument.clone(),
+                None => token,
+            };
+            let token = Token {
+                macros: macros.clone(),
+                ..token
+            };
+            self.tokens.push_front(token);
+        }
+        Ok(())
+    }
+
+    fn index(&mut self, token: Token) -> Result<(), AssemblerError> {
+        let operator = self.next()?;
+        match operator.text.as_str() {
+            ":=" => {
+                if self.peek_is("long") {
+                    self.next()?;
+                    self.emit_u16(0xF000, &token)?;
+
+                    let target = self.next()?;
+                    let value = match self.constant(&target) {
+                        Some(value) => self.check_range(&target, value, 0, 0xFFFF)? as u16,
+                        None => {
+                            self.fixups.push(Fixup {
+                                address: self.here,
+                                kind: FixupKind::LongAddress,
+                                label: target.clone(),
+                            });
+                            self.labels.get(&target.text).copied().unwrap_or(0)
+                        }
+                    };
+                    let [high, low] = value.to_be_bytes();
+                    self.emit_u8(high, &target)?;
+                    self.emit_u8(low, &target)
+                } else if self.peek_is("hex") {
+                    self.next()?;
+                    self.emit_x(0xF029, &token)
+                } else if self.peek_is("bighex") {
+                    self.next()?;
+                    self.emit_x(0xF030, &token)
+                } else {
+                    self.emit_address(0xA000, &token)
+                }
+            }
+            "+=" => self.emit_x(0xF01E, &token),
+            _ => Err(operator.error(format!(
+                "expected `:=` or `+=` but found `{}`",
+                operator.text
+            ))),
+        }
+    }
+
+    fn assignment(&mut self, x: u8, token: &Token) -> Result<(), AssemblerError> {
+        let x = x as u16;
+        let operator = self.next()?;
+
+        let opcode = match operator.text.as_str() {
+            ":=" if self.peek_is("random") => {
+                self.next()?;
+                0xC000 | x << 8 | self.byte()? as u16
+            }
+            ":=" if self.peek_is("key") => {
+                self.next()?;
+                0xF00A | x << 8
+            }
+            ":=" if self.peek_is("delay") => {
+                self.next()?;
+                0xF007 | x << 8
+            }
+            ":=" | "+=" | "-=" => match (operator.text.as_str(), self.operand()?) {
+                (":=", Operand::Register(y)) => 0x8000 | x << 8 | (y as u16) << 4,
+                (":=", Operand::Byte(byte)) => 0x6000 | x << 8 | byte as u16,
+                ("+=", Operand::Register(y)) => 0x8004 | x << 8 | (y as u16) << 4,
+                ("+=", Operand::Byte(byte)) => 0x7000 | x << 8 | byte as u16,
+                (_, Operand::Register(y)) => 0x8005 | x << 8 | (y as u16) << 4,
+                (_, Operand::Byte(byte)) => 0x7000 | x << 8 | byte.wrapping_neg() as u16,
+            },
+            "|=" | "&=" | "^=" | ">>=" | "=-" | "<<=" => {
+                let y = self.register()? as u16;
+                let operation = match operator.text.as_str() {
+                    "|=" => 0x1,
+                    "&=" => 0x2,
+                    "^=" => 0x3,
+                    ">>=" => 0x6,
+                    "=-" => 0x7,
+                    _ => 0xE,
+                };
+                0x8000 | x << 8 | y << 4 | operation
+            }
+            _ => {
+                return Err(operator.error(format!(
+                    "expected an assignment but found `{}`",
+                    operator.text
+                )))
+            }
+        };
+
+        self.emit_u16(opcode, token)
+    }
+
+    fn condition(&mut self) -> Result<Condition, AssemblerError> {
+        let x = self.register()?;
+        let operator = self.next()?;
+
+        let comparison = match operator.text.as_str() {
+            "key" => return Ok(Condition::Key(x)),
+            "-key" => return Ok(Condition::NotKey(x)),
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            "<=" => Comparison::LessEqual,
+            ">=" => Comparison::GreaterEqual,
+            _ => {
+                return Err(operator.error(format!(
+                    "expected a comparison but found `{}`",
+                    operator.text
+                )))
+            }
+        };
+
+        Ok(Condition::Compare(x, comparison, self.operand()?))
+    }
+
+    /// emits instructions that skip the next one unless `condition` holds
+    ///
+    /// ordering comparisons go through VF
+    fn then(&mut self, condition: Condition, token: &Token) -> Result<(), AssemblerError> {
+        let (x, comparison, operand) = match condition {
+            Condition::Key(x) => return self.emit_u16(0xE0A1 | (x as u16) << 8, token),
+            Condition::NotKey(x) => return self.emit_u16(0xE09E | (x as u16) << 8, token),
+            Condition::Compare(x, comparison, operand) => (x as u16, comparison, operand),
+        };
+
+        let opcode = match (comparison, operand) {
+            (Comparison::Equal, Operand::Register(y)) => 0x9000 | x << 8 | (y as u16) << 4,
+            (Comparison::Equal, Operand::Byte(byte)) => 0x4000 | x << 8 | byte as u16,
+            (Comparison::NotEqual, Operand::Register(y)) => 0x5000 | x << 8 | (y as u16) << 4,
+            (Comparison::NotEqual, Operand::Byte(byte)) => 0x3000 | x << 8 | byte as u16,
+            _ => {
+                // VF ends up as 1 when the left side is at least the right side
+                let swapped = matches!(comparison, Comparison::Greater | Comparison::LessEqual);
+                match (operand, swapped) {
+                    (Operand::Register(y), false) => {
+                        self.emit_u16(0x8F00 | x << 4, token)?;
+                        self.emit_u16(0x8F05 | (y as u16) << 4, token)?;
+                    }
+                    (Operand::Register(y), true) => {
+                        self.emit_u16(0x8F00 | (y as u16) << 4, token)?;
+                        self.emit_u16(0x8F05 | x << 4, token)?;
+                    }
+                    (Operand::Byte(byte), false) => {
+                        self.emit_u16(0x6F00 | byte as u16, token)?;
+                        self.emit_u16(0x8F07 | x << 4, token)?;
+                    }
+                    (Operand::Byte(byte), true) => {
+                        self.emit_u16(0x6F00 | byte as u16, token)?;
+                        self.emit_u16(0x8F05 | x << 4, token)?;
+                    }
+                }
+
+                match comparison {
+                    Comparison::Less | Comparison::Greater => 0x4F00,
+                    _ => 0x3F00,
+                }
+            }
+        };
+
+        self.emit_u16(opcode, token)
+    }
+
+    /// evaluates a `:calc` expression, operators have no precedence and are applied right to left
+    fn calculate(&self, token: &Token, expression: Vec<Token>) -> Result<f64, AssemblerError> {
+        let mut tokens: VecDeque<Token> = expression.into();
+        let value = self.expression(token, &mut tokens)?;
+        match tokens.front() {
+            Some(extra) => Err(extra.error(format!("unexpected `{}`", extra.text))),
+            None => Ok(value),
+        }
+    }
+
+    fn expression(
+        &self,
+        token: &Token,
+        tokens: &mut VecDeque<Token>,
+    ) -> Result<f64, AssemblerError> {
+        let left = self.term(token, tokens)?;
+
+        let Some(operator) = tokens.front().cloned() else {
+            return Ok(left);
+        };
+        let operation: fn(f64, f64) -> Option<f64> = match operator.text.as_str() {
+            "+" => |a, b| Some(a + b),
+            "-" => |a, b| Some(a - b),
+            "*" => |a, b| Some(a * b),
+            "/" => |a, b| Some(a / b),
+            "%" => |a, b| Some(a % b),
+            "&" => |a, b| Some(((a as i64) & (b as i64)) as f64),
+            "|" => |a, b| Some(((a as i64) | (b as i64)) as f64),
+            "^" => |a, b| Some(((a as i64) ^ (b as i64)) as f64),
+            "<<" => |a, b| shift(a, b, i64::checked_shl),
+            ">>" => |a, b| shift(a, b, i64::checked_shr),
+            "pow" => |a, b| Some(f64::powf(a, b)),
+            "min" => |a, b| Some(f64::min(a, b)),
+            "max" => |a, b| Some(f64::max(a, b)),
+            "<" => |a, b| Some((a < b) as u8 as f64),
+            ">" => |a, b| Some((a > b) as u8 as f64),
+            "<=" => |a, b| Some((a <= b) as u8 as f64),
+            ">=" => |a, b| Some((a >= b) as u8 as f64),
+            "==" => |a, b| Some((a == b) as u8 as f64),
+            "!=" => |a, b| Some((a != b) as u8 as f64),
+            ")" => return Ok(left),
+            _ => return Err(operator.error(format!("unknown operator `{}`", operator.text))),
+        };
+        tokens.pop_front();
+
+        let right = self.expression(token, tokens)?;
+        operation(left, right).ok_or_else(|| {
+            operator.error(format!("`{}` by {} is out of range", operator.text, right))
+        })
+    }
+
+    fn term(&self, token: &Token, tokens: &mut VecDeque<Token>) -> Result<f64, AssemblerError> {
+        let term = tokens
+            .pop_front()
+            .ok_or_else(|| token.error("incomplete expression"))?;
+
+        let unary: fn(f64) -> f64 = match term.text.as_str() {
+            "(" => {
+                let value = self.expression(token, tokens)?;
+                match tokens.pop_front() {
+                    Some(close) if close.text == ")" => return Ok(value),
+                    _ => return Err(term.error("missing `)`")),
+                }
+            }
+            "-" => |a| -a,
+            "~" => |a| !(a as i64) as f64,
+            "!" => |a| (a == 0.0) as u8 as f64,
+            "abs" => f64::abs,
+            "sqrt" => f64::sqrt,
+            "floor" => f64::floor,
+            "ceil" => f64::ceil,
+            "sin" => f64::sin,
+            "cos" => f64::cos,
+            "@" => {
+                let address = self.term(token, tokens)? as usize;
+                return Ok(self.memory.get(address).copied().unwrap_or(0) as f64);
+            }
+            "HERE" => return Ok(self.here as f64),
+            "PI" => return Ok(std::f64::consts::PI),
+            "E" => return Ok(std::f64::consts::E),
+            text => {
+                if let Some(value) = parse_number(text) {
+                    return Ok(value as f64);
+                }
+                if let Some(&value) = self.constants.get(text) {
+                    return Ok(value);
+                }
+                if let Some(&value) = self.labels.get(text) {
+                    return Ok(value as f64);
+                }
+                return Err(term.error(format!("unknown name `{}`", text)));
+            }
+        };
+
+        Ok(unary(self.term(token, tokens)?))
+    }
+}
+
+/// shifts `value` by `amount` bits, `None` if the amount is negative or not less than 64
+fn shift(value: f64, amount: f64, shift: fn(i64, u32) -> Option<i64>) -> Option<f64> {
+    let amount = u32::try_from(amount as i64).ok()?;
+    shift(value as i64, amount).map(|value| value as f64)
+}
@@ -0,0 +1,57 @@
+use crate::state::{StateError, StateReader, StateWriter};
+use serde::Serialize;
+
+/// XO-CHIP audio state, other platforms simply play the default pattern
+#[derive(Debug, Clone, Serialize)]
+pub struct Audio {
+    /// 128 one bit samples, played from the most significant bit of the first byte
+    pattern: [u8; 16],
+    pitch: u8,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio {
+            pattern: [
+                0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF,
+                0x00, 0xFF,
+            ],
+            pitch: 64,
+        }
+    }
+}
+
+impl Audio {
+    pub fn pattern(&self) -> &[u8; 16] {
+        &self.pattern
+    }
+
+    pub fn set_pattern(&mut self, pattern: [u8; 16]) {
+        self.pattern = pattern;
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.bytes(&self.pattern);
+        writer.u8(self.pitch);
+    }
+
+    pub(crate) fn load_state(reader: &mut StateReader) -> Result<Audio, StateError> {
+        Ok(Audio {
+            pattern: reader.array("audio pattern")?,
+            pitch: reader.u8()?,
+        })
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+    }
+
+    /// samples per second at which the pattern is played
+    pub fn sample_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+}
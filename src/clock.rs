@@ -1,28 +1,71 @@
-#[cfg(feature = "cli")]
+use crate::state::{StateError, StateReader, StateWriter};
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "wasm"))]
 use std::time::SystemTime;
 
 #[cfg(feature = "wasm")]
 use web_time::SystemTime;
 
+/// how the clock decides that a 60 Hz frame has passed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeMode {
+    /// frames follow the host's clock
+    WallClock,
+    /// a frame passes every `instructions_per_frame` instructions, independent of the host
+    Virtual,
+}
+
 #[derive(Debug)]
 pub struct Clock {
     delay_timer: u8,
     sound_timer: u8,
 
+    mode: TimeMode,
+    instructions_per_frame: u32,
     last_tick: u128,
+    /// instructions run since the last virtual frame
+    instructions: u32,
 }
 
-impl Default for Clock {
-    fn default() -> Self {
+impl Clock {
+    pub fn new(mode: TimeMode, instructions_per_frame: u32) -> Clock {
         Clock {
             delay_timer: 0,
             sound_timer: 0,
+            mode,
+            instructions_per_frame: instructions_per_frame.max(1),
             last_tick: Self::get_current_millis(),
+            instructions: 0,
         }
     }
-}
 
-impl Clock {
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.u8(self.delay_timer);
+        writer.u8(self.sound_timer);
+        writer.u8(match self.mode {
+            TimeMode::WallClock => 0,
+            TimeMode::Virtual => 1,
+        });
+        writer.u32(self.instructions_per_frame);
+        writer.u32(self.instructions);
+    }
+
+    pub(crate) fn load_state(reader: &mut StateReader) -> Result<Clock, StateError> {
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let mode = match reader.u8()? {
+            0 => TimeMode::WallClock,
+            1 => TimeMode::Virtual,
+            _ => return Err(StateError::Invalid { field: "time mode" }),
+        };
+
+        let mut clock = Clock::new(mode, reader.u32()?);
+        clock.delay_timer = delay_timer;
+        clock.sound_timer = sound_timer;
+        clock.instructions = reader.u32()?;
+        Ok(clock)
+    }
+
     fn get_current_millis() -> u128 {
         SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -30,19 +73,56 @@ impl Clock {
             .as_millis()
     }
 
-    pub fn tick(&mut self) {
-        let current = Self::get_current_millis();
-        if (current - self.last_tick) > 1000 / 60 {
-            self.last_tick += 1000 / 60;
+    pub fn mode(&self) -> TimeMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TimeMode) {
+        self.mode = mode;
+        self.last_tick = Self::get_current_millis();
+        self.instructions = 0;
+    }
+
+    pub fn instructions_per_frame(&self) -> u32 {
+        self.instructions_per_frame
+    }
 
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
+    pub fn set_instructions_per_frame(&mut self, instructions_per_frame: u32) {
+        self.instructions_per_frame = instructions_per_frame.max(1);
+    }
+
+    /// called after every instruction, returns whether a new 60 Hz frame started
+    pub fn tick(&mut self) -> bool {
+        match self.mode {
+            TimeMode::WallClock => {
+                let current = Self::get_current_millis();
+                if (current - self.last_tick) > 1000 / 60 {
+                    self.last_tick += 1000 / 60;
+                    self.frame();
+                    return true;
+                }
             }
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
-                println!("PEEP");
+            TimeMode::Virtual => {
+                self.instructions += 1;
+                if self.instructions >= self.instructions_per_frame {
+                    self.frame();
+                    return true;
+                }
             }
         }
+
+        false
+    }
+
+    /// advances the timers by one frame and starts counting the instructions of the next one
+    pub fn frame(&mut self) {
+        self.instructions = 0;
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
     }
 
     pub fn delay_timer(&self) -> u8 {
@@ -53,6 +133,10 @@ impl Clock {
         self.delay_timer = value;
     }
 
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
     pub fn set_sound_timer(&mut self, value: u8) {
         self.sound_timer = value;
     }
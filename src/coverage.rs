@@ -0,0 +1,277 @@
+use crate::disassembler::{disassemble_with_coverage, LineKind, PROGRAM_START};
+use crate::inspector::Inspector;
+use crate::instruction::Instruction;
+use crate::memory::Access;
+use crate::{Emulator, Platform};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::ops::{BitOr, BitOrAssign};
+use std::str::FromStr;
+
+/// first line of every coverage map, explains the flags
+pub const MAP_HEADER: &str =
+    "# address: usage of 16 bytes, 01 executed 02 instruction start 04 sprite 08 read 10 written";
+
+/// bytes written per line of a coverage map
+const BYTES_PER_LINE: usize = 16;
+
+/// memory size of the largest platform, nothing can be used past it
+const MAX_MEMORY_SIZE: usize = 0x10000;
+
+/// how a byte of memory was used, flags can be combined with `|`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Usage(u8);
+
+impl Usage {
+    pub const NONE: Usage = Usage(0);
+    /// part of an instruction that ran
+    pub const EXECUTED: Usage = Usage(0x01);
+    /// first byte of an instruction that ran
+    pub const INSTRUCTION: Usage = Usage(0x02);
+    /// read by `DXYN`
+    pub const SPRITE: Usage = Usage(0x04);
+    /// read by `FX65`, `5XY3` or `F002`
+    pub const READ: Usage = Usage(0x08);
+    /// written by `FX55`, `FX33` or `5XY2`
+    pub const WRITTEN: Usage = Usage(0x10);
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u8) -> Usage {
+        Usage(bits & 0x1F)
+    }
+
+    /// whether all flags of `other` are set
+    pub fn contains(self, other: Usage) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// whether the byte was read or written but never ran
+    pub fn is_data(self) -> bool {
+        self != Usage::NONE && !self.contains(Usage::EXECUTED)
+    }
+}
+
+impl BitOr for Usage {
+    type Output = Usage;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Usage(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Usage {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// records how every byte of memory was used while the emulator ran
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    usage: Vec<Usage>,
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage::default()
+    }
+
+    pub fn usage(&self, address: usize) -> Usage {
+        self.usage.get(address).copied().unwrap_or_default()
+    }
+
+    /// adds `usage` to `length` bytes starting at `address`, ignored if they reach past any memory
+    pub fn mark(&mut self, address: usize, length: usize, usage: Usage) {
+        let Some(end) = address
+            .checked_add(length)
+            .filter(|&end| end <= MAX_MEMORY_SIZE)
+        else {
+            return;
+        };
+
+        if self.usage.len() < end {
+            self.usage.resize(end, Usage::NONE);
+        }
+        for byte in &mut self.usage[address..end] {
+            *byte |= usage;
+        }
+    }
+
+    /// adds everything recorded in `other`, e.g. to combine several runs
+    pub fn merge(&mut self, other: &Coverage) {
+        for (address, &usage) in other.usage.iter().enumerate() {
+            if usage != Usage::NONE {
+                self.mark(address, 1, usage);
+            }
+        }
+    }
+
+    /// addresses of every instruction that ran
+    pub fn instructions(&self) -> BTreeSet<u16> {
+        self.addresses(|usage| usage.contains(Usage::INSTRUCTION))
+    }
+
+    /// addresses of every byte that was read or written but never ran
+    pub fn data(&self) -> BTreeSet<u16> {
+        self.addresses(Usage::is_data)
+    }
+
+    fn addresses(&self, filter: impl Fn(Usage) -> bool) -> BTreeSet<u16> {
+        self.usage
+            .iter()
+            .enumerate()
+            .filter(|(_, &usage)| filter(usage))
+            .map(|(address, _)| address as u16)
+            .collect()
+    }
+
+    /// how much of `rom` was used, counting code the way the disassembler finds it
+    pub fn summary(&self, rom: &[u8], platform: Platform) -> CoverageSummary {
+        let start = PROGRAM_START as usize;
+        let rom_usage = (start..start + rom.len())
+            .map(|address| self.usage(address))
+            .collect::<Vec<_>>();
+        let count = |usage: Usage| rom_usage.iter().filter(|byte| byte.contains(usage)).count();
+
+        let disassembly = disassemble_with_coverage(rom, platform, self);
+        let code = disassembly
+            .lines
+            .iter()
+            .filter(|line| matches!(line.kind, LineKind::Code { .. }))
+            .map(|line| line.bytes.len())
+            .sum();
+
+        CoverageSummary {
+            rom: rom.len(),
+            used: rom_usage
+                .iter()
+                .filter(|&&usage| usage != Usage::NONE)
+                .count(),
+            code,
+            executed: count(Usage::EXECUTED),
+            sprite: count(Usage::SPRITE),
+            read: count(Usage::READ),
+            written: count(Usage::WRITTEN),
+        }
+    }
+
+    /// writes the usage of every line of memory that was used, see [MAP_HEADER]
+    pub fn write_map(&self, output: &mut impl Write) -> std::io::Result<()> {
+        writeln!(output, "{}", MAP_HEADER)?;
+        for (line, usage) in self.usage.chunks(BYTES_PER_LINE).enumerate() {
+            if usage.iter().all(|&usage| usage == Usage::NONE) {
+                continue;
+            }
+
+            write!(output, "{:04X}:", line * BYTES_PER_LINE)?;
+            for usage in usage {
+                write!(output, " {:02X}", usage.bits())?;
+            }
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Coverage {
+    type Err = String;
+
+    /// parses a map written by [Coverage::write_map]
+    fn from_str(map: &str) -> Result<Self, Self::Err> {
+        let mut coverage = Coverage::new();
+        for (index, line) in map.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: String| format!("line {}: {}", index + 1, message);
+            let (address, usage) = line
+                .split_once(':')
+                .ok_or_else(|| error("expected an address followed by :".to_string()))?;
+            let address = usize::from_str_radix(address.trim(), 16)
+                .map_err(|_| error(format!("{:?} is not an address", address)))?;
+
+            for (offset, bits) in usage.split_whitespace().enumerate() {
+                let bits = u8::from_str_radix(bits, 16)
+                    .map_err(|_| error(format!("{:?} is not a hexadecimal byte", bits)))?;
+                let address = address
+                    .checked_add(offset)
+                    .filter(|&address| address < MAX_MEMORY_SIZE)
+                    .ok_or_else(|| {
+                        error(format!(
+                            "{:X} is past the end of memory",
+                            address.saturating_add(offset)
+                        ))
+                    })?;
+                coverage.mark(address, 1, Usage::from_bits(bits));
+            }
+        }
+        Ok(coverage)
+    }
+}
+
+impl Inspector for Coverage {
+    fn before_instruction(&mut self, emulator: &Emulator, instruction: Instruction) {
+        let pc = emulator.memory().read_pc() as usize;
+        self.mark(pc, 1, Usage::INSTRUCTION);
+        self.mark(pc, instruction.size() as usize, Usage::EXECUTED);
+
+        if let Some(access) = emulator.memory_access(instruction) {
+            let usage = match (instruction, access.access) {
+                (Instruction::Drw { .. }, _) => Usage::SPRITE,
+                (_, Access::Read) => Usage::READ,
+                (_, Access::Write) => Usage::WRITTEN,
+            };
+
+            // accesses past the end of memory fail and read or write nothing
+            let length = access
+                .length
+                .min(emulator.memory().size().saturating_sub(access.address));
+            self.mark(access.address, length, usage);
+        }
+    }
+}
+
+/// how much of a rom a run used, in bytes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CoverageSummary {
+    pub rom: usize,
+    /// bytes used in any way
+    pub used: usize,
+    /// bytes the disassembler considers code
+    pub code: usize,
+    pub executed: usize,
+    pub sprite: usize,
+    pub read: usize,
+    pub written: usize,
+}
+
+impl Display for CoverageSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let percent = |count: usize, of: usize| count as f64 * 100.0 / of.max(1) as f64;
+        writeln!(
+            f,
+            "used {} of {} rom bytes ({:.1}%)",
+            self.used,
+            self.rom,
+            percent(self.used, self.rom)
+        )?;
+        writeln!(
+            f,
+            "executed {} of {} code bytes ({:.1}%)",
+            self.executed,
+            self.code,
+            percent(self.executed, self.code)
+        )?;
+        write!(
+            f,
+            "{} bytes drawn as sprites, {} read, {} written",
+            self.sprite, self.read, self.written
+        )
+    }
+}
@@ -0,0 +1,856 @@
+use crate::assembler::assemble;
+use crate::debugger::{Condition, Debugger, Register, StopReason};
+use crate::disassembler::{disassemble, LabelKind, LineKind, PROGRAM_START};
+use crate::instruction::Instruction;
+use crate::io::terminal_io::FRAME;
+use crate::io::NoInput;
+use crate::{Emulator, Platform};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{stdin, stdout, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::Instant;
+
+/// the only thread a client gets to see
+const THREAD_ID: u64 = 1;
+
+/// variables references of the scopes
+const REGISTERS_REFERENCE: u64 = 1;
+const STACK_REFERENCE: u64 = 2;
+
+/// registers listed in the registers scope
+const REGISTERS: [Register; 21] = [
+    Register::V(0x0),
+    Register::V(0x1),
+    Register::V(0x2),
+    Register::V(0x3),
+    Register::V(0x4),
+    Register::V(0x5),
+    Register::V(0x6),
+    Register::V(0x7),
+    Register::V(0x8),
+    Register::V(0x9),
+    Register::V(0xA),
+    Register::V(0xB),
+    Register::V(0xC),
+    Register::V(0xD),
+    Register::V(0xE),
+    Register::V(0xF),
+    Register::I,
+    Register::Pc,
+    Register::Sp,
+    Register::Dt,
+    Register::St,
+];
+
+/// `chip-8 dap [--port <port>]` serves one debug session on stdio, or on a local TCP port
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let mut port = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let value = args.next().unwrap_or_default();
+                port = match value.parse::<u16>() {
+                    Ok(port) => Some(port),
+                    Err(err) => {
+                        eprintln!("invalid port {:?}: {}", value, err);
+                        return;
+                    }
+                };
+            }
+            _ => {
+                eprintln!("unknown argument {:?}", arg);
+                return;
+            }
+        }
+    }
+
+    let result = match port {
+        Some(port) => TcpListener::bind(("127.0.0.1", port)).and_then(|listener| {
+            eprintln!("listening on {}", listener.local_addr()?);
+            let (stream, _) = listener.accept()?;
+            serve(stream.try_clone()?, stream)
+        }),
+        None => serve(stdin(), stdout()),
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+    }
+}
+
+/// runs a debug session, reading requests from `input` and writing responses and events to `output`
+///
+/// a running program is run at 60 frames per second, requests are handled in between frames
+pub fn serve(input: impl Read + Send + 'static, output: impl Write) -> std::io::Result<()> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let mut input = BufReader::new(input);
+        while let Ok(Some(body)) = read_message(&mut input) {
+            // a broken message is no reason to end the session
+            let message = match serde_json::from_slice(&body) {
+                Ok(message) => message,
+                Err(err) => {
+                    eprintln!("skipping malformed message: {}", err);
+                    continue;
+                }
+            };
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session::new(output);
+    let mut next_frame = Instant::now();
+
+    while !session.done {
+        let message = match session.running {
+            Some(_) => {
+                let timeout = next_frame.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(timeout) {
+                    Ok(message) => Some(message),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            },
+        };
+
+        match message {
+            Some(message) => session.handle(message)?,
+            None => {
+                session.run_frame()?;
+                next_frame += FRAME;
+                // running behind, so don't try to catch up
+                let now = Instant::now();
+                if next_frame < now {
+                    next_frame = now;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// reads the body of a message with a `Content-Length` header, `None` once the input is closed
+fn read_message(input: &mut impl BufRead) -> std::io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+        if header.is_empty() && length.is_some() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// what a resumed program runs until
+#[derive(Debug, Copy, Clone)]
+enum Run {
+    Continue,
+    /// until the stack is back at this depth, to step over or out of a subroutine
+    Depth(usize),
+}
+
+/// the Octo source a program was assembled from
+#[derive(Debug)]
+struct Source {
+    path: String,
+    /// source line of every instruction
+    lines: BTreeMap<u16, usize>,
+}
+
+impl Source {
+    fn name(&self) -> String {
+        Path::new(&self.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    fn is(&self, path: &str) -> bool {
+        match (fs::canonicalize(&self.path), fs::canonicalize(path)) {
+            (Ok(own), Ok(other)) => own == other,
+            _ => self.path == path,
+        }
+    }
+
+    /// the first instruction on `line`, or on the next line with an instruction
+    fn address(&self, line: usize) -> Option<(u16, usize)> {
+        self.lines
+            .iter()
+            .filter(|(_, &instruction_line)| instruction_line >= line)
+            .min_by_key(|(&address, &instruction_line)| (instruction_line, address))
+            .map(|(&address, &line)| (address, line))
+    }
+
+    fn line(&self, address: u16) -> Option<usize> {
+        self.lines
+            .range(..=address)
+            .next_back()
+            .map(|(_, &line)| line)
+    }
+}
+
+struct Session<W: Write> {
+    output: W,
+    seq: u64,
+    events: Vec<Value>,
+    debugger: Option<Debugger>,
+    source: Option<Source>,
+    /// labels of the program, the stack trace names frames after them
+    labels: BTreeMap<u16, String>,
+    source_breakpoints: BTreeMap<u16, Option<Condition>>,
+    instruction_breakpoints: BTreeMap<u16, Option<Condition>>,
+    stop_on_entry: bool,
+    /// whether the client counts lines from 1 instead of 0
+    lines_start_at_1: bool,
+    running: Option<Run>,
+    done: bool,
+}
+
+impl<W: Write> Session<W> {
+    fn new(output: W) -> Session<W> {
+        Session {
+            output,
+            seq: 0,
+            events: vec![],
+            debugger: None,
+            source: None,
+            labels: BTreeMap::new(),
+            source_breakpoints: BTreeMap::new(),
+            instruction_breakpoints: BTreeMap::new(),
+            stop_on_entry: false,
+            lines_start_at_1: true,
+            running: None,
+            done: false,
+        }
+    }
+
+    fn send(&mut self, mut message: Value) -> std::io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        let body = message.to_string();
+        write!(
+            self.output,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        self.output.flush()
+    }
+
+    /// queues an event, it is sent after the response to the current request
+    fn event(&mut self, event: &str, body: Value) {
+        self.events.push(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    fn flush_events(&mut self) -> std::io::Result<()> {
+        for event in std::mem::take(&mut self.events) {
+            self.send(event)?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: Value) -> std::io::Result<()> {
+        if request["type"] != "request" {
+            return Ok(());
+        }
+
+        let command = request["command"].as_str().unwrap_or_default();
+        let arguments = &request["arguments"];
+        let result = match command {
+            "initialize" => self.initialize(arguments),
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => self.configuration_done(),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+                    { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+                ]
+            })),
+            "variables" => self.variables(arguments),
+            "setVariable" => self.set_variable(arguments),
+            "continue" => self.resume(Run::Continue),
+            "next" => self.next(),
+            "stepIn" => self.step_in(),
+            "stepOut" => self.step_out(),
+            "pause" => self.pause(),
+            "readMemory" => self.read_memory(arguments),
+            "disassemble" => self.disassemble(arguments),
+            "terminate" => {
+                self.event("terminated", json!({}));
+                Ok(json!({}))
+            }
+            "disconnect" => {
+                self.done = true;
+                Ok(json!({}))
+            }
+            _ => Err(format!("{} is not supported", command)),
+        };
+
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+
+        self.send(response)?;
+        self.flush_events()
+    }
+
+    fn debugger(&mut self) -> Result<&mut Debugger, String> {
+        self.debugger
+            .as_mut()
+            .ok_or("no program has been launched".to_string())
+    }
+
+    fn emulator(&self) -> Result<&Emulator, String> {
+        self.debugger
+            .as_ref()
+            .map(Debugger::emulator)
+            .ok_or("no program has been launched".to_string())
+    }
+
+    /// converts a line from the client to the 1 based lines of the assembler
+    fn server_line(&self, line: i64) -> usize {
+        match self.lines_start_at_1 {
+            true => line.max(1) as usize,
+            false => line.max(0) as usize + 1,
+        }
+    }
+
+    fn client_line(&self, line: usize) -> usize {
+        match self.lines_start_at_1 {
+            true => line,
+            false => line - 1,
+        }
+    }
+
+    fn initialize(&mut self, arguments: &Value) -> Result<Value, String> {
+        self.lines_start_at_1 = arguments["linesStartAt1"].as_bool().unwrap_or(true);
+
+        Ok(json!({
+            "supportsConfigurationDoneRequest": true,
+            "supportsConditionalBreakpoints": true,
+            "supportsInstructionBreakpoints": true,
+            "supportsReadMemoryRequest": true,
+            "supportsDisassembleRequest": true,
+            "supportsSetVariable": true,
+            "supportsTerminateRequest": true,
+        }))
+    }
+
+    /// loads `program`, Octo sources get assembled and make source breakpoints available
+    fn launch(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or("launch needs a program")?;
+        let platform = match arguments["platform"].as_str() {
+            Some(platform) => platform.parse()?,
+            None => Platform::SuperChip,
+        };
+
+        let (program, source) = if path.ends_with(".8o") {
+            let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+            let assembly = assemble(&text).map_err(|err| format!("{}:{}", path, err))?;
+            self.labels = assembly
+                .labels
+                .iter()
+                .map(|(name, &address)| (address, name.clone()))
+                .collect();
+            let source = Source {
+                path: path.to_string(),
+                lines: assembly.source_map,
+            };
+            (assembly.rom, Some(source))
+        } else {
+            let program = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+            let disassembly = disassemble(&program, platform);
+            self.labels = disassembly
+                .labels
+                .iter()
+                .filter(|(_, kind)| matches!(kind, LabelKind::Main | LabelKind::Subroutine))
+                .filter_map(|(&address, _)| Some((address, disassembly.label(address)?)))
+                .collect();
+            (program, None)
+        };
+
+        let mut emulator =
+            Emulator::new(program, path.to_string(), platform).map_err(|err| err.to_string())?;
+        if let Some(seed) = arguments["seed"].as_u64() {
+            emulator = emulator.with_seed(seed);
+        }
+        if let Some(speed) = arguments["speed"].as_u64() {
+            emulator = emulator.with_speed(speed as u32);
+        }
+
+        self.debugger = Some(Debugger::new(emulator));
+        self.source = source;
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+
+        // breakpoints can only be resolved once the program is known
+        self.event("initialized", json!({}));
+        Ok(json!({}))
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        self.debugger()?;
+        let path = arguments["source"]["path"].as_str().unwrap_or_default();
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        let mut breakpoints = BTreeMap::new();
+        let mut results = vec![];
+        for breakpoint in requested {
+            let line = self.server_line(breakpoint["line"].as_i64().unwrap_or_default());
+            let source = self.source.as_ref().filter(|source| source.is(path));
+
+            let result = match source.and_then(|source| source.address(line)) {
+                None => Err("no code on or after this line".to_string()),
+                Some((address, line)) => {
+                    parse_condition(&breakpoint["condition"]).map(|condition| {
+                        breakpoints.insert(address, condition);
+                        (address, line)
+                    })
+                }
+            };
+
+            results.push(match result {
+                Ok((address, line)) => json!({
+                    "verified": true,
+                    "line": self.client_line(line),
+                    "instructionReference": format!("{:#06x}", address),
+                }),
+                Err(message) => json!({ "verified": false, "message": message }),
+            });
+        }
+
+        self.source_breakpoints = breakpoints;
+        self.update_breakpoints();
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        self.debugger()?;
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        let mut breakpoints = BTreeMap::new();
+        let mut results = vec![];
+        for breakpoint in requested {
+            let result = parse_address(&breakpoint["instructionReference"]).and_then(|address| {
+                let offset = breakpoint["offset"].as_i64().unwrap_or_default();
+                let address = (address as i64 + offset) as u16;
+                let condition = parse_condition(&breakpoint["condition"])?;
+                breakpoints.insert(address, condition);
+                Ok(address)
+            });
+
+            results.push(match result {
+                Ok(address) => json!({
+                    "verified": true,
+                    "instructionReference": format!("{:#06x}", address),
+                }),
+                Err(message) => json!({ "verified": false, "message": message }),
+            });
+        }
+
+        self.instruction_breakpoints = breakpoints;
+        self.update_breakpoints();
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    /// hands the source and instruction breakpoints to the debugger, instruction breakpoints win
+    fn update_breakpoints(&mut self) {
+        let breakpoints = self
+            .source_breakpoints
+            .iter()
+            .chain(&self.instruction_breakpoints)
+            .map(|(&address, &condition)| (address, condition))
+            .collect::<BTreeMap<_, _>>();
+
+        let Some(debugger) = &mut self.debugger else {
+            return;
+        };
+        let existing = debugger
+            .breakpoints()
+            .map(|breakpoint| breakpoint.address)
+            .collect::<Vec<_>>();
+        for address in existing {
+            debugger.remove_breakpoint(address);
+        }
+        for (address, condition) in breakpoints {
+            debugger.add_breakpoint(address, condition);
+        }
+    }
+
+    fn configuration_done(&mut self) -> Result<Value, String> {
+        self.debugger()?;
+        match self.stop_on_entry {
+            true => self.event(
+                "stopped",
+                json!({ "reason": "entry", "threadId": THREAD_ID, "allThreadsStopped": true }),
+            ),
+            false => self.running = Some(Run::Continue),
+        }
+        Ok(json!({}))
+    }
+
+    fn stack_trace(&mut self) -> Result<Value, String> {
+        let memory = self.emulator()?.memory();
+
+        // every return address points right behind the call
+        let mut addresses = vec![memory.read_pc()];
+        addresses.extend(
+            memory
+                .stack()
+                .iter()
+                .rev()
+                .map(|address| address.wrapping_sub(2)),
+        );
+
+        let frames = addresses
+            .iter()
+            .enumerate()
+            .map(|(id, &address)| {
+                let name = self
+                    .labels
+                    .range(..=address)
+                    .next_back()
+                    .map_or(format!("{:#06x}", address), |(_, name)| name.clone());
+                let mut frame = json!({
+                    "id": id,
+                    "name": name,
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": format!("{:#06x}", address),
+                });
+
+                if let Some(source) = &self.source {
+                    if let Some(line) = source.line(address) {
+                        frame["line"] = json!(self.client_line(line));
+                        frame["column"] = json!(1);
+                        frame["source"] = json!({ "name": source.name(), "path": source.path });
+                    }
+                }
+                frame
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({ "stackFrames": frames, "totalFrames": addresses.len() }))
+    }
+
+    fn variables(&mut self, arguments: &Value) -> Result<Value, String> {
+        let emulator = self.emulator()?;
+
+        let variables = match arguments["variablesReference"].as_u64() {
+            Some(REGISTERS_REFERENCE) => REGISTERS
+                .iter()
+                .map(|register| {
+                    let value = register.read(emulator);
+                    let mut variable = json!({
+                        "name": register.to_string(),
+                        "value": format_register(*register, value),
+                        "variablesReference": 0,
+                    });
+                    if matches!(register, Register::I | Register::Pc) {
+                        variable["memoryReference"] = json!(format!("{:#06x}", value));
+                    }
+                    variable
+                })
+                .collect(),
+            Some(STACK_REFERENCE) => emulator
+                .memory()
+                .stack()
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, address)| {
+                    json!({
+                        "name": format!("[{}]", index),
+                        "value": format!("{:#06x}", address),
+                        "variablesReference": 0,
+                        "memoryReference": format!("{:#06x}", address),
+                    })
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn set_variable(&mut self, arguments: &Value) -> Result<Value, String> {
+        if arguments["variablesReference"].as_u64() != Some(REGISTERS_REFERENCE) {
+            return Err("only registers can be changed".to_string());
+        }
+
+        let register = arguments["name"]
+            .as_str()
+            .unwrap_or_default()
+            .parse::<Register>()?;
+        let value = parse_address(&arguments["value"])?;
+        register.write(self.debugger()?.emulator_mut(), value)?;
+
+        Ok(json!({ "value": format_register(register, value) }))
+    }
+
+    fn resume(&mut self, run: Run) -> Result<Value, String> {
+        self.debugger()?;
+        self.running = Some(run);
+        Ok(json!({ "allThreadsContinued": true }))
+    }
+
+    fn next(&mut self) -> Result<Value, String> {
+        let debugger = self.debugger()?;
+        match debugger.emulator().current_instruction() {
+            Ok(Instruction::Call(_)) => {
+                let depth = debugger.emulator().memory().stack().len();
+                self.resume(Run::Depth(depth))
+            }
+            _ => self.step_in(),
+        }
+    }
+
+    fn step_in(&mut self) -> Result<Value, String> {
+        let reason = self.debugger()?.step(&NoInput);
+        self.stopped(reason);
+        Ok(json!({}))
+    }
+
+    fn step_out(&mut self) -> Result<Value, String> {
+        match self.debugger()?.emulator().memory().stack().len() {
+            0 => self.step_in(),
+            depth => self.resume(Run::Depth(depth - 1)),
+        }
+    }
+
+    fn pause(&mut self) -> Result<Value, String> {
+        self.debugger()?;
+        self.running = None;
+        self.event(
+            "stopped",
+            json!({ "reason": "pause", "threadId": THREAD_ID, "allThreadsStopped": true }),
+        );
+        Ok(json!({}))
+    }
+
+    fn read_memory(&mut self, arguments: &Value) -> Result<Value, String> {
+        let memory = self.emulator()?.memory();
+        let address = parse_address(&arguments["memoryReference"])? as i64
+            + arguments["offset"].as_i64().unwrap_or_default();
+        let count = arguments["count"].as_u64().unwrap_or_default() as usize;
+
+        let readable = match usize::try_from(address) {
+            Ok(start) if start < memory.size() => count.min(memory.size() - start),
+            _ => 0,
+        };
+        let data = match readable {
+            0 => &[][..],
+            _ => memory
+                .read_slice(address as usize, readable)
+                .map_err(|err| err.to_string())?,
+        };
+
+        Ok(json!({
+            "address": format!("{:#06x}", address),
+            "data": STANDARD.encode(data),
+            "unreadableBytes": count - readable,
+        }))
+    }
+
+    /// disassembles the current memory, instructions outside of the program are marked invalid
+    fn disassemble(&mut self, arguments: &Value) -> Result<Value, String> {
+        let disassembly = self.debugger()?.disassemble();
+        let address = parse_address(&arguments["memoryReference"])? as i64
+            + arguments["offset"].as_i64().unwrap_or_default();
+        let instruction_offset = arguments["instructionOffset"].as_i64().unwrap_or_default();
+        let count = arguments["instructionCount"].as_i64().unwrap_or_default();
+
+        let lines = &disassembly.lines;
+        let start = PROGRAM_START as i64;
+        let end = lines
+            .last()
+            .map_or(start, |line| line.address as i64 + line.bytes.len() as i64);
+
+        // addresses outside of the disassembly count as two byte instructions
+        let index = if address < start {
+            (address - start).div_euclid(2)
+        } else if address >= end {
+            lines.len() as i64 + (address - end) / 2
+        } else {
+            lines.partition_point(|line| (line.address as i64) <= address) as i64 - 1
+        };
+
+        let instructions = (0..count)
+            .map(|offset| {
+                let position = index + instruction_offset + offset;
+                let Some(line) = usize::try_from(position)
+                    .ok()
+                    .and_then(|position| lines.get(position))
+                else {
+                    let address = match position < 0 {
+                        true => start + position * 2,
+                        false => end + (position - lines.len() as i64) * 2,
+                    };
+                    return json!({
+                        "address": format!("{:#06x}", address),
+                        "instruction": "",
+                        "presentationHint": "invalid",
+                    });
+                };
+
+                let bytes = line
+                    .bytes
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                let mut instruction = json!({
+                    "address": format!("{:#06x}", line.address),
+                    "instructionBytes": bytes,
+                    "instruction": disassembly.source(line),
+                });
+                if let Some(label) = disassembly.label(line.address) {
+                    instruction["symbol"] = json!(label);
+                }
+                if matches!(line.kind, LineKind::Data) {
+                    instruction["presentationHint"] = json!("data");
+                }
+                if let Some(source) = &self.source {
+                    if let Some(&line) = source.lines.get(&line.address) {
+                        instruction["line"] = json!(self.client_line(line));
+                        instruction["location"] =
+                            json!({ "name": source.name(), "path": source.path });
+                    }
+                }
+                instruction
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({ "instructions": instructions }))
+    }
+
+    fn run_frame(&mut self) -> std::io::Result<()> {
+        let (Some(run), Some(debugger)) = (self.running, &mut self.debugger) else {
+            self.running = None;
+            return Ok(());
+        };
+
+        let reason = match run {
+            Run::Continue => debugger.run_frame(&NoInput),
+            Run::Depth(depth) => debugger.run_to_depth(&NoInput, depth, debugger.frame() + 1),
+        };
+        if !matches!(reason, StopReason::Frame { .. }) {
+            self.stopped(reason);
+        }
+
+        self.flush_events()
+    }
+
+    /// pauses the program and tells the client why
+    fn stopped(&mut self, reason: StopReason) {
+        self.running = None;
+
+        let description = reason.to_string();
+        let reason = match reason {
+            StopReason::Step | StopReason::Frame { .. } => "step",
+            StopReason::Breakpoint { address }
+                if self.instruction_breakpoints.contains_key(&address) =>
+            {
+                "instruction breakpoint"
+            }
+            StopReason::Breakpoint { .. } => "breakpoint",
+            StopReason::Watchpoint { .. } | StopReason::RegisterChanged { .. } => "data breakpoint",
+            StopReason::Error(_) => "exception",
+            StopReason::Exited => {
+                self.event("exited", json!({ "exitCode": 0 }));
+                self.event("terminated", json!({}));
+                return;
+            }
+        };
+
+        self.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "text": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        );
+    }
+}
+
+fn format_register(register: Register, value: u16) -> String {
+    match register {
+        Register::V(_) | Register::Dt | Register::St => format!("{:#04x}", value),
+        Register::Sp => value.to_string(),
+        Register::I | Register::Pc => format!("{:#06x}", value),
+    }
+}
+
+/// parses a memory reference or value, hexadecimal with `0x` or decimal
+fn parse_address(value: &Value) -> Result<u16, String> {
+    if let Some(number) = value.as_u64() {
+        return u16::try_from(number).map_err(|_| format!("{} is out of range", number));
+    }
+
+    let text = value.as_str().unwrap_or_default().trim();
+    match text.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => text.parse(),
+    }
+    .map_err(|_| format!("{:?} is not an address", text))
+}
+
+fn parse_condition(value: &Value) -> Result<Option<Condition>, String> {
+    match value.as_str().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(condition) => condition.parse().map(Some),
+    }
+}
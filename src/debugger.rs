@@ -0,0 +1,520 @@
+use crate::disassembler::{disassemble_with_hints, Disassembly};
+use crate::error::EmulatorError;
+use crate::instruction::Instruction;
+use crate::io::IO;
+use crate::memory::Access;
+use crate::Emulator;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// frames `step_over` and `step_out` run at most before giving up on a subroutine that never returns
+pub const STEP_FRAME_LIMIT: u64 = 600;
+
+/// a piece of machine state conditions and watchpoints can look at
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    /// depth of the call stack
+    Sp,
+    Dt,
+    St,
+}
+
+impl Register {
+    pub fn read(&self, emulator: &Emulator) -> u16 {
+        let memory = emulator.memory();
+        match self {
+            Register::V(x) => memory.read_register(*x as usize) as u16,
+            Register::I => memory.read_index_register(),
+            Register::Pc => memory.read_pc(),
+            Register::Sp => memory.stack().len() as u16,
+            Register::Dt => emulator.clock().delay_timer() as u16,
+            Register::St => emulator.clock().sound_timer() as u16,
+        }
+    }
+
+    /// sets the register, the stack depth can't be written
+    pub fn write(&self, emulator: &mut Emulator, value: u16) -> Result<(), String> {
+        let byte = || u8::try_from(value).map_err(|_| format!("{} only holds a byte", self));
+        match self {
+            Register::V(x) => emulator.memory_mut().write_register(*x as usize, byte()?),
+            Register::I => emulator.memory_mut().write_index_register(value),
+            Register::Pc => emulator.memory_mut().write_pc(value),
+            Register::Sp => return Err("the stack depth can't be written".to_string()),
+            Register::Dt => emulator.clock_mut().set_delay_timer(byte()?),
+            Register::St => emulator.clock_mut().set_sound_timer(byte()?),
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let lower = value.to_lowercase();
+        match lower.as_str() {
+            "i" => Ok(Register::I),
+            "pc" => Ok(Register::Pc),
+            "sp" => Ok(Register::Sp),
+            "dt" => Ok(Register::Dt),
+            "st" => Ok(Register::St),
+            _ => lower
+                .strip_prefix('v')
+                .filter(|digit| digit.len() == 1)
+                .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+                .map(Register::V)
+                .ok_or(format!("unknown register {:?}", value)),
+        }
+    }
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Sp => write!(f, "SP"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Register(Register),
+    Value(u16),
+}
+
+impl Operand {
+    fn read(&self, emulator: &Emulator) -> u16 {
+        match self {
+            Operand::Register(register) => register.read(emulator),
+            Operand::Value(value) => *value,
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let number = match value.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        };
+
+        match number {
+            Some(number) => Ok(Operand::Value(number)),
+            None => value.parse().map(Operand::Register),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "{}", register),
+            Operand::Value(value) => write!(f, "{:#04x}", value),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    /// operators, longer ones first so `<=` is not read as `<`
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessEqual),
+        (">=", Comparison::GreaterEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+}
+
+/// a comparison like `V3 == 0x10` that is checked before a breakpoint stops
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub left: Operand,
+    pub comparison: Comparison,
+    pub right: Operand,
+}
+
+impl Condition {
+    pub fn holds(&self, emulator: &Emulator) -> bool {
+        let left = self.left.read(emulator);
+        let right = self.right.read(emulator);
+        match self.comparison {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterEqual => left >= right,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (index, operator, comparison) = Comparison::OPERATORS
+            .iter()
+            .find_map(|(operator, comparison)| {
+                value
+                    .find(operator)
+                    .map(|index| (index, *operator, *comparison))
+            })
+            .ok_or(format!("{:?} has no comparison", value))?;
+
+        Ok(Condition {
+            left: value[..index].trim().parse()?,
+            comparison,
+            right: value[index + operator.len()..].trim().parse()?,
+        })
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let operator = Comparison::OPERATORS
+            .iter()
+            .find(|(_, comparison)| *comparison == self.comparison)
+            .map(|(operator, _)| *operator)
+            .unwrap_or_default();
+        write!(f, "{} {} {}", self.left, operator, self.right)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub condition: Option<Condition>,
+}
+
+/// which accesses a memory watchpoint stops on
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum Watch {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Watch {
+    fn matches(&self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (Watch::ReadWrite, _) | (Watch::Read, Access::Read) | (Watch::Write, Access::Write)
+        )
+    }
+}
+
+/// why the debugger handed control back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum StopReason {
+    /// a step, step over or step out completed
+    Step,
+    /// the program counter reached a breakpoint whose condition holds, nothing ran at it yet
+    Breakpoint {
+        address: u16,
+    },
+    /// the last instruction accessed a watched address
+    Watchpoint {
+        address: u16,
+        access: Access,
+    },
+    /// the last instruction changed a watched register
+    RegisterChanged {
+        register: Register,
+        old: u16,
+        new: u16,
+    },
+    /// the frame asked for by `run_to_frame` was reached
+    Frame {
+        frame: u64,
+    },
+    Exited,
+    Error(EmulatorError),
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Step => write!(f, "step"),
+            StopReason::Breakpoint { address } => write!(f, "breakpoint at {:#06x}", address),
+            StopReason::Watchpoint { address, access } => {
+                let access = match access {
+                    Access::Read => "read",
+                    Access::Write => "write",
+                };
+                write!(f, "{} of {:#06x}", access, address)
+            }
+            StopReason::RegisterChanged { register, old, new } => {
+                write!(f, "{} changed from {:#04x} to {:#04x}", register, old, new)
+            }
+            StopReason::Frame { frame } => write!(f, "frame {}", frame),
+            StopReason::Exited => write!(f, "program exited"),
+            StopReason::Error(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// what `run` executes until
+#[derive(Debug, Copy, Clone)]
+enum Goal {
+    Step,
+    /// until the stack is back at `depth`, or `frame` frames have completed
+    Depth {
+        depth: usize,
+        frame: u64,
+    },
+    Frame(u64),
+}
+
+/// runs an emulator instruction by instruction, stopping at breakpoints and watchpoints
+///
+/// frames are kept the same as with `Emulator::run_frame`, so a debugged program behaves identically
+#[derive(Debug)]
+pub struct Debugger {
+    emulator: Emulator,
+    breakpoints: BTreeMap<u16, Breakpoint>,
+    watchpoints: BTreeMap<u16, Watch>,
+    watched_registers: BTreeSet<Register>,
+    /// frames completed since the debugger was created
+    frame: u64,
+    /// instructions run in the current frame
+    instructions: u32,
+}
+
+impl Debugger {
+    pub fn new(emulator: Emulator) -> Debugger {
+        Debugger {
+            emulator,
+            breakpoints: BTreeMap::new(),
+            watchpoints: BTreeMap::new(),
+            watched_registers: BTreeSet::new(),
+            frame: 0,
+            instructions: 0,
+        }
+    }
+
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    pub fn emulator_mut(&mut self) -> &mut Emulator {
+        &mut self.emulator
+    }
+
+    pub fn into_emulator(self) -> Emulator {
+        self.emulator
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// disassembles the memory from the program start to the last non zero byte
+    ///
+    /// the program counter is always treated as code, so code that is only jumped to dynamically still shows up
+    pub fn disassemble(&self) -> Disassembly {
+        let hints = BTreeSet::from([self.emulator.memory().read_pc()]);
+        disassemble_with_hints(
+            self.emulator.program_memory(),
+            self.emulator.platform(),
+            &hints,
+        )
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16, condition: Option<Condition>) {
+        self.breakpoints
+            .insert(address, Breakpoint { address, condition });
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.values()
+    }
+
+    pub fn add_watchpoint(&mut self, address: u16, watch: Watch) {
+        self.watchpoints.insert(address, watch);
+    }
+
+    pub fn remove_watchpoint(&mut self, address: u16) -> bool {
+        self.watchpoints.remove(&address).is_some()
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = (u16, Watch)> + '_ {
+        self.watchpoints
+            .iter()
+            .map(|(&address, &watch)| (address, watch))
+    }
+
+    pub fn watch_register(&mut self, register: Register) {
+        self.watched_registers.insert(register);
+    }
+
+    pub fn unwatch_register(&mut self, register: Register) -> bool {
+        self.watched_registers.remove(&register)
+    }
+
+    pub fn watched_registers(&self) -> impl Iterator<Item = Register> + '_ {
+        self.watched_registers.iter().copied()
+    }
+
+    /// runs a single instruction
+    pub fn step(&mut self, io: &dyn IO) -> StopReason {
+        self.run(io, Goal::Step)
+    }
+
+    /// runs a single instruction, but runs a called subroutine until it returns
+    ///
+    /// stops after [STEP_FRAME_LIMIT] frames if the subroutine doesn't return by then
+    pub fn step_over(&mut self, io: &dyn IO) -> StopReason {
+        match self.emulator.current_instruction() {
+            Ok(Instruction::Call(_)) => {
+                let depth = self.emulator.memory().stack().len();
+                self.run_to_depth(io, depth, self.frame + STEP_FRAME_LIMIT)
+            }
+            _ => self.step(io),
+        }
+    }
+
+    /// runs until the current subroutine returns, a single step outside of subroutines
+    ///
+    /// stops after [STEP_FRAME_LIMIT] frames if the subroutine doesn't return by then
+    pub fn step_out(&mut self, io: &dyn IO) -> StopReason {
+        match self.emulator.memory().stack().len() {
+            0 => self.step(io),
+            depth => self.run_to_depth(io, depth - 1, self.frame + STEP_FRAME_LIMIT),
+        }
+    }
+
+    /// runs until the stack is back at `depth`, giving up once `frame` frames have completed
+    ///
+    /// lets frontends step over subroutines that never return without blocking
+    pub fn run_to_depth(&mut self, io: &dyn IO, depth: usize, frame: u64) -> StopReason {
+        self.run(io, Goal::Depth { depth, frame })
+    }
+
+    /// runs until `frame` frames have completed
+    pub fn run_to_frame(&mut self, io: &dyn IO, frame: u64) -> StopReason {
+        if self.frame >= frame {
+            return StopReason::Frame { frame: self.frame };
+        }
+        self.run(io, Goal::Frame(frame))
+    }
+
+    /// runs the rest of the current frame, meant to be called once per frame to run the program normally
+    pub fn run_frame(&mut self, io: &dyn IO) -> StopReason {
+        self.run_to_frame(io, self.frame + 1)
+    }
+
+    fn run(&mut self, io: &dyn IO, goal: Goal) -> StopReason {
+        let mut first = true;
+
+        loop {
+            if self.emulator.has_exited() {
+                return StopReason::Exited;
+            }
+
+            // execution never stops at the breakpoint it resumes from
+            let pc = self.emulator.memory().read_pc();
+            if !first {
+                if let Some(breakpoint) = self.breakpoints.get(&pc) {
+                    if breakpoint
+                        .condition
+                        .is_none_or(|condition| condition.holds(&self.emulator))
+                    {
+                        return StopReason::Breakpoint { address: pc };
+                    }
+                }
+            }
+            first = false;
+
+            match self.instruction(io) {
+                Ok(Some(reason)) => return reason,
+                Ok(None) => {}
+                Err(err) => return StopReason::Error(err),
+            }
+
+            let done = match goal {
+                Goal::Step => true,
+                Goal::Depth { depth, .. } => self.emulator.memory().stack().len() <= depth,
+                Goal::Frame(_) => false,
+            };
+            if done {
+                return StopReason::Step;
+            }
+
+            let frame = match goal {
+                Goal::Step => u64::MAX,
+                Goal::Depth { frame, .. } | Goal::Frame(frame) => frame,
+            };
+            if self.frame >= frame {
+                return StopReason::Frame { frame: self.frame };
+            }
+        }
+    }
+
+    /// runs one instruction and ends the frame like `Emulator::run_frame` would
+    fn instruction(&mut self, io: &dyn IO) -> Result<Option<StopReason>, EmulatorError> {
+        let instruction = self.emulator.current_instruction()?;
+        let access = self.emulator.memory_access(instruction);
+        let registers = self
+            .watched_registers
+            .iter()
+            .map(|register| (*register, register.read(&self.emulator)))
+            .collect::<Vec<_>>();
+
+        let progressed = self.emulator.step(io)?;
+
+        self.instructions += 1;
+        if !progressed || self.instructions >= self.emulator.speed() {
+            self.emulator.end_frame();
+            self.frame += 1;
+            self.instructions = 0;
+        }
+
+        if let Some(access) = access.filter(|_| progressed) {
+            let hit = self.watchpoints.iter().find(|(&address, watch)| {
+                access.contains(address as usize) && watch.matches(access.access)
+            });
+            if let Some((&address, _)) = hit {
+                return Ok(Some(StopReason::Watchpoint {
+                    address,
+                    access: access.access,
+                }));
+            }
+        }
+
+        for (register, old) in registers {
+            let new = register.read(&self.emulator);
+            if new != old {
+                return Ok(Some(StopReason::RegisterChanged { register, old, new }));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,286 @@
+use crate::coverage::Coverage;
+use crate::instruction::Instruction;
+use crate::Platform;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+/// address the rom gets loaded to
+pub const PROGRAM_START: u16 = 0x200;
+
+/// amount of data bytes written per line
+const BYTES_PER_LINE: usize = 8;
+
+/// why an address got a label
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LabelKind {
+    Main,
+    Subroutine,
+    Jump,
+    Data,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineKind {
+    /// a decoded instruction, `i := long` carries its address
+    Code {
+        instruction: Instruction,
+        long_address: Option<u16>,
+    },
+    Data,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub kind: LineKind,
+}
+
+/// a rom split into code and data
+#[derive(Debug, Clone)]
+pub struct Disassembly {
+    pub lines: Vec<Line>,
+    pub labels: BTreeMap<u16, LabelKind>,
+}
+
+/// disassembles a rom loaded at `0x200`
+///
+/// code is found by following every reachable path from the entry point,
+/// everything that is never reached is treated as data
+pub fn disassemble(rom: &[u8], platform: Platform) -> Disassembly {
+    disassemble_with_hints(rom, platform, &BTreeSet::new())
+}
+
+/// like [disassemble], but additionally treats the `hints` as entry points
+pub fn disassemble_with_hints(
+    rom: &[u8],
+    platform: Platform,
+    hints: &BTreeSet<u16>,
+) -> Disassembly {
+    disassemble_guided(rom, platform, hints, &BTreeSet::new())
+}
+
+/// like [disassemble], but starts at every instruction that ran and never decodes bytes only used as data
+pub fn disassemble_with_coverage(
+    rom: &[u8],
+    platform: Platform,
+    coverage: &Coverage,
+) -> Disassembly {
+    disassemble_guided(rom, platform, &coverage.instructions(), &coverage.data())
+}
+
+/// disassembles from the entry point and the `hints`, skipping instructions overlapping `data`
+fn disassemble_guided(
+    rom: &[u8],
+    platform: Platform,
+    hints: &BTreeSet<u16>,
+    data: &BTreeSet<u16>,
+) -> Disassembly {
+    let end = PROGRAM_START as usize + rom.len();
+    let read = |address: u16| -> Option<u16> {
+        let index = (address as usize).checked_sub(PROGRAM_START as usize)?;
+        Some(u16::from_be_bytes([*rom.get(index)?, *rom.get(index + 1)?]))
+    };
+
+    let mut code: BTreeMap<u16, (Instruction, Option<u16>)> = BTreeMap::new();
+    let mut labels = BTreeMap::new();
+    let mut pending = vec![PROGRAM_START];
+    pending.extend(hints.iter().rev());
+
+    label(&mut labels, PROGRAM_START, LabelKind::Main);
+
+    while let Some(address) = pending.pop() {
+        if code.contains_key(&address) || overlaps(&code, address) {
+            continue;
+        }
+
+        let Some(instruction) =
+            read(address).and_then(|opcode| Instruction::decode(opcode, platform))
+        else {
+            continue;
+        };
+
+        let long_address = match instruction {
+            Instruction::LdILong => match read(address.wrapping_add(2)) {
+                Some(long_address) => Some(long_address),
+                None => continue,
+            },
+            _ => None,
+        };
+
+        if data
+            .range(address..address.saturating_add(instruction.size()))
+            .next()
+            .is_some()
+        {
+            continue;
+        }
+
+        code.insert(address, (instruction, long_address));
+        let next = address.wrapping_add(instruction.size());
+
+        match instruction {
+            Instruction::Jp(target) => {
+                label(&mut labels, target, LabelKind::Jump);
+                pending.push(target);
+            }
+            Instruction::Call(target) => {
+                label(&mut labels, target, LabelKind::Subroutine);
+                pending.push(next);
+                pending.push(target);
+            }
+            Instruction::Ret | Instruction::Exit | Instruction::JpV0(_) => {}
+            Instruction::SeByte { .. }
+            | Instruction::SneByte { .. }
+            | Instruction::SeReg { .. }
+            | Instruction::SneReg { .. }
+            | Instruction::Skp(_)
+            | Instruction::Sknp(_) => {
+                let skipped = read(next)
+                    .and_then(|opcode| Instruction::decode(opcode, platform))
+                    .map_or(2, |instruction| instruction.size());
+                pending.push(next.wrapping_add(skipped));
+                pending.push(next);
+            }
+            Instruction::LdI(target) => {
+                label(&mut labels, target, LabelKind::Data);
+                pending.push(next);
+            }
+            Instruction::LdILong => {
+                if let Some(target) = long_address {
+                    label(&mut labels, target, LabelKind::Data);
+                }
+                pending.push(next);
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    let mut lines = vec![];
+    let mut address = PROGRAM_START as usize;
+    while address < end {
+        if let Some(&(instruction, long_address)) = code.get(&(address as u16)) {
+            let size = instruction.size() as usize;
+            let start = address - PROGRAM_START as usize;
+            lines.push(Line {
+                address: address as u16,
+                bytes: rom[start..start + size].to_vec(),
+                kind: LineKind::Code {
+                    instruction,
+                    long_address,
+                },
+            });
+            address += size;
+            continue;
+        }
+
+        // data runs until the next instruction, label or the line is full
+        let start = address;
+        address += 1;
+        while address < end
+            && address - start < BYTES_PER_LINE
+            && !code.contains_key(&(address as u16))
+            && !labels.contains_key(&(address as u16))
+        {
+            address += 1;
+        }
+
+        let offset = PROGRAM_START as usize;
+        lines.push(Line {
+            address: start as u16,
+            bytes: rom[start - offset..address - offset].to_vec(),
+            kind: LineKind::Data,
+        });
+    }
+
+    // labels only make sense where a line starts
+    let starts: BTreeSet<u16> = lines.iter().map(|line| line.address).collect();
+    labels.retain(|address, _| starts.contains(address));
+
+    Disassembly { lines, labels }
+}
+
+fn label(labels: &mut BTreeMap<u16, LabelKind>, address: u16, kind: LabelKind) {
+    let entry = labels.entry(address).or_insert(kind);
+    *entry = (*entry).min(kind);
+}
+
+/// whether `address` points into the middle of an already decoded instruction
+fn overlaps(code: &BTreeMap<u16, (Instruction, Option<u16>)>, address: u16) -> bool {
+    code.range(..address)
+        .next_back()
+        .is_some_and(|(start, (instruction, _))| {
+            *start as usize + instruction.size() as usize > address as usize
+        })
+}
+
+impl Disassembly {
+    /// name of the label at `address`, if there is one
+    pub fn label(&self, address: u16) -> Option<String> {
+        let kind = self.labels.get(&address)?;
+        Some(match kind {
+            LabelKind::Main => "main".to_string(),
+            LabelKind::Subroutine => format!("sub_{:03x}", address),
+            LabelKind::Jump => format!("label_{:03x}", address),
+            LabelKind::Data => format!("data_{:03x}", address),
+        })
+    }
+
+    /// the line starting at or containing `address`
+    pub fn line_at(&self, address: u16) -> Option<&Line> {
+        let index = self
+            .lines
+            .partition_point(|line| line.address <= address)
+            .checked_sub(1)?;
+        let line = &self.lines[index];
+        ((address as usize) < line.address as usize + line.bytes.len()).then_some(line)
+    }
+
+    /// the Octo source of a single line, without label or comment
+    pub fn source(&self, line: &Line) -> String {
+        match &line.kind {
+            LineKind::Code {
+                instruction,
+                long_address,
+            } => instruction.to_octo(
+                &|address| {
+                    self.label(address)
+                        .unwrap_or_else(|| format!("{:#05x}", address))
+                },
+                *long_address,
+            ),
+            LineKind::Data => line
+                .bytes
+                .iter()
+                .map(|byte| format!("{:#04x}", byte))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+impl Display for Disassembly {
+    /// writes the disassembly as Octo source, every line is annotated with its address and bytes
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.label(line.address) {
+                writeln!(f, ": {}", label)?;
+            }
+
+            let bytes = line
+                .bytes
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<String>();
+            writeln!(
+                f,
+                "\t{:<31} # {:#06x}: {}",
+                self.source(line),
+                line.address,
+                bytes
+            )?;
+        }
+
+        Ok(())
+    }
+}
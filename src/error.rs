@@ -0,0 +1,42 @@
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum EmulatorError {
+    UnknownOpcode { pc: u16, opcode: u16 },
+    StackUnderflow,
+    StackOverflow,
+    MemoryOutOfBounds { address: usize },
+    ProgramCounterUnderflow,
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl Display for EmulatorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmulatorError::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:#06x} at {:#06x}", opcode, pc)
+            }
+            EmulatorError::StackUnderflow => write!(f, "returned with an empty stack"),
+            EmulatorError::StackOverflow => write!(f, "stack overflow"),
+            EmulatorError::MemoryOutOfBounds { address } => {
+                write!(f, "memory access out of bounds at {:#06x}", address)
+            }
+            EmulatorError::ProgramCounterUnderflow => write!(f, "program counter underflow"),
+            EmulatorError::RomTooLarge { size, max } => {
+                write!(f, "rom is {} bytes but at most {} bytes fit", size, max)
+            }
+        }
+    }
+}
+
+impl Error for EmulatorError {}
+
+/// state of the emulator after a tick
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum TickOutcome {
+    Running,
+    /// the program stopped itself with `00FD`
+    Exited,
+}
@@ -0,0 +1,128 @@
+use crate::gpu::Gpu;
+use crate::palette::Palette;
+use std::fmt::{Display, Formatter};
+
+/// characters of the four plane combinations in ASCII renderings
+const ASCII: [char; 4] = ['.', '#', '+', '@'];
+
+/// most pixels [Framebuffer::to_png] writes, a byte each before compression
+#[cfg(feature = "cli")]
+pub const MAX_PNG_PIXELS: usize = 1 << 26;
+
+/// a copy of the display, one entry with the plane bits for every physical pixel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn capture(gpu: &Gpu) -> Framebuffer {
+        Framebuffer {
+            width: gpu.screen_width(),
+            height: gpu.screen_height(),
+            pixels: gpu.pixels().to_vec(),
+        }
+    }
+
+    /// 64 bit FNV-1a of the size and pixels, stays the same across runs, versions and machines
+    pub fn hash(&self) -> u64 {
+        let size = [self.width as u32, self.height as u32];
+        size.iter()
+            .flat_map(|value| value.to_le_bytes())
+            .chain(self.pixels.iter().copied())
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /// a binary PBM image, every pixel with any plane set is black
+    pub fn to_pbm(&self) -> Vec<u8> {
+        let mut pbm = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+        for row in self.pixels.chunks(self.width) {
+            for byte in row.chunks(8) {
+                let bits = byte
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &pixel)| pixel != 0)
+                    .fold(0u8, |bits, (x, _)| bits | 0x80 >> x);
+                pbm.push(bits);
+            }
+        }
+        pbm
+    }
+
+    /// the plane combination of every pixel with each pixel turned into a `scale` by `scale` square
+    pub fn scaled(&self, scale: usize) -> Vec<u8> {
+        let mut scaled = Vec::with_capacity(self.pixels.len() * scale * scale);
+        for row in self.pixels.chunks(self.width) {
+            let scaled_row = row
+                .iter()
+                .flat_map(|pixel| std::iter::repeat_n(pixel & 0b11, scale))
+                .collect::<Vec<_>>();
+            for _ in 0..scale {
+                scaled.extend_from_slice(&scaled_row);
+            }
+        }
+        scaled
+    }
+
+    /// the color of every pixel as red, green, blue and alpha bytes, the layout of canvas image data
+    pub fn to_rgba(&self, palette: &Palette) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| {
+                let [r, g, b] = palette.color(*pixel);
+                [r, g, b, 0xFF]
+            })
+            .collect()
+    }
+
+    /// a PNG image with each pixel turned into a `scale` by `scale` square of its color,
+    /// fails if it would have more than [MAX_PNG_PIXELS] pixels
+    #[cfg(feature = "cli")]
+    pub fn to_png(&self, scale: usize, palette: &Palette) -> Result<Vec<u8>, String> {
+        let too_large = || {
+            format!(
+                "a {}x{} display scaled by {} is too large for a PNG",
+                self.width, self.height, scale
+            )
+        };
+        let width = self.width.checked_mul(scale).ok_or_else(too_large)?;
+        let height = self.height.checked_mul(scale).ok_or_else(too_large)?;
+        if width
+            .checked_mul(height)
+            .is_none_or(|pixels| pixels > MAX_PNG_PIXELS)
+        {
+            return Err(too_large());
+        }
+
+        let mut png = vec![];
+        let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(palette.colors.concat());
+
+        let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+        writer
+            .write_image_data(&self.scaled(scale))
+            .map_err(|err| err.to_string())?;
+        writer.finish().map_err(|err| err.to_string())?;
+        Ok(png)
+    }
+}
+
+/// one character per pixel, `.` for off and `#` for the first plane
+impl Display for Framebuffer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.pixels.chunks(self.width) {
+            let line = row
+                .iter()
+                .map(|pixel| ASCII[(pixel & 0b11) as usize])
+                .collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
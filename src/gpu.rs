@@ -1,35 +1,235 @@
-use crate::io::IO;
+use crate::state::{StateError, StateReader, StateWriter};
 use crate::Platform;
 
 #[derive(Debug)]
-
 pub struct Gpu {
-    pixels: Vec<bool>,
+    width: usize,
+    height: usize,
+    lores_scale: usize,
+    hires: bool,
+    selected_planes: u8,
+    /// one bit per plane for every physical pixel
+    pixels: Vec<u8>,
 }
 
 impl Gpu {
     pub fn new(platform: Platform) -> Gpu {
         Gpu {
-            pixels: vec![false; platform.width() * platform.height()],
+            width: platform.width(),
+            height: platform.height(),
+            lores_scale: platform.width() / 64,
+            hires: false,
+            selected_planes: 0b01,
+            pixels: vec![0; platform.width() * platform.height()],
+        }
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.bool(self.hires);
+        writer.u8(self.selected_planes);
+        writer.bytes(&self.pixels);
+    }
+
+    pub(crate) fn load_state(
+        reader: &mut StateReader,
+        platform: Platform,
+    ) -> Result<Gpu, StateError> {
+        let mut gpu = Gpu::new(platform);
+        gpu.hires = reader.bool()?;
+        gpu.selected_planes = reader.u8()? & 0b11;
+
+        let pixels = reader.bytes()?;
+        if pixels.len() != gpu.pixels.len() {
+            return Err(StateError::Invalid { field: "pixels" });
         }
+        gpu.pixels = pixels.to_vec();
+
+        Ok(gpu)
+    }
+
+    /// size of one logical pixel on the physical screen
+    fn scale(&self) -> usize {
+        if self.hires {
+            1
+        } else {
+            self.lores_scale
+        }
+    }
+
+    /// logical width for the current resolution
+    pub fn width(&self) -> usize {
+        self.width / self.scale()
+    }
+
+    /// logical height for the current resolution
+    pub fn height(&self) -> usize {
+        self.height / self.scale()
     }
 
-    pub fn flip_pixel(&mut self, platform: Platform, x: usize, y: usize) -> bool {
-        //todo - dont hard code sizes
-        if x >= platform.width() || y >= platform.height() {
+    /// width in physical pixels, the length of a row of [Gpu::pixels]
+    pub fn screen_width(&self) -> usize {
+        self.width
+    }
+
+    /// height in physical pixels
+    pub fn screen_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.pixels.fill(0);
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    pub fn select_planes(&mut self, planes: u8) {
+        self.selected_planes = planes & 0b11;
+    }
+
+    pub fn selected_plane_count(&self) -> usize {
+        self.selected_planes.count_ones() as usize
+    }
+
+    /// draws 8 pixel wide sprites (16 when `wide`) and returns whether a pixel got turned off
+    ///
+    /// `sprite` holds the data for every selected plane one after another,
+    /// pixels past the screen edges wrap around when `wrap` is set and are clipped otherwise
+    pub fn draw_sprite(
+        &mut self,
+        x: usize,
+        y: usize,
+        sprite: &[u8],
+        wide: bool,
+        wrap: bool,
+    ) -> bool {
+        let x = x % self.width();
+        let y = y % self.height();
+
+        let plane_count = self.selected_plane_count();
+        if plane_count == 0 {
             return false;
         }
 
-        self.pixels[y * platform.width() + x] = !self.pixels[y * platform.width() + x];
+        let bytes_per_row = if wide { 2 } else { 1 };
+        let plane_length = sprite.len() / plane_count;
+        let mut was_turned_off = false;
+
+        let selected_planes = self.selected_planes;
+        let planes = (0..2)
+            .map(|plane| 1 << plane)
+            .filter(|plane| selected_planes & plane != 0);
+        for (plane, data) in planes.zip(sprite.chunks(plane_length.max(1))) {
+            for (y_off, row) in data.chunks(bytes_per_row).enumerate() {
+                for (byte_off, byte) in row.iter().enumerate() {
+                    for bit in 0..8 {
+                        if byte & (0x80 >> bit) != 0
+                            && self.flip_pixel(plane, x + byte_off * 8 + bit, y + y_off, wrap)
+                        {
+                            was_turned_off = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        was_turned_off
+    }
+
+    fn flip_pixel(&mut self, plane: u8, x: usize, y: usize, wrap: bool) -> bool {
+        let (x, y) = if wrap {
+            (x % self.width(), y % self.height())
+        } else if x >= self.width() || y >= self.height() {
+            return false;
+        } else {
+            (x, y)
+        };
+
+        let scale = self.scale();
+        let mut was_on = false;
+
+        for y_off in 0..scale {
+            for x_off in 0..scale {
+                let index = (y * scale + y_off) * self.width + x * scale + x_off;
+
+                was_on |= self.pixels[index] & plane != 0;
+                self.pixels[index] ^= plane;
+            }
+        }
+
+        was_on
+    }
+
+    /// moves the selected planes by the given amount of physical rows, negative values move up
+    fn shift_rows(&mut self, rows: isize) {
+        let planes = self.selected_planes;
+        let old = self.pixels.clone();
+
+        for y in 0..self.height {
+            let source = y as isize - rows;
+
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                let moved = if source >= 0 && (source as usize) < self.height {
+                    old[source as usize * self.width + x] & planes
+                } else {
+                    0
+                };
+
+                self.pixels[index] = (self.pixels[index] & !planes) | moved;
+            }
+        }
+    }
+
+    /// moves the selected planes by the given amount of physical columns, negative values move left
+    fn shift_columns(&mut self, columns: isize) {
+        let planes = self.selected_planes;
+        let old = self.pixels.clone();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                let source = x as isize - columns;
+                let moved = if source >= 0 && (source as usize) < self.width {
+                    old[y * self.width + source as usize] & planes
+                } else {
+                    0
+                };
+
+                self.pixels[index] = (self.pixels[index] & !planes) | moved;
+            }
+        }
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.shift_rows((rows * self.scale()) as isize);
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.shift_rows(-((rows * self.scale()) as isize));
+    }
+
+    pub fn scroll_left(&mut self, columns: usize) {
+        self.shift_columns(-((columns * self.scale()) as isize));
+    }
 
-        !self.pixels[y * platform.width() + x]
+    pub fn scroll_right(&mut self, columns: usize) {
+        self.shift_columns((columns * self.scale()) as isize);
     }
 
+    /// clears the selected planes
     pub fn clear(&mut self) {
-        self.pixels.fill(false);
+        let planes = self.selected_planes;
+        self.pixels.iter_mut().for_each(|pixel| *pixel &= !planes);
     }
 
-    pub fn pixels(&self) -> &[bool] {
+    pub fn pixels(&self) -> &[u8] {
         &self.pixels
     }
 }
@@ -0,0 +1,170 @@
+use crate::error::{EmulatorError, TickOutcome};
+use crate::framebuffer::Framebuffer;
+use crate::inspector::Inspector;
+use crate::io::IO;
+use crate::Emulator;
+use std::str::FromStr;
+
+/// keys to hold from a frame on, one line per change
+///
+/// every line is a frame number followed by the hexadecimal keys held from that frame until the next line,
+/// a frame without keys releases all of them, `#` starts a comment
+///
+/// ```text
+/// # press 5 on frame 30 and let go of it two frames later
+/// 30 5
+/// 32
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputScript {
+    /// frames and the keys held from them on, ordered by frame
+    changes: Vec<(u64, Vec<u8>)>,
+}
+
+impl InputScript {
+    /// a script that never presses a key
+    pub fn new() -> InputScript {
+        InputScript::default()
+    }
+
+    /// holds `keys` from `frame` on, replaces any change at the same frame
+    pub fn with_keys(mut self, frame: u64, keys: &[u8]) -> InputScript {
+        let index = self.changes.partition_point(|(start, _)| *start < frame);
+        if self
+            .changes
+            .get(index)
+            .is_some_and(|(start, _)| *start == frame)
+        {
+            self.changes[index].1 = keys.to_vec();
+        } else {
+            self.changes.insert(index, (frame, keys.to_vec()));
+        }
+        self
+    }
+
+    /// the keys held during `frame`
+    pub fn keys_at(&self, frame: u64) -> &[u8] {
+        let index = self.changes.partition_point(|(start, _)| *start <= frame);
+        match index.checked_sub(1) {
+            Some(index) => &self.changes[index].1,
+            None => &[],
+        }
+    }
+}
+
+impl FromStr for InputScript {
+    type Err = String;
+
+    fn from_str(script: &str) -> Result<Self, Self::Err> {
+        let mut changes: Vec<(u64, Vec<u8>)> = vec![];
+        for (index, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(frame) = words.next() else {
+                continue;
+            };
+
+            let error = |message: String| format!("line {}: {}", index + 1, message);
+            let frame = frame
+                .parse::<u64>()
+                .map_err(|_| error(format!("{:?} is not a frame", frame)))?;
+            if changes.last().is_some_and(|(last, _)| *last >= frame) {
+                return Err(error(format!(
+                    "frame {} is not after the line before",
+                    frame
+                )));
+            }
+
+            let keys = words
+                .map(|key| match u8::from_str_radix(key, 16) {
+                    Ok(key) if key <= 0xF => Ok(key),
+                    _ => Err(error(format!("{:?} is not a key from 0 to F", key))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            changes.push((frame, keys));
+        }
+
+        Ok(InputScript { changes })
+    }
+}
+
+/// plays an [InputScript], pressing its keys frame by frame
+pub struct ScriptedInput<'a> {
+    script: &'a InputScript,
+    pressed: Vec<u8>,
+    just_pressed: Vec<u8>,
+}
+
+impl<'a> ScriptedInput<'a> {
+    pub fn new(script: &'a InputScript) -> ScriptedInput<'a> {
+        ScriptedInput {
+            script,
+            pressed: vec![],
+            just_pressed: vec![],
+        }
+    }
+
+    /// holds the keys of `frame`, keys that weren't held in the frame before count as just pressed
+    pub fn start_frame(&mut self, frame: u64) {
+        let keys = self.script.keys_at(frame);
+        self.just_pressed = keys
+            .iter()
+            .filter(|key| !self.pressed.contains(key))
+            .copied()
+            .collect();
+        self.pressed = keys.to_vec();
+    }
+}
+
+impl IO for ScriptedInput<'_> {
+    fn is_code_pressed(&self, code: u8) -> bool {
+        self.pressed.contains(&code)
+    }
+
+    fn get_just_pressed(&self) -> Vec<u8> {
+        self.just_pressed.clone()
+    }
+}
+
+/// how a headless run ended
+#[derive(Debug, Clone)]
+pub struct HeadlessRun {
+    /// frames that ran, fewer than asked for when the program exited
+    pub frames: u64,
+    pub exited: bool,
+    /// the display after the last frame
+    pub framebuffer: Framebuffer,
+}
+
+/// runs `frames` frames as fast as possible with the keys of `script`, stops early if the program exits
+pub fn run_headless(
+    emulator: &mut Emulator,
+    frames: u64,
+    script: &InputScript,
+) -> Result<HeadlessRun, EmulatorError> {
+    run_headless_inspected(emulator, frames, script, &mut ())
+}
+
+/// like [run_headless], showing everything the emulator runs to `inspector`
+pub fn run_headless_inspected(
+    emulator: &mut Emulator,
+    frames: u64,
+    script: &InputScript,
+    inspector: &mut dyn Inspector,
+) -> Result<HeadlessRun, EmulatorError> {
+    let mut input = ScriptedInput::new(script);
+    let mut frame = 0;
+    let mut exited = emulator.has_exited();
+
+    while frame < frames && !exited {
+        input.start_frame(frame);
+        exited = emulator.run_frame_inspected(&input, inspector)? == TickOutcome::Exited;
+        frame += 1;
+    }
+
+    Ok(HeadlessRun {
+        frames: frame,
+        exited,
+        framebuffer: Framebuffer::capture(emulator.display()),
+    })
+}
@@ -0,0 +1,43 @@
+use crate::instruction::Instruction;
+use crate::Emulator;
+
+/// watches the emulator run, e.g. to trace or profile a program
+///
+/// every hook does nothing by default, `()` is the inspector that watches nothing
+pub trait Inspector {
+    /// called right before `instruction` runs, the emulator is still in the state before it
+    fn before_instruction(&mut self, _emulator: &Emulator, _instruction: Instruction) {}
+
+    /// called once a frame has ended and the timers were ticked
+    fn after_frame(&mut self, _emulator: &Emulator) {}
+}
+
+impl Inspector for () {}
+
+/// `None` watches nothing
+impl<I: Inspector> Inspector for Option<I> {
+    fn before_instruction(&mut self, emulator: &Emulator, instruction: Instruction) {
+        if let Some(inspector) = self {
+            inspector.before_instruction(emulator, instruction);
+        }
+    }
+
+    fn after_frame(&mut self, emulator: &Emulator) {
+        if let Some(inspector) = self {
+            inspector.after_frame(emulator);
+        }
+    }
+}
+
+/// both inspectors watch, the first one first
+impl<A: Inspector, B: Inspector> Inspector for (A, B) {
+    fn before_instruction(&mut self, emulator: &Emulator, instruction: Instruction) {
+        self.0.before_instruction(emulator, instruction);
+        self.1.before_instruction(emulator, instruction);
+    }
+
+    fn after_frame(&mut self, emulator: &Emulator) {
+        self.0.after_frame(emulator);
+        self.1.after_frame(emulator);
+    }
+}
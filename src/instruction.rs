@@ -0,0 +1,385 @@
+use crate::memory::{u16_to_u4_array, ToU16, ToU8};
+use crate::Platform;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// a single decoded instruction, register operands are indices from 0x0 to 0xF
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum Instruction {
+    /// `00CN`
+    ScrollDown(u8),
+    /// `00DN`
+    ScrollUp(u8),
+    /// `00E0`
+    Cls,
+    /// `00EE`
+    Ret,
+    /// `00FB`
+    ScrollRight,
+    /// `00FC`
+    ScrollLeft,
+    /// `00FD`
+    Exit,
+    /// `00FE`
+    Lores,
+    /// `00FF`
+    Hires,
+    /// `1NNN`
+    Jp(u16),
+    /// `2NNN`
+    Call(u16),
+    /// `3XNN`
+    SeByte { x: u8, byte: u8 },
+    /// `4XNN`
+    SneByte { x: u8, byte: u8 },
+    /// `5XY0`
+    SeReg { x: u8, y: u8 },
+    /// `5XY2`
+    SaveRange { x: u8, y: u8 },
+    /// `5XY3`
+    LoadRange { x: u8, y: u8 },
+    /// `6XNN`
+    LdByte { x: u8, byte: u8 },
+    /// `7XNN`
+    AddByte { x: u8, byte: u8 },
+    /// `8XY0`
+    LdReg { x: u8, y: u8 },
+    /// `8XY1`
+    Or { x: u8, y: u8 },
+    /// `8XY2`
+    And { x: u8, y: u8 },
+    /// `8XY3`
+    Xor { x: u8, y: u8 },
+    /// `8XY4`
+    AddReg { x: u8, y: u8 },
+    /// `8XY5`
+    Sub { x: u8, y: u8 },
+    /// `8XY6`
+    Shr { x: u8, y: u8 },
+    /// `8XY7`
+    Subn { x: u8, y: u8 },
+    /// `8XYE`
+    Shl { x: u8, y: u8 },
+    /// `9XY0`
+    SneReg { x: u8, y: u8 },
+    /// `ANNN`
+    LdI(u16),
+    /// `BNNN`, jumps relative to VX with the jump quirk where X is the highest nibble of NNN
+    JpV0(u16),
+    /// `CXNN`
+    Rnd { x: u8, byte: u8 },
+    /// `DXYN`
+    Drw { x: u8, y: u8, n: u8 },
+    /// `EX9E`
+    Skp(u8),
+    /// `EXA1`
+    Sknp(u8),
+    /// `F000 NNNN`, the address is stored in the following two bytes
+    LdILong,
+    /// `FN01`
+    Plane(u8),
+    /// `F002`
+    Audio,
+    /// `FX07`
+    LdVxDt(u8),
+    /// `FX0A`
+    LdVxK(u8),
+    /// `FX15`
+    LdDtVx(u8),
+    /// `FX18`
+    LdStVx(u8),
+    /// `FX1E`
+    AddI(u8),
+    /// `FX29`
+    LdF(u8),
+    /// `FX30`
+    LdHf(u8),
+    /// `FX33`
+    Bcd(u8),
+    /// `FX3A`
+    Pitch(u8),
+    /// `FX55`
+    Store(u8),
+    /// `FX65`
+    Load(u8),
+    /// `FX75`
+    SaveFlags(u8),
+    /// `FX85`
+    LoadFlags(u8),
+}
+
+impl Instruction {
+    /// returns `None` for opcodes the platform doesn't know
+    pub fn decode(opcode: u16, platform: Platform) -> Option<Instruction> {
+        let [a, b, c, d] = u16_to_u4_array(opcode);
+        let extended = platform != Platform::Chip8;
+        let xo = platform == Platform::X0Chip;
+
+        let instruction = match (a, b, c, d) {
+            (0x0, 0x0, 0xC, n) if extended => Instruction::ScrollDown(n),
+            (0x0, 0x0, 0xD, n) if xo => Instruction::ScrollUp(n),
+            (0x0, 0x0, 0xE, 0x0) => Instruction::Cls,
+            (0x0, 0x0, 0xE, 0xE) => Instruction::Ret,
+            (0x0, 0x0, 0xF, 0xB) if extended => Instruction::ScrollRight,
+            (0x0, 0x0, 0xF, 0xC) if extended => Instruction::ScrollLeft,
+            (0x0, 0x0, 0xF, 0xD) if extended => Instruction::Exit,
+            (0x0, 0x0, 0xF, 0xE) if extended => Instruction::Lores,
+            (0x0, 0x0, 0xF, 0xF) if extended => Instruction::Hires,
+            (0x1, n1, n2, n3) => Instruction::Jp((n1, n2, n3).to_u16()),
+            (0x2, n1, n2, n3) => Instruction::Call((n1, n2, n3).to_u16()),
+            (0x3, x, n1, n2) => Instruction::SeByte {
+                x,
+                byte: (n1, n2).to_u8(),
+            },
+            (0x4, x, n1, n2) => Instruction::SneByte {
+                x,
+                byte: (n1, n2).to_u8(),
+            },
+            (0x5, x, y, 0x0) => Instruction::SeReg { x, y },
+            (0x5, x, y, 0x2) if xo => Instruction::SaveRange { x, y },
+            (0x5, x, y, 0x3) if xo => Instruction::LoadRange { x, y },
+            (0x6, x, n1, n2) => Instruction::LdByte {
+                x,
+                byte: (n1, n2).to_u8(),
+            },
+            (0x7, x, n1, n2) => Instruction::AddByte {
+                x,
+                byte: (n1, n2).to_u8(),
+            },
+            (0x8, x, y, 0x0) => Instruction::LdReg { x, y },
+            (0x8, x, y, 0x1) => Instruction::Or { x, y },
+            (0x8, x, y, 0x2) => Instruction::And { x, y },
+            (0x8, x, y, 0x3) => Instruction::Xor { x, y },
+            (0x8, x, y, 0x4) => Instruction::AddReg { x, y },
+            (0x8, x, y, 0x5) => Instruction::Sub { x, y },
+            (0x8, x, y, 0x6) => Instruction::Shr { x, y },
+            (0x8, x, y, 0x7) => Instruction::Subn { x, y },
+            (0x8, x, y, 0xE) => Instruction::Shl { x, y },
+            (0x9, x, y, 0x0) => Instruction::SneReg { x, y },
+            (0xA, n1, n2, n3) => Instruction::LdI((n1, n2, n3).to_u16()),
+            (0xB, n1, n2, n3) => Instruction::JpV0((n1, n2, n3).to_u16()),
+            (0xC, x, n1, n2) => Instruction::Rnd {
+                x,
+                byte: (n1, n2).to_u8(),
+            },
+            (0xD, x, y, n) => Instruction::Drw { x, y, n },
+            (0xE, x, 0x9, 0xE) => Instruction::Skp(x),
+            (0xE, x, 0xA, 0x1) => Instruction::Sknp(x),
+            (0xF, 0x0, 0x0, 0x0) if xo => Instruction::LdILong,
+            (0xF, n, 0x0, 0x1) if xo => Instruction::Plane(n),
+            (0xF, 0x0, 0x0, 0x2) if xo => Instruction::Audio,
+            (0xF, x, 0x0, 0x7) => Instruction::LdVxDt(x),
+            (0xF, x, 0x0, 0xA) => Instruction::LdVxK(x),
+            (0xF, x, 0x1, 0x5) => Instruction::LdDtVx(x),
+            (0xF, x, 0x1, 0x8) => Instruction::LdStVx(x),
+            (0xF, x, 0x1, 0xE) => Instruction::AddI(x),
+            (0xF, x, 0x2, 0x9) => Instruction::LdF(x),
+            (0xF, x, 0x3, 0x0) if extended => Instruction::LdHf(x),
+            (0xF, x, 0x3, 0x3) => Instruction::Bcd(x),
+            (0xF, x, 0x3, 0xA) if xo => Instruction::Pitch(x),
+            (0xF, x, 0x5, 0x5) => Instruction::Store(x),
+            (0xF, x, 0x6, 0x5) => Instruction::Load(x),
+            (0xF, x, 0x7, 0x5) if (x as usize) < platform.flag_count() => Instruction::SaveFlags(x),
+            (0xF, x, 0x8, 0x5) if (x as usize) < platform.flag_count() => Instruction::LoadFlags(x),
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    pub fn encode(&self) -> u16 {
+        let with_x = |base: u16, x: u8| base | (x as u16) << 8;
+        let with_xy = |base: u16, x: u8, y: u8| with_x(base, x) | (y as u16) << 4;
+        let with_xnn = |base: u16, x: u8, byte: u8| with_x(base, x) | byte as u16;
+
+        match *self {
+            Instruction::ScrollDown(n) => 0x00C0 | n as u16,
+            Instruction::ScrollUp(n) => 0x00D0 | n as u16,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Lores => 0x00FE,
+            Instruction::Hires => 0x00FF,
+            Instruction::Jp(address) => 0x1000 | address,
+            Instruction::Call(address) => 0x2000 | address,
+            Instruction::SeByte { x, byte } => with_xnn(0x3000, x, byte),
+            Instruction::SneByte { x, byte } => with_xnn(0x4000, x, byte),
+            Instruction::SeReg { x, y } => with_xy(0x5000, x, y),
+            Instruction::SaveRange { x, y } => with_xy(0x5002, x, y),
+            Instruction::LoadRange { x, y } => with_xy(0x5003, x, y),
+            Instruction::LdByte { x, byte } => with_xnn(0x6000, x, byte),
+            Instruction::AddByte { x, byte } => with_xnn(0x7000, x, byte),
+            Instruction::LdReg { x, y } => with_xy(0x8000, x, y),
+            Instruction::Or { x, y } => with_xy(0x8001, x, y),
+            Instruction::And { x, y } => with_xy(0x8002, x, y),
+            Instruction::Xor { x, y } => with_xy(0x8003, x, y),
+            Instruction::AddReg { x, y } => with_xy(0x8004, x, y),
+            Instruction::Sub { x, y } => with_xy(0x8005, x, y),
+            Instruction::Shr { x, y } => with_xy(0x8006, x, y),
+            Instruction::Subn { x, y } => with_xy(0x8007, x, y),
+            Instruction::Shl { x, y } => with_xy(0x800E, x, y),
+            Instruction::SneReg { x, y } => with_xy(0x9000, x, y),
+            Instruction::LdI(address) => 0xA000 | address,
+            Instruction::JpV0(address) => 0xB000 | address,
+            Instruction::Rnd { x, byte } => with_xnn(0xC000, x, byte),
+            Instruction::Drw { x, y, n } => with_xy(0xD000, x, y) | n as u16,
+            Instruction::Skp(x) => with_x(0xE09E, x),
+            Instruction::Sknp(x) => with_x(0xE0A1, x),
+            Instruction::LdILong => 0xF000,
+            Instruction::Plane(n) => with_x(0xF001, n),
+            Instruction::Audio => 0xF002,
+            Instruction::LdVxDt(x) => with_x(0xF007, x),
+            Instruction::LdVxK(x) => with_x(0xF00A, x),
+            Instruction::LdDtVx(x) => with_x(0xF015, x),
+            Instruction::LdStVx(x) => with_x(0xF018, x),
+            Instruction::AddI(x) => with_x(0xF01E, x),
+            Instruction::LdF(x) => with_x(0xF029, x),
+            Instruction::LdHf(x) => with_x(0xF030, x),
+            Instruction::Bcd(x) => with_x(0xF033, x),
+            Instruction::Pitch(x) => with_x(0xF03A, x),
+            Instruction::Store(x) => with_x(0xF055, x),
+            Instruction::Load(x) => with_x(0xF065, x),
+            Instruction::SaveFlags(x) => with_x(0xF075, x),
+            Instruction::LoadFlags(x) => with_x(0xF085, x),
+        }
+    }
+
+    /// bytes the instruction takes up in memory
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LdILong => 4,
+            _ => 2,
+        }
+    }
+
+    /// the opcode pattern of the instruction, e.g. `8XY4`, the same for all operands
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::ScrollDown(_) => "00CN",
+            Instruction::ScrollUp(_) => "00DN",
+            Instruction::Cls => "00E0",
+            Instruction::Ret => "00EE",
+            Instruction::ScrollRight => "00FB",
+            Instruction::ScrollLeft => "00FC",
+            Instruction::Exit => "00FD",
+            Instruction::Lores => "00FE",
+            Instruction::Hires => "00FF",
+            Instruction::Jp(_) => "1NNN",
+            Instruction::Call(_) => "2NNN",
+            Instruction::SeByte { .. } => "3XNN",
+            Instruction::SneByte { .. } => "4XNN",
+            Instruction::SeReg { .. } => "5XY0",
+            Instruction::SaveRange { .. } => "5XY2",
+            Instruction::LoadRange { .. } => "5XY3",
+            Instruction::LdByte { .. } => "6XNN",
+            Instruction::AddByte { .. } => "7XNN",
+            Instruction::LdReg { .. } => "8XY0",
+            Instruction::Or { .. } => "8XY1",
+            Instruction::And { .. } => "8XY2",
+            Instruction::Xor { .. } => "8XY3",
+            Instruction::AddReg { .. } => "8XY4",
+            Instruction::Sub { .. } => "8XY5",
+            Instruction::Shr { .. } => "8XY6",
+            Instruction::Subn { .. } => "8XY7",
+            Instruction::Shl { .. } => "8XYE",
+            Instruction::SneReg { .. } => "9XY0",
+            Instruction::LdI(_) => "ANNN",
+            Instruction::JpV0(_) => "BNNN",
+            Instruction::Rnd { .. } => "CXNN",
+            Instruction::Drw { .. } => "DXYN",
+            Instruction::Skp(_) => "EX9E",
+            Instruction::Sknp(_) => "EXA1",
+            Instruction::LdILong => "F000",
+            Instruction::Plane(_) => "FN01",
+            Instruction::Audio => "F002",
+            Instruction::LdVxDt(_) => "FX07",
+            Instruction::LdVxK(_) => "FX0A",
+            Instruction::LdDtVx(_) => "FX15",
+            Instruction::LdStVx(_) => "FX18",
+            Instruction::AddI(_) => "FX1E",
+            Instruction::LdF(_) => "FX29",
+            Instruction::LdHf(_) => "FX30",
+            Instruction::Bcd(_) => "FX33",
+            Instruction::Pitch(_) => "FX3A",
+            Instruction::Store(_) => "FX55",
+            Instruction::Load(_) => "FX65",
+            Instruction::SaveFlags(_) => "FX75",
+            Instruction::LoadFlags(_) => "FX85",
+        }
+    }
+}
+
+impl Instruction {
+    /// formats the instruction in Octo syntax, `address` names the targets of jumps, calls and `i :=`
+    ///
+    /// `F000 NNNN` is written as `i := long` followed by the name of the address in `long_address`
+    pub fn to_octo(&self, address: &dyn Fn(u16) -> String, long_address: Option<u16>) -> String {
+        match *self {
+            Instruction::ScrollDown(n) => format!("scroll-down {}", n),
+            Instruction::ScrollUp(n) => format!("scroll-up {}", n),
+            Instruction::Cls => "clear".to_string(),
+            Instruction::Ret => "return".to_string(),
+            Instruction::ScrollRight => "scroll-right".to_string(),
+            Instruction::ScrollLeft => "scroll-left".to_string(),
+            Instruction::Exit => "exit".to_string(),
+            Instruction::Lores => "lores".to_string(),
+            Instruction::Hires => "hires".to_string(),
+            Instruction::Jp(target) => format!("jump {}", address(target)),
+            Instruction::Call(target) => format!(":call {}", address(target)),
+            Instruction::SeByte { x, byte } => format!("if v{:x} != {:#04x} then", x, byte),
+            Instruction::SneByte { x, byte } => format!("if v{:x} == {:#04x} then", x, byte),
+            Instruction::SeReg { x, y } => format!("if v{:x} != v{:x} then", x, y),
+            Instruction::SaveRange { x, y } => format!("save v{:x} - v{:x}", x, y),
+            Instruction::LoadRange { x, y } => format!("load v{:x} - v{:x}", x, y),
+            Instruction::LdByte { x, byte } => format!("v{:x} := {:#04x}", x, byte),
+            Instruction::AddByte { x, byte } => format!("v{:x} += {:#04x}", x, byte),
+            Instruction::LdReg { x, y } => format!("v{:x} := v{:x}", x, y),
+            Instruction::Or { x, y } => format!("v{:x} |= v{:x}", x, y),
+            Instruction::And { x, y } => format!("v{:x} &= v{:x}", x, y),
+            Instruction::Xor { x, y } => format!("v{:x} ^= v{:x}", x, y),
+            Instruction::AddReg { x, y } => format!("v{:x} += v{:x}", x, y),
+            Instruction::Sub { x, y } => format!("v{:x} -= v{:x}", x, y),
+            Instruction::Shr { x, y } => format!("v{:x} >>= v{:x}", x, y),
+            Instruction::Subn { x, y } => format!("v{:x} =- v{:x}", x, y),
+            Instruction::Shl { x, y } => format!("v{:x} <<= v{:x}", x, y),
+            Instruction::SneReg { x, y } => format!("if v{:x} == v{:x} then", x, y),
+            Instruction::LdI(target) => format!("i := {}", address(target)),
+            Instruction::JpV0(target) => format!("jump0 {}", address(target)),
+            Instruction::Rnd { x, byte } => format!("v{:x} := random {:#04x}", x, byte),
+            Instruction::Drw { x, y, n } => format!("sprite v{:x} v{:x} {:#x}", x, y, n),
+            Instruction::Skp(x) => format!("if v{:x} -key then", x),
+            Instruction::Sknp(x) => format!("if v{:x} key then", x),
+            Instruction::LdILong => match long_address {
+                Some(target) => format!("i := long {}", address(target)),
+                None => "i := long".to_string(),
+            },
+            Instruction::Plane(n) => format!("plane {}", n),
+            Instruction::Audio => "audio".to_string(),
+            Instruction::LdVxDt(x) => format!("v{:x} := delay", x),
+            Instruction::LdVxK(x) => format!("v{:x} := key", x),
+            Instruction::LdDtVx(x) => format!("delay := v{:x}", x),
+            Instruction::LdStVx(x) => format!("buzzer := v{:x}", x),
+            Instruction::AddI(x) => format!("i += v{:x}", x),
+            Instruction::LdF(x) => format!("i := hex v{:x}", x),
+            Instruction::LdHf(x) => format!("i := bighex v{:x}", x),
+            Instruction::Bcd(x) => format!("bcd v{:x}", x),
+            Instruction::Pitch(x) => format!("pitch := v{:x}", x),
+            Instruction::Store(x) => format!("save v{:x}", x),
+            Instruction::Load(x) => format!("load v{:x}", x),
+            Instruction::SaveFlags(x) => format!("saveflags v{:x}", x),
+            Instruction::LoadFlags(x) => format!("loadflags v{:x}", x),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.to_octo(&|address| format!("{:#05x}", address), None)
+        )
+    }
+}
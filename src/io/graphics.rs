@@ -0,0 +1,180 @@
+use crate::framebuffer::Framebuffer;
+use crate::palette::Palette;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// longest base64 payload kitty accepts in one escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+/// image id of the display, sending it again replaces the image instead of adding one
+const KITTY_IMAGE_ID: u32 = 1;
+
+/// ways of drawing the display as an image in terminals that support it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    /// DEC Sixel, supported by xterm, foot, mlterm, WezTerm and others
+    Sixel,
+    /// the kitty graphics protocol, supported by kitty, WezTerm and Ghostty
+    Kitty,
+}
+
+impl GraphicsProtocol {
+    /// the protocol the terminal supports judging by its environment variables, `var` looks one up
+    ///
+    /// multiplexers like tmux don't pass images through, so they never get one
+    pub fn detect(var: impl Fn(&str) -> Option<String>) -> Option<GraphicsProtocol> {
+        if var("TMUX").is_some() || var("STY").is_some() {
+            return None;
+        }
+
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+
+        if var("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || ["WezTerm", "ghostty"].contains(&program.as_str())
+        {
+            return Some(GraphicsProtocol::Kitty);
+        }
+
+        if term.contains("sixel")
+            || ["foot", "foot-extra", "mlterm", "contour"].contains(&term.as_str())
+            || ["iTerm.app", "mintty"].contains(&program.as_str())
+        {
+            return Some(GraphicsProtocol::Sixel);
+        }
+
+        None
+    }
+
+    /// escape sequences that draw `framebuffer` at the cursor with each pixel as a `scale` by `scale` square,
+    /// fails if the image is too large to encode
+    pub fn encode(
+        &self,
+        framebuffer: &Framebuffer,
+        scale: usize,
+        palette: &Palette,
+    ) -> Result<Vec<u8>, String> {
+        match self {
+            GraphicsProtocol::Sixel => Ok(encode_sixel(framebuffer, scale, palette)),
+            GraphicsProtocol::Kitty => encode_kitty(framebuffer, scale, palette),
+        }
+    }
+
+    /// escape sequences that remove a drawn image, clearing the screen is enough for Sixel
+    pub fn clear(&self) -> Vec<u8> {
+        match self {
+            GraphicsProtocol::Sixel => vec![],
+            GraphicsProtocol::Kitty => {
+                format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", KITTY_IMAGE_ID).into_bytes()
+            }
+        }
+    }
+}
+
+/// a PNG split into base64 chunks, `q=2` keeps the terminal from answering and `C=1` the cursor in place
+fn encode_kitty(
+    framebuffer: &Framebuffer,
+    scale: usize,
+    palette: &Palette,
+) -> Result<Vec<u8>, String> {
+    let payload = STANDARD.encode(framebuffer.to_png(scale, palette)?);
+    let chunks = payload.as_bytes().chunks(KITTY_CHUNK).collect::<Vec<_>>();
+
+    let mut output = vec![];
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = (index + 1 < chunks.len()) as u8;
+        let keys = match index {
+            0 => format!("a=T,f=100,i={},p=1,q=2,C=1,m={}", KITTY_IMAGE_ID, more),
+            _ => format!("q=2,m={}", more),
+        };
+        output.extend_from_slice(format!("\x1b_G{};", keys).as_bytes());
+        output.extend_from_slice(chunk);
+        output.extend_from_slice(b"\x1b\\");
+    }
+    Ok(output)
+}
+
+/// one color register per palette color and a band of six pixel rows per line,
+/// every color of a band paints its own pixels
+fn encode_sixel(framebuffer: &Framebuffer, scale: usize, palette: &Palette) -> Vec<u8> {
+    let (width, height) = (framebuffer.width * scale, framebuffer.height * scale);
+    let pixels = framebuffer.scaled(scale);
+
+    let mut sixel = format!("\x1bPq\"1;1;{};{}", width, height);
+    for (index, [r, g, b]) in palette.colors.iter().enumerate() {
+        let percent = |value: &u8| (*value as u32 * 100 + 127) / 255;
+        sixel += &format!("#{};2;{};{};{}", index, percent(r), percent(g), percent(b));
+    }
+
+    let bands = (0..height)
+        .step_by(6)
+        .map(|top| {
+            let rows = &pixels[top * width..(top + 6).min(height) * width];
+            (0..palette.colors.len() as u8)
+                .filter_map(|color| {
+                    let line = (0..width)
+                        .map(|x| {
+                            let bits = rows
+                                .chunks(width)
+                                .enumerate()
+                                .filter(|(_, row)| row[x] == color)
+                                .fold(0, |bits, (y, _)| bits | 1 << y);
+                            (63 + bits) as u8 as char
+                        })
+                        .collect::<String>();
+                    let line = line.trim_end_matches('?');
+                    (!line.is_empty()).then(|| format!("#{}{}", color, run_lengths(line)))
+                })
+                .collect::<Vec<_>>()
+                .join("$")
+        })
+        .collect::<Vec<_>>();
+
+    sixel += &bands.join("-");
+    sixel += "\x1b\\";
+    sixel.into_bytes()
+}
+
+/// replaces runs of four or more of the same sixel with `!<count><sixel>`
+fn run_lengths(line: &str) -> String {
+    let mut output = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        let mut count = 1;
+        while chars.next_if_eq(&char).is_some() {
+            count += 1;
+        }
+
+        if count >= 4 {
+            output += &format!("!{}{}", count, char);
+        } else {
+            output.extend(std::iter::repeat_n(char, count));
+        }
+    }
+    output
+}
+
+impl FromStr for GraphicsProtocol {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "sixel" => Ok(GraphicsProtocol::Sixel),
+            "kitty" => Ok(GraphicsProtocol::Kitty),
+            _ => Err(format!("unknown graphics protocol: {}", value)),
+        }
+    }
+}
+
+impl Display for GraphicsProtocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphicsProtocol::Sixel => write!(f, "sixel"),
+            GraphicsProtocol::Kitty => write!(f, "kitty"),
+        }
+    }
+}
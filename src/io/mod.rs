@@ -1,6 +1,13 @@
+use crate::audio::Audio;
 use crate::Platform;
 use serde::Serialize;
 
+#[cfg(feature = "cli")]
+pub mod graphics;
+#[cfg(feature = "cli")]
+pub mod renderer;
+#[cfg(feature = "cli")]
+pub mod terminal_debugger;
 #[cfg(feature = "cli")]
 pub mod terminal_io;
 
@@ -12,7 +19,10 @@ pub struct RenderContext<'a> {
     pub platform: Platform,
     pub title: &'a str,
     pub registries: &'a [u8; 16],
-    pub pixels: &'a [bool],
+    /// plane bits of every pixel
+    pub pixels: &'a [u8],
+    pub sound_playing: bool,
+    pub audio: &'a Audio,
 }
 
 pub trait IO {
@@ -21,6 +31,19 @@ pub trait IO {
     fn get_just_pressed(&self) -> Vec<u8>;
 }
 
+/// input where no key is ever pressed, for running programs without anyone playing
+pub struct NoInput;
+
+impl IO for NoInput {
+    fn is_code_pressed(&self, _code: u8) -> bool {
+        false
+    }
+
+    fn get_just_pressed(&self) -> Vec<u8> {
+        vec![]
+    }
+}
+
 pub fn key_to_char(value: u8) -> Option<char> {
     match value {
         0x0 => Some('x'),
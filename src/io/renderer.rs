@@ -0,0 +1,191 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// ways of drawing the display as text, from the largest to the most compact
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Renderer {
+    /// two full blocks per pixel
+    Blocks,
+    /// upper and lower half blocks, one character per 1x2 pixels
+    HalfBlocks,
+    /// quadrant blocks, one character per 2x2 pixels
+    Quadrants,
+    /// Braille dots, one character per 2x4 pixels
+    Braille,
+}
+
+/// quadrant blocks by the bits of their top left, top right, bottom left and bottom right pixel
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Braille dot bits of the pixels of a 2x4 cell, row by row
+const BRAILLE_DOTS: [u32; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+impl Renderer {
+    pub const ALL: [Renderer; 4] = [
+        Renderer::Blocks,
+        Renderer::HalfBlocks,
+        Renderer::Quadrants,
+        Renderer::Braille,
+    ];
+
+    /// width and height of the pixels drawn by one character
+    fn cell(&self) -> (usize, usize) {
+        match self {
+            Renderer::Blocks => (1, 1),
+            Renderer::HalfBlocks => (1, 2),
+            Renderer::Quadrants => (2, 2),
+            Renderer::Braille => (2, 4),
+        }
+    }
+
+    /// characters a row of `width` pixels takes
+    pub fn columns(&self, width: usize) -> usize {
+        match self {
+            Renderer::Blocks => width * 2,
+            _ => width.div_ceil(self.cell().0),
+        }
+    }
+
+    /// lines `height` rows of pixels take
+    pub fn rows(&self, height: usize) -> usize {
+        height.div_ceil(self.cell().1)
+    }
+
+    /// the character of a cell, bit `y * cell width + x` is set for every lit pixel
+    fn glyph(&self, bits: usize) -> char {
+        match self {
+            Renderer::Blocks => [' ', '█'][bits],
+            Renderer::HalfBlocks => [' ', '▀', '▄', '█'][bits],
+            Renderer::Quadrants => QUADRANTS[bits],
+            Renderer::Braille if bits == 0 => ' ',
+            Renderer::Braille => {
+                let dots = (0..8)
+                    .filter(|bit| bits & 1 << bit != 0)
+                    .fold(0, |dots, bit| dots | BRAILLE_DOTS[bit]);
+                // the dots only take the low byte, U+2800 to U+28FF are all Braille patterns
+                char::from_u32(0x2800 + dots).unwrap()
+            }
+        }
+    }
+
+    /// characters printed for every cell, blocks are two wide to look square
+    pub fn glyph_width(&self) -> usize {
+        match self {
+            Renderer::Blocks => 2,
+            _ => 1,
+        }
+    }
+
+    /// the display as rows of cells
+    ///
+    /// a character only has two colors, so cells with pixels in more get the most common plane bits as
+    /// the foreground, the background stays off if any pixel is and the other pixels count as background
+    pub fn cells(&self, pixels: &[u8], width: usize) -> Vec<Vec<Cell>> {
+        let height = pixels.len() / width;
+        let (cell_width, cell_height) = self.cell();
+        let pixel = |x: usize, y: usize| match x < width && y < height {
+            true => pixels[y * width + x] & 0b11,
+            false => 0,
+        };
+
+        (0..self.rows(height))
+            .map(|row| {
+                (0..width.div_ceil(cell_width))
+                    .map(|column| {
+                        let cell_pixels = (0..cell_width * cell_height)
+                            .map(|index| {
+                                pixel(
+                                    column * cell_width + index % cell_width,
+                                    row * cell_height + index / cell_width,
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        let most_common = |except: u8| {
+                            (1..4)
+                                .filter(|value| *value != except)
+                                .map(|value| {
+                                    let count = cell_pixels.iter().filter(|p| **p == value).count();
+                                    (count, value)
+                                })
+                                .filter(|(count, _)| *count > 0)
+                                .max_by_key(|(count, value)| (*count, 4 - value))
+                                .map_or(0, |(_, value)| value)
+                        };
+
+                        let foreground = most_common(0);
+                        let background = match cell_pixels.contains(&0) {
+                            true => 0,
+                            false => most_common(foreground),
+                        };
+                        let bits = cell_pixels
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, pixel)| foreground != 0 && **pixel == foreground)
+                            .fold(0, |bits, (index, _)| bits | 1 << index);
+
+                        Cell {
+                            glyph: self.glyph(bits),
+                            foreground,
+                            background,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// the display as lines of text, every pixel with any plane set is lit
+    pub fn render(&self, pixels: &[u8], width: usize) -> Vec<String> {
+        let lit = pixels
+            .iter()
+            .map(|pixel| (*pixel != 0) as u8)
+            .collect::<Vec<_>>();
+
+        self.cells(&lit, width)
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .flat_map(|cell| std::iter::repeat_n(cell.glyph, self.glyph_width()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// one character of a rendering with the plane bits of its colors
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub glyph: char,
+    /// plane bits of the pixels the glyph draws
+    pub foreground: u8,
+    /// plane bits of the pixels around them
+    pub background: u8,
+}
+
+impl FromStr for Renderer {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "blocks" => Ok(Renderer::Blocks),
+            "half" | "half-blocks" => Ok(Renderer::HalfBlocks),
+            "quadrants" => Ok(Renderer::Quadrants),
+            "braille" => Ok(Renderer::Braille),
+            _ => Err(format!("unknown renderer: {}", value)),
+        }
+    }
+}
+
+impl Display for Renderer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Renderer::Blocks => "blocks",
+            Renderer::HalfBlocks => "half-blocks",
+            Renderer::Quadrants => "quadrants",
+            Renderer::Braille => "braille",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,816 @@
+use crate::debugger::{Condition, Debugger, Register, StopReason, Watch};
+use crate::disassembler::{Disassembly, PROGRAM_START};
+use crate::io::renderer::Renderer;
+use crate::io::terminal_io::{load_emulator, TerminalIO, FRAME};
+use crate::Emulator;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{ContentStyle, PrintStyledContent, StyledContent, Stylize};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, size, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::collections::BTreeSet;
+use std::io::{stdout, Stdout, Write};
+use std::time::{Duration, Instant};
+
+/// `chip-8 debug <rom>` takes the same options as running a rom
+pub fn run(args: impl Iterator<Item = String>) {
+    match load_emulator(args) {
+        Ok(emulator) => TerminalDebugger::new(emulator).start(),
+        Err(err) => println!("{}", err),
+    }
+}
+
+/// width of the register, stack and breakpoint column
+const SIDE_WIDTH: u16 = 30;
+
+/// width of the disassembly pane
+const CODE_WIDTH: u16 = 44;
+
+/// bytes per row of the memory pane
+const MEMORY_ROW: usize = 16;
+
+/// registers the cursor of the register pane moves over
+const EDITABLE_REGISTERS: [Register; 20] = [
+    Register::V(0x0),
+    Register::V(0x1),
+    Register::V(0x2),
+    Register::V(0x3),
+    Register::V(0x4),
+    Register::V(0x5),
+    Register::V(0x6),
+    Register::V(0x7),
+    Register::V(0x8),
+    Register::V(0x9),
+    Register::V(0xA),
+    Register::V(0xB),
+    Register::V(0xC),
+    Register::V(0xD),
+    Register::V(0xE),
+    Register::V(0xF),
+    Register::I,
+    Register::Pc,
+    Register::Dt,
+    Register::St,
+];
+
+const HELP: &str =
+    "F5 run/pause  F6 step  F7 step over  F8 step out  F9 toggle  F10 frame  Tab pane  Enter edit  Esc quit";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pane {
+    Code,
+    Registers,
+    Memory,
+}
+
+/// what the prompt line edits once Enter is pressed
+#[derive(Debug, Copy, Clone)]
+enum Edit {
+    Register(Register),
+    Memory(usize),
+    Condition(u16),
+}
+
+#[derive(Debug)]
+struct Prompt {
+    label: String,
+    input: String,
+    edit: Edit,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Rect {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+/// the whole terminal, drawn in one go so nothing flickers
+struct Canvas {
+    width: u16,
+    height: u16,
+    cells: Vec<(char, ContentStyle)>,
+}
+
+impl Canvas {
+    fn new(width: u16, height: u16) -> Canvas {
+        Canvas {
+            width,
+            height,
+            cells: vec![(' ', ContentStyle::new()); width as usize * height as usize],
+        }
+    }
+
+    /// prints `text` clipped to the canvas, returns the column after it
+    fn print(&mut self, x: u16, y: u16, text: &str, style: ContentStyle) -> u16 {
+        let mut x = x;
+        for char in text.chars() {
+            if x < self.width && y < self.height {
+                self.cells[y as usize * self.width as usize + x as usize] = (char, style);
+            }
+            x += 1;
+        }
+        x
+    }
+
+    /// draws a border around `rect` and returns the space inside of it
+    fn frame(&mut self, rect: Rect, title: &str, focused: bool) -> Rect {
+        let style = match focused {
+            true => ContentStyle::new().bold(),
+            false => ContentStyle::new().dim(),
+        };
+        let inner = rect.width.saturating_sub(2) as usize;
+
+        self.print(rect.x, rect.y, &format!("╭{}╮", "─".repeat(inner)), style);
+        self.print(rect.x + 2, rect.y, &format!(" {} ", title), style);
+        for y in 1..rect.height.saturating_sub(1) {
+            self.print(rect.x, rect.y + y, "│", style);
+            self.print(rect.x + rect.width - 1, rect.y + y, "│", style);
+        }
+        let bottom = rect.y + rect.height.saturating_sub(1);
+        self.print(rect.x, bottom, &format!("╰{}╯", "─".repeat(inner)), style);
+
+        Rect {
+            x: rect.x + 1,
+            y: rect.y + 1,
+            width: rect.width.saturating_sub(2),
+            height: rect.height.saturating_sub(2),
+        }
+    }
+
+    fn flush(&self, stdout: &mut Stdout) {
+        for y in 0..self.height {
+            queue!(stdout, MoveTo(0, y)).unwrap();
+
+            // the very last cell would scroll the terminal
+            let width = match y + 1 == self.height {
+                true => self.width.saturating_sub(1),
+                false => self.width,
+            };
+            let row = &self.cells[y as usize * self.width as usize..][..width as usize];
+
+            let mut start = 0;
+            while start < row.len() {
+                let style = row[start].1;
+                let end = row[start..]
+                    .iter()
+                    .position(|(_, cell)| *cell != style)
+                    .map_or(row.len(), |length| start + length);
+                let text = row[start..end]
+                    .iter()
+                    .map(|(char, _)| char)
+                    .collect::<String>();
+                queue!(stdout, PrintStyledContent(StyledContent::new(style, text))).unwrap();
+                start = end;
+            }
+        }
+
+        stdout.flush().unwrap()
+    }
+}
+
+/// a full screen debugger, the program gets the keys that are not used by the debugger
+struct TerminalDebugger {
+    debugger: Debugger,
+    io: TerminalIO,
+    disassembly: Disassembly,
+    running: bool,
+    focus: Pane,
+    /// selected line of the disassembly, follows the program counter when `None`
+    code_cursor: Option<u16>,
+    register_cursor: usize,
+    memory_cursor: usize,
+    /// first row shown in the memory pane
+    memory_scroll: usize,
+    prompt: Option<Prompt>,
+    status: String,
+    quit: bool,
+}
+
+impl TerminalDebugger {
+    fn new(emulator: Emulator) -> TerminalDebugger {
+        let mut terminal_debugger = TerminalDebugger {
+            debugger: Debugger::new(emulator),
+            io: TerminalIO::new(),
+            disassembly: Disassembly {
+                lines: vec![],
+                labels: Default::default(),
+            },
+            running: false,
+            focus: Pane::Code,
+            code_cursor: None,
+            register_cursor: 0,
+            memory_cursor: PROGRAM_START as usize,
+            memory_scroll: PROGRAM_START as usize / MEMORY_ROW,
+            prompt: None,
+            status: "paused, press F5 to run".to_string(),
+            quit: false,
+        };
+        terminal_debugger.update_disassembly();
+        terminal_debugger
+    }
+
+    fn start(mut self) {
+        let mut stdout = stdout();
+        enable_raw_mode().unwrap();
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            Hide,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )
+        .unwrap();
+
+        let mut next_frame = Instant::now();
+        while !self.quit {
+            let now = Instant::now();
+            if now >= next_frame {
+                if self.running {
+                    self.run_frame();
+                }
+                next_frame += FRAME;
+                // running behind, so don't try to catch up
+                if next_frame < now {
+                    next_frame = now + FRAME;
+                }
+            }
+
+            self.render(&mut stdout);
+
+            let timeout = next_frame.saturating_duration_since(Instant::now());
+            if poll(timeout).unwrap() {
+                while poll(Duration::ZERO).unwrap() {
+                    if let Event::Key(key) = read().unwrap() {
+                        if key.kind == KeyEventKind::Press {
+                            self.handle_key(key);
+                        }
+                    }
+                }
+            }
+        }
+
+        execute!(
+            stdout,
+            PopKeyboardEnhancementFlags,
+            Show,
+            LeaveAlternateScreen
+        )
+        .unwrap();
+        disable_raw_mode().unwrap();
+    }
+
+    fn run_frame(&mut self) {
+        let reason = self.debugger.run_frame(&self.io);
+        self.io.just_pressed.lock().unwrap().clear();
+
+        if !matches!(reason, StopReason::Frame { .. }) {
+            self.stopped(reason);
+        }
+    }
+
+    fn stopped(&mut self, reason: StopReason) {
+        self.running = false;
+        self.code_cursor = None;
+        self.status = reason.to_string();
+        self.update_disassembly();
+    }
+
+    /// runs a single step like `Debugger::step`, pausing the program
+    fn step(&mut self, step: fn(&mut Debugger, &TerminalIO) -> StopReason) {
+        let reason = step(&mut self.debugger, &self.io);
+        self.io.just_pressed.lock().unwrap().clear();
+        self.stopped(reason);
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if self.prompt.is_some() {
+            self.handle_prompt_key(key.code);
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::F(5) => {
+                self.running = !self.running;
+                self.status = match self.running {
+                    true => "running".to_string(),
+                    false => "paused".to_string(),
+                };
+                self.code_cursor = None;
+                self.update_disassembly();
+            }
+            KeyCode::F(6) => self.step(|debugger, io| debugger.step(io)),
+            KeyCode::F(7) => self.step(|debugger, io| debugger.step_over(io)),
+            KeyCode::F(8) => self.step(|debugger, io| debugger.step_out(io)),
+            KeyCode::F(10) => self.step(|debugger, io| debugger.run_frame(io)),
+            KeyCode::F(9) => self.toggle(),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::Code => Pane::Registers,
+                    Pane::Registers => Pane::Memory,
+                    Pane::Memory => Pane::Code,
+                }
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Pane::Code => Pane::Memory,
+                    Pane::Registers => Pane::Code,
+                    Pane::Memory => Pane::Registers,
+                }
+            }
+            KeyCode::Up => self.move_cursor(-1, -1, -(MEMORY_ROW as isize)),
+            KeyCode::Down => self.move_cursor(1, 1, MEMORY_ROW as isize),
+            KeyCode::Left => self.move_cursor(0, -1, -1),
+            KeyCode::Right => self.move_cursor(0, 1, 1),
+            KeyCode::PageUp => self.move_cursor(-16, -4, -(MEMORY_ROW as isize) * 16),
+            KeyCode::PageDown => self.move_cursor(16, 4, MEMORY_ROW as isize * 16),
+            KeyCode::Enter => self.open_prompt(),
+            code => self.io.press(code),
+        }
+    }
+
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+
+        match code {
+            KeyCode::Char(char) => prompt.input.push(char),
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.status = match self.apply(prompt.edit, prompt.input.trim()) {
+                        Ok(status) => status,
+                        Err(err) => err,
+                    };
+                    self.update_disassembly();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// the address the disassembly cursor is on
+    fn code_address(&self) -> u16 {
+        self.code_cursor
+            .unwrap_or_else(|| self.debugger.emulator().memory().read_pc())
+    }
+
+    fn selected_register(&self) -> Register {
+        EDITABLE_REGISTERS[self.register_cursor]
+    }
+
+    /// moves the cursor of the focused pane, by lines, registers or bytes
+    fn move_cursor(&mut self, lines: isize, registers: isize, bytes: isize) {
+        match self.focus {
+            Pane::Code => {
+                let lines_count = self.disassembly.lines.len();
+                if lines == 0 || lines_count == 0 {
+                    return;
+                }
+
+                let address = self.code_address();
+                let index = self
+                    .disassembly
+                    .lines
+                    .partition_point(|line| line.address <= address)
+                    .saturating_sub(1);
+                let index = index.saturating_add_signed(lines).min(lines_count - 1);
+                self.code_cursor = Some(self.disassembly.lines[index].address);
+            }
+            Pane::Registers => {
+                self.register_cursor = self
+                    .register_cursor
+                    .saturating_add_signed(registers)
+                    .min(EDITABLE_REGISTERS.len() - 1);
+            }
+            Pane::Memory => {
+                let size = self.debugger.emulator().memory().size();
+                self.memory_cursor = self
+                    .memory_cursor
+                    .saturating_add_signed(bytes)
+                    .min(size - 1);
+            }
+        }
+    }
+
+    /// toggles a breakpoint, watched register or watchpoint at the cursor
+    fn toggle(&mut self) {
+        self.status = match self.focus {
+            Pane::Code => {
+                let address = self.code_address();
+                match self.debugger.remove_breakpoint(address) {
+                    true => format!("removed breakpoint at {:#06x}", address),
+                    false => {
+                        self.debugger.add_breakpoint(address, None);
+                        format!("added breakpoint at {:#06x}", address)
+                    }
+                }
+            }
+            Pane::Registers => {
+                let register = self.selected_register();
+                match self.debugger.unwatch_register(register) {
+                    true => format!("stopped watching {}", register),
+                    false => {
+                        self.debugger.watch_register(register);
+                        format!("watching {}", register)
+                    }
+                }
+            }
+            Pane::Memory => {
+                let address = self.memory_cursor as u16;
+                match self.debugger.remove_watchpoint(address) {
+                    true => format!("removed watchpoint at {:#06x}", address),
+                    false => {
+                        self.debugger.add_watchpoint(address, Watch::ReadWrite);
+                        format!("watching {:#06x}", address)
+                    }
+                }
+            }
+        }
+    }
+
+    fn open_prompt(&mut self) {
+        self.prompt = Some(match self.focus {
+            Pane::Code => {
+                let address = self.code_address();
+                let condition = self
+                    .debugger
+                    .breakpoints()
+                    .find(|breakpoint| breakpoint.address == address)
+                    .and_then(|breakpoint| breakpoint.condition);
+                Prompt {
+                    label: format!("break at {:#06x} if", address),
+                    input: condition
+                        .map(|condition| condition.to_string())
+                        .unwrap_or_default(),
+                    edit: Edit::Condition(address),
+                }
+            }
+            Pane::Registers => {
+                let register = self.selected_register();
+                Prompt {
+                    label: format!("{} =", register),
+                    input: format!("{:x}", register.read(self.debugger.emulator())),
+                    edit: Edit::Register(register),
+                }
+            }
+            Pane::Memory => Prompt {
+                label: format!("bytes at {:#06x} =", self.memory_cursor),
+                input: String::new(),
+                edit: Edit::Memory(self.memory_cursor),
+            },
+        });
+    }
+
+    /// applies what was typed on the prompt, returns the new status
+    fn apply(&mut self, edit: Edit, input: &str) -> Result<String, String> {
+        let emulator = self.debugger.emulator_mut();
+
+        match edit {
+            Edit::Register(register) => {
+                let value = parse_hex(input)?;
+                register.write(emulator, value)?;
+                Ok(format!("{} = {:#x}", register, value))
+            }
+            Edit::Memory(address) => {
+                let bytes = input
+                    .split_whitespace()
+                    .map(|byte| {
+                        parse_hex(byte).and_then(|value| {
+                            u8::try_from(value).map_err(|_| format!("{} is not a byte", byte))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                emulator
+                    .memory_mut()
+                    .write_slice(address, &bytes)
+                    .map_err(|err| err.to_string())?;
+
+                let size = emulator.memory().size();
+                self.memory_cursor = (address + bytes.len()).min(size - 1);
+                Ok(format!("wrote {} bytes at {:#06x}", bytes.len(), address))
+            }
+            Edit::Condition(address) => {
+                let condition = match input {
+                    "" => None,
+                    input => Some(input.parse::<Condition>()?),
+                };
+                self.debugger.add_breakpoint(address, condition);
+                Ok(match condition {
+                    Some(condition) => format!("break at {:#06x} if {}", address, condition),
+                    None => format!("added breakpoint at {:#06x}", address),
+                })
+            }
+        }
+    }
+
+    fn update_disassembly(&mut self) {
+        self.disassembly = self.debugger.disassemble();
+    }
+
+    fn render(&mut self, stdout: &mut Stdout) {
+        if self.running {
+            self.update_disassembly();
+        }
+
+        let (width, height) = size().unwrap();
+        let mut canvas = Canvas::new(width, height);
+        let platform = self.debugger.emulator().platform();
+
+        let display = Rect {
+            x: 0,
+            y: 0,
+            width: platform.width() as u16 + 2,
+            height: platform.height() as u16 / 2 + 2,
+        };
+        let registers = Rect {
+            x: display.width,
+            y: 0,
+            width: SIDE_WIDTH,
+            height: 9,
+        };
+        let stack = Rect {
+            x: display.width,
+            y: registers.height,
+            width: SIDE_WIDTH,
+            height: display.height.saturating_sub(registers.height).max(3),
+        };
+        let bottom = display.height.max(registers.height + stack.height);
+        let bottom_height = height.saturating_sub(bottom + 2).max(3);
+        let code = Rect {
+            x: 0,
+            y: bottom,
+            width: CODE_WIDTH,
+            height: bottom_height,
+        };
+        let memory = Rect {
+            x: CODE_WIDTH,
+            y: bottom,
+            width: 7 + MEMORY_ROW as u16 * 4 + 2,
+            height: bottom_height,
+        };
+        let breakpoints = Rect {
+            x: memory.x + memory.width,
+            y: bottom,
+            width: SIDE_WIDTH,
+            height: bottom_height,
+        };
+
+        self.draw_display(&mut canvas, display);
+        self.draw_registers(&mut canvas, registers);
+        self.draw_stack(&mut canvas, stack);
+        self.draw_code(&mut canvas, code);
+        self.draw_memory(&mut canvas, memory);
+        self.draw_breakpoints(&mut canvas, breakpoints);
+        self.draw_status(&mut canvas, bottom + bottom_height);
+
+        canvas.flush(stdout);
+    }
+
+    fn draw_display(&self, canvas: &mut Canvas, rect: Rect) {
+        let emulator = self.debugger.emulator();
+        let title = format!(
+            "{} frame {}",
+            emulator.program_name(),
+            self.debugger.frame()
+        );
+        let inner = canvas.frame(rect, &title, false);
+
+        let context = emulator.get_render_context();
+        let lines = Renderer::HalfBlocks.render(context.pixels, context.platform.width());
+        for (row, line) in lines.iter().take(inner.height as usize).enumerate() {
+            canvas.print(inner.x, inner.y + row as u16, line, ContentStyle::new());
+        }
+    }
+
+    fn draw_registers(&self, canvas: &mut Canvas, rect: Rect) {
+        let inner = canvas.frame(rect, "Registers", self.focus == Pane::Registers);
+        let emulator = self.debugger.emulator();
+        let watched = self.debugger.watched_registers().collect::<BTreeSet<_>>();
+
+        for (index, register) in EDITABLE_REGISTERS.iter().enumerate() {
+            let (column, row, digits) = match register {
+                Register::V(x) => (*x as u16 % 4 * 7, *x as u16 / 4, 2),
+                Register::I | Register::Pc => ((index as u16 - 16) * 14, 4, 4),
+                _ => ((index as u16 - 18) * 14, 5, 2),
+            };
+
+            let mut name_style = ContentStyle::new();
+            if watched.contains(register) {
+                name_style = name_style.yellow();
+            }
+            let mut value_style = ContentStyle::new();
+            if self.focus == Pane::Registers && index == self.register_cursor {
+                value_style = value_style.reverse();
+            }
+
+            let x = canvas.print(
+                inner.x + column,
+                inner.y + row,
+                &format!("{:<3}", register.to_string()),
+                name_style,
+            );
+            let value = format!("{:0digits$x}", register.read(emulator), digits = digits);
+            canvas.print(x, inner.y + row, &value, value_style);
+        }
+
+        canvas.print(
+            inner.x,
+            inner.y + 6,
+            &format!("SP {}", Register::Sp.read(emulator)),
+            ContentStyle::new(),
+        );
+    }
+
+    fn draw_stack(&self, canvas: &mut Canvas, rect: Rect) {
+        let inner = canvas.frame(rect, "Stack", false);
+        let stack = self.debugger.emulator().memory().stack();
+
+        for (row, address) in stack.iter().rev().take(inner.height as usize).enumerate() {
+            canvas.print(
+                inner.x,
+                inner.y + row as u16,
+                &format!("{:>2} {:#06x}", stack.len() - row, address),
+                ContentStyle::new(),
+            );
+        }
+    }
+
+    fn draw_code(&self, canvas: &mut Canvas, rect: Rect) {
+        let inner = canvas.frame(rect, "Disassembly", self.focus == Pane::Code);
+        let pc = self.debugger.emulator().memory().read_pc();
+        let selected = self.code_address();
+
+        // labels get a row of their own
+        let mut rows = vec![];
+        for line in &self.disassembly.lines {
+            if let Some(label) = self.disassembly.label(line.address) {
+                rows.push((None, format!(": {}", label)));
+            }
+            rows.push((Some(line.address), self.disassembly.source(line)));
+        }
+
+        let selected_row = rows
+            .iter()
+            .rposition(|(address, _)| address.is_some_and(|address| address <= selected))
+            .unwrap_or(0);
+        let visible = inner.height as usize;
+        let first = selected_row
+            .saturating_sub(visible / 2)
+            .min(rows.len().saturating_sub(visible));
+
+        for (row, (address, source)) in rows.iter().skip(first).take(visible).enumerate() {
+            let y = inner.y + row as u16;
+            let Some(address) = *address else {
+                let text = format!("{:.width$}", source, width = inner.width as usize);
+                canvas.print(inner.x, y, &text, ContentStyle::new().dim());
+                continue;
+            };
+
+            let breakpoint = self
+                .debugger
+                .breakpoints()
+                .find(|breakpoint| breakpoint.address == address);
+            let marker = match breakpoint {
+                Some(breakpoint) if breakpoint.condition.is_some() => "◐",
+                Some(_) => "●",
+                None => " ",
+            };
+            canvas.print(inner.x, y, marker, ContentStyle::new().red());
+
+            let mut style = ContentStyle::new();
+            if address == pc {
+                style = style.green().bold();
+            }
+            if self.focus == Pane::Code && address == selected {
+                style = style.reverse();
+            }
+            let pointer = if address == pc { '>' } else { ' ' };
+            let text = format!("{}{:04x}  {}", pointer, address, source);
+            let text = format!("{:<width$.width$}", text, width = inner.width as usize - 1);
+            canvas.print(inner.x + 1, y, &text, style);
+        }
+    }
+
+    fn draw_memory(&mut self, canvas: &mut Canvas, rect: Rect) {
+        let inner = canvas.frame(rect, "Memory", self.focus == Pane::Memory);
+        let emulator = self.debugger.emulator();
+        let memory = emulator.memory();
+        let index = memory.read_index_register() as usize;
+        let watched = self
+            .debugger
+            .watchpoints()
+            .map(|(address, _)| address as usize)
+            .collect::<BTreeSet<_>>();
+
+        // keep the cursor in view
+        let visible = (inner.height as usize).max(1);
+        let cursor_row = self.memory_cursor / MEMORY_ROW;
+        if cursor_row < self.memory_scroll {
+            self.memory_scroll = cursor_row;
+        } else if cursor_row >= self.memory_scroll + visible {
+            self.memory_scroll = cursor_row + 1 - visible;
+        }
+
+        let rows = memory.size() / MEMORY_ROW;
+        for row in 0..visible.min(rows - self.memory_scroll) {
+            let start = (self.memory_scroll + row) * MEMORY_ROW;
+            let y = inner.y + row as u16;
+            let bytes = memory.read_slice(start, MEMORY_ROW).unwrap_or_default();
+
+            let mut x = canvas.print(
+                inner.x,
+                y,
+                &format!("{:04x} ", start),
+                ContentStyle::new().dim(),
+            );
+            for (offset, byte) in bytes.iter().enumerate() {
+                let address = start + offset;
+                let mut style = ContentStyle::new();
+                if address == index {
+                    style = style.cyan();
+                }
+                if watched.contains(&address) {
+                    style = style.magenta();
+                }
+                if self.focus == Pane::Memory && address == self.memory_cursor {
+                    style = style.reverse();
+                }
+                x = canvas.print(x + 1, y, &format!("{:02x}", byte), style);
+            }
+
+            let text = bytes
+                .iter()
+                .map(|&byte| match byte {
+                    0x20..=0x7E => byte as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            canvas.print(x + 2, y, &text, ContentStyle::new().dim());
+        }
+    }
+
+    fn draw_breakpoints(&self, canvas: &mut Canvas, rect: Rect) {
+        let inner = canvas.frame(rect, "Breakpoints", false);
+
+        let mut rows = vec![];
+        for breakpoint in self.debugger.breakpoints() {
+            rows.push(match breakpoint.condition {
+                Some(condition) => format!("● {:#06x} if {}", breakpoint.address, condition),
+                None => format!("● {:#06x}", breakpoint.address),
+            });
+        }
+        for (address, watch) in self.debugger.watchpoints() {
+            let watch = match watch {
+                Watch::Read => "read",
+                Watch::Write => "write",
+                Watch::ReadWrite => "access",
+            };
+            rows.push(format!("◆ {:#06x} {}", address, watch));
+        }
+        for register in self.debugger.watched_registers() {
+            rows.push(format!("◆ {} changes", register));
+        }
+
+        for (row, text) in rows.iter().take(inner.height as usize).enumerate() {
+            let text = format!("{:.width$}", text, width = inner.width as usize);
+            canvas.print(inner.x, inner.y + row as u16, &text, ContentStyle::new());
+        }
+    }
+
+    fn draw_status(&self, canvas: &mut Canvas, y: u16) {
+        match &self.prompt {
+            Some(prompt) => {
+                let text = format!("{} {}", prompt.label, prompt.input);
+                let x = canvas.print(0, y, &text, ContentStyle::new().bold());
+                canvas.print(x, y, " ", ContentStyle::new().reverse());
+            }
+            None => {
+                let state = match self.running {
+                    true => " RUNNING ",
+                    false => " PAUSED ",
+                };
+                let x = canvas.print(0, y, state, ContentStyle::new().reverse());
+                canvas.print(x + 1, y, &self.status, ContentStyle::new());
+            }
+        }
+
+        canvas.print(0, y + 1, HELP, ContentStyle::new().dim());
+    }
+}
+
+/// parses a hexadecimal number with or without `0x`
+fn parse_hex(value: &str) -> Result<u16, String> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("{:?} is not a hexadecimal number", value))
+}
@@ -1,4 +1,17 @@
+use crate::assembler::assemble;
+use crate::coverage::Coverage;
+use crate::error::EmulatorError;
+use crate::font::FontSet;
+use crate::framebuffer::Framebuffer;
+use crate::inspector::Inspector;
+use crate::io::graphics::GraphicsProtocol;
+use crate::io::renderer::Renderer;
 use crate::io::{char_to_key, key_to_char, RenderContext, IO};
+use crate::palette::Theme;
+use crate::profiler::Profiler;
+use crate::recorder::{Recorder, RecordingFormat};
+use crate::rewind::Rewind;
+use crate::trace::Tracer;
 use crate::{Emulator, Platform};
 use async_std::stream::StreamExt;
 use crossterm::cursor::{Hide, MoveTo, MoveToColumn, Show};
@@ -6,48 +19,349 @@ use crossterm::event::{
     DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode, KeyEventKind,
     KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
 };
-use crossterm::style::{Print, Stylize};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use crossterm::style::{Color, Print, Stylize};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, size, window_size, Clear, ClearType};
 use crossterm::{execute, queue};
-use std::io::{stdout, Stdout, Write};
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::{stdout, BufWriter, Stdout, Write};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
-use std::time::Duration;
-use std::{env, fs};
-
-pub fn run() {
-    let program_path = env::args().nth(1);
-
-    let program_path = match program_path {
-        Some(program_path) => program_path,
-        None => {
-            println!("Please specify a program path");
-            return;
+use std::time::{Duration, Instant};
+
+/// runs a program, `--trace <file>` writes every executed instruction to the file
+///
+/// `--profile <file>` writes a profile to the file on exit and its call stacks next to it with the extension `folded`,
+/// `--coverage <file>` writes how every byte of memory was used to the file on exit
+///
+/// `--theme <name>` colors the display and everything around it, `--palette <colors>` replaces the display colors,
+/// they apply to the F9 screenshots and F10 recordings as well, which `--scale <n>` and `--record-format <gif|y4m>` set up,
+/// `--renderer <blocks|half-blocks|quadrants|braille>` draws the display with the given characters
+/// instead of the largest ones that fit the terminal
+///
+/// terminals that support Sixel or the kitty graphics protocol get the display as an image,
+/// `--graphics <sixel|kitty|text>` picks the protocol instead of guessing it from the environment
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let mut trace_path = None;
+    let mut profile_path = None;
+    let mut coverage_path = None;
+    let mut terminal_io = TerminalIO::new();
+    let mut graphics = GraphicsProtocol::detect(|name| env::var(name).ok());
+    let mut theme = Theme::default();
+    let mut palette = None;
+    let mut rest = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" => trace_path = args.next(),
+            "--profile" => profile_path = args.next(),
+            "--coverage" => coverage_path = args.next(),
+            "--theme" => match args.next().unwrap_or_default().parse() {
+                Ok(value) => theme = value,
+                Err(err) => {
+                    println!("{}", err);
+                    return;
+                }
+            },
+            "--palette" => palette = args.next(),
+            "--scale" => {
+                let value = args.next().unwrap_or_default();
+                match value.parse::<usize>() {
+                    Ok(scale) if scale > 0 => terminal_io = terminal_io.with_scale(scale),
+                    _ => {
+                        println!("invalid scale {:?}", value);
+                        return;
+                    }
+                }
+            }
+            "--graphics" => match args.next().unwrap_or_default().as_str() {
+                "text" => graphics = None,
+                value => match value.parse() {
+                    Ok(protocol) => graphics = Some(protocol),
+                    Err(err) => {
+                        println!("{}", err);
+                        return;
+                    }
+                },
+            },
+            "--renderer" => match args.next().unwrap_or_default().parse() {
+                Ok(renderer) => terminal_io = terminal_io.with_renderer(renderer),
+                Err(err) => {
+                    println!("{}", err);
+                    return;
+                }
+            },
+            "--record-format" => match args.next().unwrap_or_default().parse() {
+                Ok(format) => terminal_io = terminal_io.with_recording_format(format),
+                Err(err) => {
+                    println!("{}", err);
+                    return;
+                }
+            },
+            _ => rest.push(arg),
         }
-    };
+    }
 
-    println!("{}", program_path);
-    let program = fs::read(&program_path);
+    if let Some(protocol) = graphics {
+        terminal_io = terminal_io.with_graphics(protocol);
+    }
+    if let Some(colors) = palette {
+        match theme.palette.with_colors(&colors) {
+            Ok(palette) => theme = theme.with_palette(palette),
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        }
+    }
+    terminal_io = terminal_io.with_theme(theme);
 
-    let program = match program {
-        Ok(program) => program,
+    let emulator = match load_emulator(rest.into_iter()) {
+        Ok(emulator) => emulator,
         Err(err) => {
             println!("{}", err);
             return;
         }
     };
+    let program_name = emulator.program_name().to_string();
+    let program = emulator.program_memory().to_vec();
+    let platform = emulator.platform();
+    println!("{}", program_name);
+
+    let tracer = match &trace_path {
+        Some(trace_path) => match File::create(trace_path) {
+            Ok(file) => Some(Tracer::new(BufWriter::new(file))),
+            Err(err) => {
+                println!("could not create {}: {}", trace_path, err);
+                return;
+            }
+        },
+        None => None,
+    };
+    let profiler = profile_path.as_ref().map(|_| Profiler::new());
+    let coverage = coverage_path.as_ref().map(|_| Coverage::new());
 
-    let io = TerminalIO::new();
-    let emulator = Emulator::new(program, program_path, Platform::SuperChip, &io);
+    let mut inspector = ((tracer, profiler), coverage);
+    TerminalIO::start_inspected(terminal_io, emulator, &mut inspector);
+    let ((tracer, profiler), coverage) = inspector;
 
-    TerminalIO::start(io, emulator);
+    if let (Some(tracer), Some(trace_path)) = (tracer, trace_path) {
+        if let Err(err) = tracer.finish() {
+            println!("could not write {}: {}", trace_path, err);
+        }
+    }
+    if let (Some(profiler), Some(profile_path)) = (profiler, profile_path) {
+        if let Err(err) = write_profile(&profiler, &profile_path, &program_name) {
+            println!("could not write the profile: {}", err);
+        }
+    }
+    if let (Some(coverage), Some(coverage_path)) = (coverage, coverage_path) {
+        let written = create_file(Path::new(&coverage_path)).and_then(|mut output| {
+            coverage
+                .write_map(&mut output)
+                .and_then(|_| output.flush())
+                .map_err(|err| format!("{}: {}", coverage_path, err))
+        });
+        match written {
+            Ok(()) => println!("{}", coverage.summary(&program, platform)),
+            Err(err) => println!("could not write the coverage: {}", err),
+        }
+    }
+}
+
+/// writes the report of `profiler` to `path` and its call stacks next to it
+fn write_profile(profiler: &Profiler, path: &str, program_name: &str) -> Result<(), String> {
+    let folded_path = Path::new(path).with_extension("folded");
+
+    let mut report = create_file(Path::new(path))?;
+    profiler
+        .write_report(&mut report, program_name)
+        .and_then(|_| report.flush())
+        .map_err(|err| format!("{}: {}", path, err))?;
+
+    let mut folded = create_file(&folded_path)?;
+    profiler
+        .write_folded(&mut folded)
+        .and_then(|_| folded.flush())
+        .map_err(|err| format!("{}: {}", folded_path.display(), err))?;
+
+    println!("wrote {} and {}", path, folded_path.display());
+    Ok(())
+}
+
+fn create_file(path: &Path) -> Result<BufWriter<File>, String> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// creates the emulator from the program path and the `--platform`, `--font`, `--seed` and `--speed` options
+pub fn load_emulator(mut args: impl Iterator<Item = String>) -> Result<Emulator, String> {
+    let mut program_path = None;
+    let mut platform = Platform::SuperChip;
+    let mut font = None;
+    let mut seed = None;
+    let mut speed = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => {
+                let value = args.next().unwrap_or_default();
+                platform = value.parse()?;
+            }
+            "--font" => {
+                let value = args.next().unwrap_or_default();
+                font = Some(value.parse::<FontSet>()?);
+            }
+            "--seed" => {
+                let value = args.next().unwrap_or_default();
+                seed = match value.parse::<u64>() {
+                    Ok(seed) => Some(seed),
+                    Err(err) => return Err(format!("invalid seed {:?}: {}", value, err)),
+                };
+            }
+            "--speed" => {
+                let value = args.next().unwrap_or_default();
+                speed = match value.parse::<u32>() {
+                    Ok(speed) => Some(speed),
+                    Err(err) => return Err(format!("invalid speed {:?}: {}", value, err)),
+                };
+            }
+            _ => program_path = Some(arg),
+        }
+    }
+
+    let program_path = program_path.ok_or("Please specify a program path")?;
+    let program = read_program(&program_path)?;
+
+    let mut emulator =
+        Emulator::new(program, program_path, platform).map_err(|err| err.to_string())?;
+    if let Some(font) = font {
+        emulator = emulator.with_font(font);
+    }
+    if let Some(speed) = speed {
+        emulator = emulator.with_speed(speed);
+    }
+    if let Some(seed) = seed {
+        emulator = emulator.with_seed(seed);
+    }
+
+    Ok(emulator)
+}
+
+/// duration of one 60 Hz frame
+pub(crate) const FRAME: Duration = Duration::from_micros(1_000_000 / 60);
+
+/// save state slots, F1 to F4 save and F5 to F8 load
+const STATE_SLOTS: u8 = 4;
+
+/// frames kept for rewinding, 10 seconds
+const REWIND_FRAMES: usize = 600;
+
+/// frames rewound per Backspace press, holding the key repeats it
+const REWIND_STEP: usize = 4;
+
+/// how many times larger than the display screenshots and recordings are by default
+const CAPTURE_SCALE: usize = 4;
+
+/// reads a rom, Octo sources ending in `.8o` get assembled first
+pub fn read_program(path: &str) -> Result<Vec<u8>, String> {
+    if path.ends_with(".8o") {
+        let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        return assemble(&source)
+            .map(|assembly| assembly.rom)
+            .map_err(|err| format!("{}:{}", path, err));
+    }
+
+    fs::read(path).map_err(|err| err.to_string())
+}
+
+fn record_press(
+    pressed_keys: &Mutex<Vec<KeyCode>>,
+    just_pressed: &Mutex<Vec<char>>,
+    code: KeyCode,
+) {
+    let mut keys = pressed_keys.lock().unwrap();
+
+    if keys.contains(&code) {
+        keys.retain(|key| key != &code);
+    } else {
+        keys.push(code);
+        if let KeyCode::Char(char) = code {
+            just_pressed.lock().unwrap().push(char);
+        }
+    }
 }
 
 pub struct TerminalIO {
     pub pressed_keys: Arc<Mutex<Vec<KeyCode>>>,
     pub just_pressed: Arc<Mutex<Vec<char>>>,
+    was_sound_playing: Cell<bool>,
+    theme: Theme,
+    scale: usize,
+    recording_format: RecordingFormat,
+    /// draws the display with this renderer instead of picking one that fits the terminal
+    renderer: Option<Renderer>,
+    /// draws the display as an image with this protocol where the renderer would have drawn it
+    graphics: Option<GraphicsProtocol>,
+    /// hash of the framebuffer last drawn as an image, to only send it again when it changed
+    drawn_image: Cell<Option<u64>>,
+}
+
+/// where the display, keypad and messages go in the terminal
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Layout {
+    renderer: Renderer,
+    /// the protocol and scale of the image drawn in place of the renderer
+    image: Option<(GraphicsProtocol, usize)>,
+    /// column and row of the top left corner of the keypad, hidden if it fits nowhere
+    keypad: Option<(u16, u16)>,
+    /// columns everything takes
+    width: u16,
+    /// the first row below the display and the panels, for the error and status lines
+    bottom: u16,
+}
+
+impl Layout {
+    /// puts the keypad to the right of the display if the terminal is wide enough and below it if it is tall enough
+    fn new(renderer: Renderer, platform: Platform, (width, height): (u16, u16)) -> Layout {
+        let display_width =
+            TerminalIO::REGISTRIES_WIDTH + renderer.columns(platform.width()) as u16 + 2;
+        let display_bottom = renderer.rows(platform.height()) as u16 + 3;
+
+        let keypad = if display_width + TerminalIO::KEYPAD_WIDTH <= width {
+            Some((display_width, 1))
+        } else if display_bottom + TerminalIO::KEYPAD_HEIGHT + 2 <= height {
+            Some((TerminalIO::REGISTRIES_WIDTH, display_bottom))
+        } else {
+            None
+        };
+
+        let (keypad_right, keypad_bottom) = keypad.map_or((0, 0), |(x, y)| {
+            (x + TerminalIO::KEYPAD_WIDTH, y + TerminalIO::KEYPAD_HEIGHT)
+        });
+        Layout {
+            renderer,
+            image: None,
+            keypad,
+            width: display_width.max(keypad_right),
+            bottom: display_bottom
+                .max(keypad_bottom)
+                .max(TerminalIO::REGISTRIES_HEIGHT),
+        }
+    }
+
+    /// whether everything including the error and status lines fits in the terminal
+    fn fits(&self, (width, height): (u16, u16)) -> bool {
+        self.width <= width && self.bottom + 2 <= height
+    }
 }
 
+/// a recording started with F10 and the file it goes to
+type Recording = (String, Recorder<BufWriter<File>>);
+
 impl IO for TerminalIO {
     fn is_code_pressed(&self, code: u8) -> bool {
         let Some(code) = key_to_char(code) else {
@@ -63,22 +377,239 @@ impl IO for TerminalIO {
             .lock()
             .unwrap()
             .iter()
-            .filter_map(|char| char_to_key(char.clone()))
+            .filter_map(|char| char_to_key(*char))
             .collect()
     }
 }
+
+impl Default for TerminalIO {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TerminalIO {
     pub fn new() -> Self {
         TerminalIO {
             pressed_keys: Arc::new(Mutex::new(Vec::new())),
             just_pressed: Arc::new(Mutex::new(Vec::new())),
+            was_sound_playing: Cell::new(false),
+            theme: Theme::default(),
+            scale: CAPTURE_SCALE,
+            recording_format: RecordingFormat::Gif,
+            renderer: None,
+            graphics: None,
+            drawn_image: Cell::new(None),
         }
     }
+
+    /// draws the display as an image with `protocol`, the terminal has to support it
+    pub fn with_graphics(mut self, protocol: GraphicsProtocol) -> Self {
+        self.graphics = Some(protocol);
+        self
+    }
+
+    /// draws the display with `renderer` whatever the size of the terminal
+    pub fn with_renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = Some(renderer);
+        self
+    }
+
+    /// colors of the display, everything around it, screenshots and recordings
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// how many times larger than the display screenshots and recordings are
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// the format F10 records in
+    pub fn with_recording_format(mut self, format: RecordingFormat) -> Self {
+        self.recording_format = format;
+        self
+    }
+
+    /// records a key press, pressing a held key again releases it
+    pub(crate) fn press(&self, code: KeyCode) {
+        record_press(&self.pressed_keys, &self.just_pressed, code);
+    }
+
     fn is_key_pressed(&self, code: KeyCode) -> bool {
         self.pressed_keys.lock().unwrap().contains(&code)
     }
 
-    pub fn start(terminal_io: TerminalIO, mut emulator: Emulator) {
+    /// whether `code` is pressed, releasing it so hotkeys only fire once
+    fn take_key(&self, code: KeyCode) -> bool {
+        let mut keys = self.pressed_keys.lock().unwrap();
+        let pressed = keys.contains(&code);
+        keys.retain(|key| key != &code);
+        pressed
+    }
+
+    /// the layout with the largest renderer that fits the terminal, the most compact one if none does
+    ///
+    /// with graphics the display is drawn as an image as large as fits where the renderer would have drawn it,
+    /// terminals that don't report their size in pixels get the renderer
+    fn layout(&self, platform: Platform) -> Layout {
+        let terminal = size().unwrap_or((80, 24));
+        let mut layout = match self.renderer {
+            Some(renderer) => Layout::new(renderer, platform, terminal),
+            None => {
+                let layouts =
+                    Renderer::ALL.map(|renderer| Layout::new(renderer, platform, terminal));
+                layouts
+                    .into_iter()
+                    .find(|layout| layout.fits(terminal))
+                    .unwrap_or(layouts[layouts.len() - 1])
+            }
+        };
+
+        layout.image = self.graphics.and_then(|protocol| {
+            let window = window_size().ok()?;
+            if window.columns == 0 || window.rows == 0 {
+                return None;
+            }
+            let cell_width = (window.width / window.columns) as usize;
+            let cell_height = (window.height / window.rows) as usize;
+
+            let (width, height) = (platform.width(), platform.height());
+            let scale = (layout.renderer.columns(width) * cell_width / width)
+                .min(layout.renderer.rows(height) * cell_height / height);
+            (scale > 0).then_some((protocol, scale))
+        });
+        layout
+    }
+
+    /// clears the terminal and any image drawn with `layout`
+    fn clear(&self, stdout: &mut Stdout, layout: Option<Layout>) {
+        if let Some((protocol, _)) = layout.and_then(|layout| layout.image) {
+            stdout.write_all(&protocol.clear()).unwrap();
+        }
+        execute!(stdout, MoveTo(0, 0), Clear(ClearType::All)).unwrap();
+        self.drawn_image.set(None);
+    }
+
+    fn state_path(emulator: &Emulator, slot: u8) -> String {
+        format!("{}.{}.state", emulator.program_name(), slot)
+    }
+
+    /// the first `<program>.<n>.<extension>` that doesn't exist yet
+    fn capture_path(emulator: &Emulator, extension: &str) -> String {
+        (1..)
+            .map(|n| format!("{}.{}.{}", emulator.program_name(), n, extension))
+            .find(|path| !Path::new(path).exists())
+            .unwrap()
+    }
+
+    /// handles F9 to take a screenshot and F10 to start or stop recording, returns a message about what happened
+    fn handle_capture_keys(
+        &self,
+        emulator: &Emulator,
+        recording: &mut Option<Recording>,
+    ) -> Option<String> {
+        let mut status = None;
+
+        if self.take_key(KeyCode::F(9)) {
+            let path = Self::capture_path(emulator, "png");
+            let png =
+                Framebuffer::capture(emulator.display()).to_png(self.scale, &self.theme.palette);
+            let saved = png.and_then(|png| fs::write(&path, png).map_err(|err| err.to_string()));
+            status = Some(match saved {
+                Ok(()) => format!("saved {}", path),
+                Err(err) => format!("could not save {}: {}", path, err),
+            });
+        }
+
+        if self.take_key(KeyCode::F(10)) {
+            status = Some(match recording.take() {
+                Some(recording) => Self::finish_recording(recording),
+                None => match self.start_recording(emulator) {
+                    Ok(started) => {
+                        let message = format!("recording to {}", started.0);
+                        *recording = Some(started);
+                        message
+                    }
+                    Err(err) => format!("could not record: {}", err),
+                },
+            });
+        }
+
+        status
+    }
+
+    fn start_recording(&self, emulator: &Emulator) -> Result<Recording, String> {
+        let path = Self::capture_path(emulator, self.recording_format.extension());
+        let output = create_file(Path::new(&path))?;
+        let display = emulator.display();
+        let recorder = Recorder::new(
+            output,
+            self.recording_format,
+            display.screen_width(),
+            display.screen_height(),
+            self.scale,
+            &self.theme.palette,
+        )
+        .map_err(|err| format!("{}: {}", path, err))?;
+        Ok((path, recorder))
+    }
+
+    fn finish_recording((path, recorder): Recording) -> String {
+        let frames = recorder.frames();
+        match recorder.finish().and_then(|mut output| output.flush()) {
+            Ok(()) => format!("recorded {} frames to {}", frames, path),
+            Err(err) => format!("could not record {}: {}", path, err),
+        }
+    }
+
+    /// handles the save state hotkeys, returns a message about what happened
+    fn handle_state_keys(
+        &self,
+        emulator: &mut Emulator,
+        error: &mut Option<EmulatorError>,
+    ) -> Option<String> {
+        let mut status = None;
+
+        for slot in 1..=STATE_SLOTS {
+            if self.take_key(KeyCode::F(slot)) {
+                let path = Self::state_path(emulator, slot);
+                status = Some(match fs::write(&path, emulator.save_state()) {
+                    Ok(()) => format!("saved slot {}", slot),
+                    Err(err) => format!("could not save {}: {}", path, err),
+                });
+            }
+
+            if self.take_key(KeyCode::F(slot + STATE_SLOTS)) {
+                let path = Self::state_path(emulator, slot);
+                let loaded = fs::read(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|state| emulator.load_state(&state).map_err(|err| err.to_string()));
+                status = Some(match loaded {
+                    Ok(()) => {
+                        *error = None;
+                        format!("loaded slot {}", slot)
+                    }
+                    Err(err) => format!("could not load {}: {}", path, err),
+                });
+            }
+        }
+
+        status
+    }
+
+    pub fn start(terminal_io: TerminalIO, emulator: Emulator) {
+        Self::start_inspected(terminal_io, emulator, &mut ());
+    }
+
+    /// like [TerminalIO::start], showing everything the emulator runs to `inspector`
+    pub fn start_inspected(
+        terminal_io: TerminalIO,
+        mut emulator: Emulator,
+        inspector: &mut dyn Inspector,
+    ) {
         let pressed_keys = terminal_io.pressed_keys.clone();
         let just_pressed = terminal_io.just_pressed.clone();
         async_std::task::spawn(async move {
@@ -88,15 +619,76 @@ impl TerminalIO {
         let mut stdout = stdout();
         execute!(stdout, MoveTo(0, 0), Clear(ClearType::All)).unwrap();
 
+        let mut error = None;
+        let mut status = None;
+        let mut rewind = Rewind::new(REWIND_FRAMES);
+        let mut recording = None;
+        let mut layout = None;
+        let mut next_frame = Instant::now();
+
         while !terminal_io.is_key_pressed(KeyCode::Esc) {
-            emulator.tick(&terminal_io);
-            terminal_io.render(emulator.get_render_context());
+            if let Some(message) = terminal_io.handle_state_keys(&mut emulator, &mut error) {
+                status = Some(message);
+            }
+            if let Some(message) = terminal_io.handle_capture_keys(&emulator, &mut recording) {
+                status = Some(message);
+            }
+
+            if terminal_io.take_key(KeyCode::Backspace) {
+                let rewound = rewind.rewind(&mut emulator, REWIND_STEP);
+                if rewound > 0 {
+                    error = None;
+                }
+                status = Some(format!(
+                    "rewound {} frames, {:.1}s left",
+                    rewound,
+                    rewind.len() as f32 / 60.0
+                ));
+            } else if error.is_none() {
+                error = emulator.run_frame_inspected(&terminal_io, inspector).err();
+                if error.is_none() {
+                    rewind.record(&emulator);
+                    if let Some((_, recorder)) = &mut recording {
+                        if let Err(err) = recorder.frame(&Framebuffer::capture(emulator.display()))
+                        {
+                            status = Some(format!("stopped recording: {}", err));
+                            recording = None;
+                        }
+                    }
+                }
+            }
+
+            let next_layout = terminal_io.layout(emulator.platform());
+            if layout != Some(next_layout) {
+                // the terminal was resized, don't leave parts of the old layout behind
+                terminal_io.clear(&mut stdout, layout);
+                layout = Some(next_layout);
+            }
+
+            terminal_io.render(emulator.get_render_context(), &next_layout);
+
+            if let Some(error) = &error {
+                terminal_io.print_error(error, next_layout.bottom);
+            }
+            if let Some(status) = &status {
+                terminal_io.print_status(status, next_layout.bottom + 1);
+            }
 
             terminal_io.just_pressed.lock().unwrap().clear();
-            sleep(Duration::from_millis(10));
+
+            next_frame += FRAME;
+            let now = Instant::now();
+            match next_frame.checked_duration_since(now) {
+                Some(remaining) => sleep(remaining),
+                // running behind, e.g. after the terminal was suspended, so don't try to catch up
+                None => next_frame = now,
+            }
         }
 
-        execute!(stdout, MoveTo(0, 0), Clear(ClearType::All)).unwrap();
+        terminal_io.clear(&mut stdout, layout);
+        if let Some(recording) = recording {
+            println!("{}\r", Self::finish_recording(recording));
+        }
     }
 
     async fn start_listening(
@@ -123,16 +715,7 @@ impl TerminalIO {
                 Some(Ok(event)) => {
                     if let Event::Key(key_event) = event {
                         if key_event.kind == KeyEventKind::Press {
-                            let mut keys = pressed_keys.lock().unwrap();
-                            let mut just_pressed = just_pressed.lock().unwrap();
-
-                            if keys.contains(&key_event.code) {
-                                keys.retain(|key| key != &key_event.code);
-                            } else {
-                                keys.push(key_event.code);
-                                just_pressed
-                                    .push(key_event.code.to_string().chars().next().unwrap());
-                            }
+                            record_press(&pressed_keys, &just_pressed, key_event.code);
                         }
                     }
 
@@ -157,71 +740,176 @@ impl TerminalIO {
 
     const REGISTRIES_WIDTH: u16 = 10;
 
-    fn render(&self, context: RenderContext) {
+    /// the heading and one line per register
+    const REGISTRIES_HEIGHT: u16 = 18;
+
+    const KEYPAD_WIDTH: u16 = 27;
+
+    const KEYPAD_HEIGHT: u16 = 14;
+
+    fn print_error(&self, error: &EmulatorError, row: u16) {
+        let mut stdout = stdout();
+
+        queue!(
+            stdout,
+            MoveTo(Self::REGISTRIES_WIDTH, row),
+            Print(format!("{} - press Esc to quit", error).red()),
+            MoveToColumn(0),
+        )
+        .unwrap();
+
+        stdout.flush().unwrap()
+    }
+
+    fn print_status(&self, status: &str, row: u16) {
+        let mut stdout = stdout();
+
+        queue!(
+            stdout,
+            MoveTo(Self::REGISTRIES_WIDTH, row),
+            Print(status.with(self.foreground())),
+            Clear(ClearType::UntilNewLine),
+            MoveToColumn(0),
+        )
+        .unwrap();
+
+        stdout.flush().unwrap()
+    }
+
+    fn render(&self, context: RenderContext, layout: &Layout) {
         let mut stdout = stdout();
 
         self.print_registries(&context, &mut stdout);
-        self.print_keyboard(&mut stdout, context.platform.width());
-        self.print_screen(&context, &mut stdout);
+        if let Some(keypad) = layout.keypad {
+            self.print_keyboard(&mut stdout, keypad);
+        }
+        self.print_screen(&context, &mut stdout, layout);
+
+        if context.sound_playing && !self.was_sound_playing.get() {
+            queue!(stdout, Print("\x07")).unwrap();
+        }
+        self.was_sound_playing.set(context.sound_playing);
 
         stdout.flush().unwrap()
     }
 
+    fn foreground(&self) -> Color {
+        rgb(self.theme.foreground)
+    }
+
+    fn border(&self) -> Color {
+        rgb(self.theme.border)
+    }
+
     fn print_registries(&self, context: &RenderContext, stdout: &mut Stdout) {
-        queue!(stdout, MoveTo(0, 1), Print("Registers\n"),).unwrap();
+        queue!(
+            stdout,
+            MoveTo(0, 1),
+            Print("Registers\n".with(self.foreground())),
+        )
+        .unwrap();
 
         for register in context.registries {
             queue!(
                 stdout,
                 MoveToColumn(0),
-                Print(format!("{:#04x}\n", register))
+                Print(format!("{:#04x}\n", register).with(self.foreground()))
             )
             .unwrap();
         }
     }
 
-    fn print_screen(&self, context: &RenderContext, stdout: &mut Stdout) {
-        let height = context.platform.height();
+    fn print_screen(&self, context: &RenderContext, stdout: &mut Stdout, layout: &Layout) {
         let width = context.platform.width();
+        let columns = layout.renderer.columns(width);
 
         queue!(
             stdout,
             MoveTo(Self::REGISTRIES_WIDTH, 0),
-            Print(format!("{}\n", context.title.bold())),
+            Print(context.title.bold().with(self.foreground())),
+            Print("\n"),
             MoveToColumn(Self::REGISTRIES_WIDTH),
-            Print(format!("╭{}╮\n", "──".repeat(context.platform.width()))),
+            Print(format!("╭{}╮\n", "─".repeat(columns)).with(self.border())),
         )
         .unwrap();
 
-        for y in 0..height {
-            queue!(stdout, MoveToColumn(Self::REGISTRIES_WIDTH), Print("│")).unwrap();
-            for x in 0..width {
-                if context.pixels[y * width + x] {
-                    queue!(stdout, Print("██")).unwrap();
-                } else {
-                    queue!(stdout, Print("  ")).unwrap();
+        match layout.image {
+            Some(_) => {
+                // only the sides, anything printed inside would draw over the image
+                for _ in 0..layout.renderer.rows(context.platform.height()) {
+                    queue!(
+                        stdout,
+                        MoveToColumn(Self::REGISTRIES_WIDTH),
+                        Print("│".with(self.border())),
+                        MoveToColumn(Self::REGISTRIES_WIDTH + columns as u16 + 1),
+                        Print("│\n".with(self.border())),
+                    )
+                    .unwrap();
                 }
             }
+            None => {
+                let palette = &self.theme.palette;
+                for row in layout.renderer.cells(context.pixels, width) {
+                    queue!(
+                        stdout,
+                        MoveToColumn(Self::REGISTRIES_WIDTH),
+                        Print("│".with(self.border()))
+                    )
+                    .unwrap();
 
-            queue!(stdout, Print("│\n")).unwrap();
+                    // one color change per run of cells with the same colors
+                    for run in row.chunk_by(|a, b| {
+                        (a.foreground, a.background) == (b.foreground, b.background)
+                    }) {
+                        let text = run
+                            .iter()
+                            .flat_map(|cell| {
+                                std::iter::repeat_n(cell.glyph, layout.renderer.glyph_width())
+                            })
+                            .collect::<String>();
+                        let foreground = rgb(palette.color(run[0].foreground));
+                        let background = rgb(palette.color(run[0].background));
+                        queue!(stdout, Print(text.with(foreground).on(background))).unwrap();
+                    }
+
+                    queue!(stdout, Print("│\n".with(self.border()))).unwrap();
+                }
+            }
         }
 
         queue!(
             stdout,
             MoveToColumn(Self::REGISTRIES_WIDTH),
-            Print(format!("╰{}╯\n", "──".repeat(width))),
+            Print(format!("╰{}╯\n", "─".repeat(columns)).with(self.border())),
             MoveToColumn(0),
         )
-        .unwrap()
+        .unwrap();
+
+        if let Some((protocol, scale)) = layout.image {
+            let framebuffer = Framebuffer {
+                width,
+                height: context.platform.height(),
+                pixels: context.pixels.to_vec(),
+            };
+            let hash = framebuffer.hash();
+            if self.drawn_image.get() != Some(hash) {
+                // an image too large to encode leaves the display empty
+                if let Ok(image) = protocol.encode(&framebuffer, scale, &self.theme.palette) {
+                    queue!(stdout, MoveTo(Self::REGISTRIES_WIDTH + 1, 2)).unwrap();
+                    stdout.write_all(&image).unwrap();
+                }
+                self.drawn_image.set(Some(hash));
+            }
+        }
     }
 
-    fn print_keyboard(&self, stdout: &mut Stdout, screen_width: usize) {
-        let offset = Self::REGISTRIES_WIDTH + (screen_width * 2) as u16 + 2;
+    fn print_keyboard(&self, stdout: &mut Stdout, (offset, top): (u16, u16)) {
+        let border = self.border();
 
         queue!(
             stdout,
-            MoveTo(offset, 1),
-            Print(format!("╭─{}╮\n", "──────".repeat(4))),
+            MoveTo(offset, top),
+            Print(format!("╭─{}╮\n", "──────".repeat(4)).with(border)),
         )
         .unwrap();
 
@@ -229,30 +917,32 @@ impl TerminalIO {
             queue!(
                 stdout,
                 MoveToColumn(offset),
-                Print(format!("│{} │\n", " ╭───╮".repeat(4))),
+                Print(format!("│{} │\n", " ╭───╮".repeat(4)).with(border)),
                 MoveToColumn(offset),
-                Print("│ "),
+                Print("│ ".with(border)),
             )
             .unwrap();
 
             for x in 0..4 {
                 let key = y * 4 + x;
 
+                let mut label = format!("{:X}", key).with(self.foreground());
                 if self.is_code_pressed(key) {
-                    queue!(
-                        stdout,
-                        Print(format!("│ {} │ ", format!("{:X}", key).bold()))
-                    )
-                    .unwrap();
-                } else {
-                    queue!(stdout, Print(format!("│ {:X} │ ", key))).unwrap();
+                    label = label.bold();
                 }
+                queue!(
+                    stdout,
+                    Print("│ ".with(border)),
+                    Print(label),
+                    Print(" │ ".with(border))
+                )
+                .unwrap();
             }
             queue!(
                 stdout,
-                Print("│ \n"),
+                Print("│ \n".with(border)),
                 MoveToColumn(offset),
-                Print(format!("│{} │\n", " ╰───╯".repeat(4))),
+                Print(format!("│{} │\n", " ╰───╯".repeat(4)).with(border)),
             )
             .unwrap();
         }
@@ -260,8 +950,12 @@ impl TerminalIO {
         queue!(
             stdout,
             MoveToColumn(offset),
-            Print(format!("╰─{}╯\n", "──────".repeat(4))),
+            Print(format!("╰─{}╯\n", "──────".repeat(4)).with(border)),
         )
         .unwrap()
     }
 }
+
+fn rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::Rgb { r, g, b }
+}
@@ -1,12 +1,15 @@
+use crate::framebuffer::Framebuffer;
 use crate::io::{char_to_key, key_to_char, IO};
+use crate::palette::Theme;
 use crate::programs::Program;
+use crate::rewind::Rewind;
 use crate::{Emulator, Platform};
 use std::ops::Not;
 use std::sync::{Mutex, OnceLock};
 use strum::IntoEnumIterator;
 use wasm_bindgen::prelude::wasm_bindgen;
-use wasm_bindgen::JsValue;
-use web_sys::console::log_1;
+use wasm_bindgen::{Clamped, JsValue};
+use web_sys::ImageData;
 
 #[wasm_bindgen]
 #[derive(Debug)]
@@ -20,6 +23,11 @@ extern "C" {}
 
 static EMULATOR: OnceLock<Mutex<Emulator>> = OnceLock::new();
 static IO: OnceLock<Mutex<WebIO>> = OnceLock::new();
+static REWIND: OnceLock<Mutex<Rewind>> = OnceLock::new();
+static THEME: Mutex<Theme> = Mutex::new(Theme::OCTO);
+
+/// frames kept for rewinding, 10 seconds
+const REWIND_FRAMES: usize = 600;
 
 #[wasm_bindgen]
 pub fn get_programs() -> Vec<JsValue> {
@@ -29,12 +37,25 @@ pub fn get_programs() -> Vec<JsValue> {
 }
 
 #[wasm_bindgen]
-pub fn init(program: JsValue) {
+pub fn get_platforms() -> Vec<JsValue> {
+    Platform::iter()
+        .map(|value| serde_wasm_bindgen::to_value(&value).unwrap())
+        .collect()
+}
+
+#[wasm_bindgen]
+pub fn init(program: JsValue, platform: JsValue, seed: Option<u32>) -> Result<(), JsValue> {
     let program = serde_wasm_bindgen::from_value::<Program>(program)
         .unwrap()
         .source();
+    let platform =
+        serde_wasm_bindgen::from_value::<Platform>(platform).unwrap_or(Platform::SuperChip);
     let io = WebIO::new();
-    let emulator = Emulator::new(program, "IBM".to_string(), Platform::SuperChip, &io);
+    let mut emulator = Emulator::new(program, "IBM".to_string(), platform)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    if let Some(seed) = seed {
+        emulator = emulator.with_seed(seed as u64);
+    }
 
     match (IO.get(), EMULATOR.get()) {
         (Some(io_lock), Some(emulator_lock)) => {
@@ -46,13 +67,89 @@ pub fn init(program: JsValue) {
             EMULATOR.set(Mutex::new(emulator)).unwrap();
         }
     }
+    REWIND
+        .get_or_init(|| Mutex::new(Rewind::new(REWIND_FRAMES)))
+        .lock()
+        .unwrap()
+        .clear();
+
+    Ok(())
+}
+
+/// returns the `TickOutcome`, errors are thrown as strings
+#[wasm_bindgen]
+pub fn tick() -> Result<JsValue, JsValue> {
+    let mut io = IO.get().unwrap().lock().unwrap();
+    let outcome = EMULATOR.get().unwrap().lock().unwrap().tick(&*io);
+    io.just_pressed_keys.clear();
+
+    match outcome {
+        Ok(outcome) => Ok(serde_wasm_bindgen::to_value(&outcome).unwrap()),
+        Err(err) => Err(JsValue::from_str(&err.to_string())),
+    }
 }
 
+/// runs one 60 Hz frame, meant to be called once per animation frame
 #[wasm_bindgen]
-pub fn tick() {
+pub fn run_frame() -> Result<JsValue, JsValue> {
     let mut io = IO.get().unwrap().lock().unwrap();
-    EMULATOR.get().unwrap().lock().unwrap().tick(&*io);
+    let mut emulator = EMULATOR.get().unwrap().lock().unwrap();
+    let outcome = emulator.run_frame(&*io);
     io.just_pressed_keys.clear();
+
+    if outcome.is_ok() {
+        REWIND.get().unwrap().lock().unwrap().record(&emulator);
+    }
+
+    match outcome {
+        Ok(outcome) => Ok(serde_wasm_bindgen::to_value(&outcome).unwrap()),
+        Err(err) => Err(JsValue::from_str(&err.to_string())),
+    }
+}
+
+/// steps back up to `frames` frames recorded by `run_frame`, returns how many were rewound
+#[wasm_bindgen]
+pub fn rewind(frames: u32) -> u32 {
+    let mut emulator = EMULATOR.get().unwrap().lock().unwrap();
+    REWIND
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .rewind(&mut emulator, frames as usize) as u32
+}
+
+/// snapshot in the same format as the native build
+#[wasm_bindgen]
+pub fn save_state() -> Vec<u8> {
+    EMULATOR.get().unwrap().lock().unwrap().save_state()
+}
+
+#[wasm_bindgen]
+pub fn load_state(state: &[u8]) -> Result<(), JsValue> {
+    EMULATOR
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .load_state(state)
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn get_speed() -> u32 {
+    EMULATOR.get().unwrap().lock().unwrap().speed()
+}
+
+/// sets the instructions per frame
+#[wasm_bindgen]
+pub fn set_speed(instructions_per_frame: u32) {
+    EMULATOR
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .set_speed(instructions_per_frame);
 }
 
 #[wasm_bindgen]
@@ -62,6 +159,43 @@ pub fn get_render_context() -> JsValue {
     serde_wasm_bindgen::to_value(&emulator.get_render_context()).unwrap()
 }
 
+/// names of the built-in themes
+#[wasm_bindgen]
+pub fn get_themes() -> Vec<JsValue> {
+    Theme::ALL
+        .iter()
+        .map(|theme| JsValue::from_str(theme.name))
+        .collect()
+}
+
+/// the colors of the display and everything around it
+#[wasm_bindgen]
+pub fn get_theme() -> JsValue {
+    serde_wasm_bindgen::to_value(&*THEME.lock().unwrap()).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn set_theme(name: &str) -> Result<(), JsValue> {
+    *THEME.lock().unwrap() = name
+        .parse()
+        .map_err(|err: String| JsValue::from_str(&err))?;
+    Ok(())
+}
+
+/// the display in the colors of the theme with one image pixel per display pixel
+#[wasm_bindgen]
+pub fn render_image() -> Result<ImageData, JsValue> {
+    let emulator = EMULATOR.get().unwrap().lock().unwrap();
+    let framebuffer = Framebuffer::capture(emulator.display());
+    let rgba = framebuffer.to_rgba(&THEME.lock().unwrap().palette);
+
+    ImageData::new_with_u8_clamped_array_and_sh(
+        Clamped(&rgba),
+        framebuffer.width as u32,
+        framebuffer.height as u32,
+    )
+}
+
 #[wasm_bindgen]
 pub fn on_key_down(key: char) {
     let mut io = IO.get().unwrap().lock().unwrap();
@@ -90,7 +224,7 @@ impl IO for WebIO {
     fn get_just_pressed(&self) -> Vec<u8> {
         self.just_pressed_keys
             .iter()
-            .filter_map(|char| char_to_key(char.clone()))
+            .filter_map(|char| char_to_key(*char))
             .collect()
     }
 }
@@ -1,27 +1,72 @@
-use crate::clock::Clock;
+use crate::audio::Audio;
+use crate::clock::{Clock, TimeMode};
+use crate::disassembler::PROGRAM_START;
+use crate::error::{EmulatorError, TickOutcome};
+use crate::font::FontSet;
 use crate::gpu::Gpu;
-use crate::io::{RenderContext, IO};
-use crate::memory::Memory;
-use crate::memory::ToU16;
-use crate::memory::ToU8;
-use rand::{thread_rng, Rng, RngCore};
-use serde::Serialize;
+use crate::inspector::Inspector;
+use crate::instruction::Instruction;
+#[cfg(any(feature = "cli", feature = "wasm"))]
+use crate::io::RenderContext;
+use crate::io::IO;
+use crate::memory::{Access, Memory, MemoryAccess};
+use crate::quirks::{MemoryIncrement, Quirks};
+use crate::random::{RandomSource, Xorshift};
+use crate::state::{StateError, StateReader, StateWriter};
+use serde::{Deserialize, Serialize};
 use std::ops::Not;
+use std::str::FromStr;
+use strum::{EnumIter, IntoEnumIterator};
 
+pub mod assembler;
+pub mod audio;
 pub mod clock;
+pub mod coverage;
+#[cfg(feature = "cli")]
+pub mod dap;
+pub mod debugger;
+pub mod disassembler;
+pub mod error;
+pub mod font;
+pub mod framebuffer;
 pub mod gpu;
+pub mod headless;
+pub mod inspector;
+pub mod instruction;
 pub mod io;
 pub mod memory;
+pub mod palette;
 
+pub mod profiler;
 pub mod programs;
-
-#[derive(Debug, Copy, Clone, Serialize)]
-enum Platform {
+pub mod quirks;
+pub mod random;
+#[cfg(feature = "cli")]
+pub mod recorder;
+pub mod rewind;
+pub mod state;
+pub mod trace;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
+pub enum Platform {
     Chip8,
     SuperChip,
     X0Chip,
 }
 
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(Platform::Chip8),
+            "schip" | "superchip" => Ok(Platform::SuperChip),
+            "xo" | "xochip" | "xo-chip" => Ok(Platform::X0Chip),
+            _ => Err(format!("unknown platform: {}", value)),
+        }
+    }
+}
+
 impl Platform {
     pub fn width(&self) -> usize {
         match self {
@@ -38,6 +83,33 @@ impl Platform {
             Platform::X0Chip => 64,
         }
     }
+
+    /// bytes of addressable memory
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Platform::Chip8 => 4096,
+            Platform::SuperChip => 4096,
+            Platform::X0Chip => 65536,
+        }
+    }
+
+    /// default speed in instructions per 60 Hz frame
+    pub fn instructions_per_frame(&self) -> u32 {
+        match self {
+            Platform::Chip8 => 11,
+            Platform::SuperChip => 30,
+            Platform::X0Chip => 1000,
+        }
+    }
+
+    /// amount of persistent RPL flags usable through `FX75` and `FX85`
+    pub fn flag_count(&self) -> usize {
+        match self {
+            Platform::Chip8 => 0,
+            Platform::SuperChip => 8,
+            Platform::X0Chip => 16,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -47,6 +119,15 @@ pub struct Emulator {
     memory: Memory,
     display: Gpu,
     clock: Clock,
+    audio: Audio,
+    quirks: Quirks,
+    font: FontSet,
+    random: Box<dyn RandomSource>,
+    /// set once per 60 Hz frame, consumed by `DXYN` when waiting for the display
+    vblank: bool,
+    /// the key `FX0A` saw go down and waits to be released
+    awaited_key: Option<u8>,
+    exited: bool,
 }
 
 impl Emulator {
@@ -54,113 +135,515 @@ impl Emulator {
         program: Vec<u8>,
         program_name: String,
         platform: Platform,
-        io: &dyn IO,
-    ) -> Emulator {
-        let mut memory = Memory::new(4096);
+    ) -> Result<Emulator, EmulatorError> {
+        let mut memory = Memory::new(platform.memory_size());
+
+        if 0x200 + program.len() > memory.size() {
+            return Err(EmulatorError::RomTooLarge {
+                size: program.len(),
+                max: memory.size() - 0x200,
+            });
+        }
 
-        memory.write_slice(0x200, &program);
+        memory.write_slice(0x200, &program)?;
         memory.write_pc(0x200);
 
-        Emulator {
+        let mut emulator = Emulator {
             platform,
             program_name,
             memory,
             display: Gpu::new(platform),
-            clock: Clock::default(),
+            clock: Clock::new(TimeMode::Virtual, platform.instructions_per_frame()),
+            audio: Audio::default(),
+            quirks: Quirks::preset(platform),
+            font: FontSet::preset(platform),
+            random: Box::new(Xorshift::new(rand::random())),
+            vblank: true,
+            awaited_key: None,
+            exited: false,
+        };
+
+        emulator.load_font();
+        Ok(emulator)
+    }
+
+    /// makes `CXNN` produce the same numbers on every run with this seed
+    pub fn with_seed(self, seed: u64) -> Emulator {
+        self.with_random_source(Box::new(Xorshift::new(seed)))
+    }
+
+    pub fn with_random_source(mut self, random: Box<dyn RandomSource>) -> Emulator {
+        self.random = random;
+        self
+    }
+
+    pub fn random_source(&self) -> &dyn RandomSource {
+        self.random.as_ref()
+    }
+
+    pub fn random_source_mut(&mut self) -> &mut dyn RandomSource {
+        self.random.as_mut()
+    }
+
+    pub fn with_time_mode(mut self, mode: TimeMode) -> Emulator {
+        self.clock.set_mode(mode);
+        self
+    }
+
+    pub fn time_mode(&self) -> TimeMode {
+        self.clock.mode()
+    }
+
+    pub fn set_time_mode(&mut self, mode: TimeMode) {
+        self.clock.set_mode(mode);
+    }
+
+    /// sets how many instructions `run_frame` executes
+    pub fn with_speed(mut self, instructions_per_frame: u32) -> Emulator {
+        self.set_speed(instructions_per_frame);
+        self
+    }
+
+    pub fn speed(&self) -> u32 {
+        self.clock.instructions_per_frame()
+    }
+
+    pub fn set_speed(&mut self, instructions_per_frame: u32) {
+        self.clock
+            .set_instructions_per_frame(instructions_per_frame);
+    }
+
+    pub fn with_font(mut self, font: FontSet) -> Emulator {
+        self.font = font;
+        self.load_font();
+        self
+    }
+
+    pub fn font(&self) -> FontSet {
+        self.font
+    }
+
+    fn load_font(&mut self) {
+        // the interpreter area always fits both fonts
+        self.memory
+            .write_slice(font::SMALL_FONT_ADDRESS as usize, self.font.small())
+            .unwrap();
+        self.memory
+            .write_slice(font::BIG_FONT_ADDRESS as usize, self.font.big())
+            .unwrap();
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Emulator {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// the memory from the program start to the last non zero byte, the rom until the program changes it
+    pub fn program_memory(&self) -> &[u8] {
+        let start = PROGRAM_START as usize;
+        let program = self
+            .memory
+            .read_slice(start, self.memory.size() - start)
+            .unwrap_or_default();
+        let length = program
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |index| index + 1);
+        &program[..length]
+    }
+
+    pub fn display(&self) -> &Gpu {
+        &self.display
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    pub fn program_name(&self) -> &str {
+        &self.program_name
+    }
+
+    /// whether the program stopped itself with `00FD`
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// snapshot of the whole machine, see [state] for the format
+    ///
+    /// the random source is stored by its state, so it is restored into the current source
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+
+        writer.u8(Platform::iter().position(|p| p == self.platform).unwrap() as u8);
+        writer.bytes(self.program_name.as_bytes());
+        self.quirks.save_state(&mut writer);
+        writer.u8(FontSet::iter().position(|f| f == self.font).unwrap() as u8);
+        self.memory.save_state(&mut writer);
+        self.display.save_state(&mut writer);
+        self.clock.save_state(&mut writer);
+        self.audio.save_state(&mut writer);
+        writer.bytes(&self.random.state());
+        writer.bool(self.vblank);
+        writer.bool(self.awaited_key.is_some());
+        writer.u8(self.awaited_key.unwrap_or_default());
+        writer.bool(self.exited);
+
+        writer.finish()
+    }
+
+    /// restores a snapshot taken by `save_state`, the emulator stays untouched if it is rejected
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut reader = StateReader::new(state)?;
+
+        let platform = Platform::iter()
+            .nth(reader.u8()? as usize)
+            .ok_or(StateError::Invalid { field: "platform" })?;
+        let program_name =
+            String::from_utf8(reader.bytes()?.to_vec()).map_err(|_| StateError::Invalid {
+                field: "program name",
+            })?;
+        let quirks = Quirks::load_state(&mut reader)?;
+        let font = FontSet::iter()
+            .nth(reader.u8()? as usize)
+            .ok_or(StateError::Invalid { field: "font" })?;
+        let memory = Memory::load_state(&mut reader, platform.memory_size())?;
+        let display = Gpu::load_state(&mut reader, platform)?;
+        let clock = Clock::load_state(&mut reader)?;
+        let audio = Audio::load_state(&mut reader)?;
+        let random = reader.bytes()?;
+        let vblank = reader.bool()?;
+        let awaiting_key = reader.bool()?;
+        let awaited_key = awaiting_key.then_some(reader.u8()?);
+        let exited = reader.bool()?;
+        reader.finish()?;
+
+        self.random
+            .restore(random)
+            .map_err(|_| StateError::Invalid { field: "random" })?;
+        self.platform = platform;
+        self.program_name = program_name;
+        self.quirks = quirks;
+        self.font = font;
+        self.memory = memory;
+        self.display = display;
+        self.clock = clock;
+        self.audio = audio;
+        self.vblank = vblank;
+        self.awaited_key = awaited_key;
+        self.exited = exited;
+
+        Ok(())
+    }
+
+    /// runs one 60 Hz frame, at most `speed` instructions followed by a single timer tick
+    ///
+    /// the frame ends early when an instruction waits, e.g. for a key or the vertical blank
+    pub fn run_frame(&mut self, io: &dyn IO) -> Result<TickOutcome, EmulatorError> {
+        self.run_frame_inspected(io, &mut ())
+    }
+
+    /// like [Emulator::run_frame], showing every instruction and the end of the frame to `inspector`
+    pub fn run_frame_inspected(
+        &mut self,
+        io: &dyn IO,
+        inspector: &mut dyn Inspector,
+    ) -> Result<TickOutcome, EmulatorError> {
+        for _ in 0..self.clock.instructions_per_frame() {
+            if !self.step_inspected(io, inspector)? {
+                break;
+            }
+        }
+
+        self.end_frame();
+        inspector.after_frame(self);
+
+        Ok(if self.exited {
+            TickOutcome::Exited
+        } else {
+            TickOutcome::Running
+        })
+    }
+
+    /// runs a single instruction without advancing the timers
+    ///
+    /// returns false if nothing happened because the instruction waits or the program exited
+    pub fn step(&mut self, io: &dyn IO) -> Result<bool, EmulatorError> {
+        self.step_inspected(io, &mut ())
+    }
+
+    /// like [Emulator::step], showing the instruction to `inspector` before it runs
+    pub fn step_inspected(
+        &mut self,
+        io: &dyn IO,
+        inspector: &mut dyn Inspector,
+    ) -> Result<bool, EmulatorError> {
+        if self.exited {
+            return Ok(false);
         }
+
+        let pc = self.memory.read_pc();
+        self.run_instruction(io, inspector)?;
+        Ok(self.memory.read_pc() != pc)
+    }
+
+    /// ticks the timers and starts the vertical blank
+    pub fn end_frame(&mut self) {
+        self.clock.frame();
+        self.vblank = true;
     }
 
-    pub fn tick(&mut self, io: &dyn IO) {
-        self.run_instruction(io);
-        self.clock.tick();
+    /// memory `instruction` accesses when run in the current state
+    pub fn memory_access(&self, instruction: Instruction) -> Option<MemoryAccess> {
+        let (access, length) = match instruction {
+            Instruction::Drw { n, .. } => {
+                let wide = n == 0 && self.platform != Platform::Chip8;
+                let length = if wide { 32 } else { n as usize };
+                (Access::Read, length * self.display.selected_plane_count())
+            }
+            Instruction::Audio => (Access::Read, 16),
+            Instruction::Bcd(_) => (Access::Write, 3),
+            Instruction::Store(x) => (Access::Write, x as usize + 1),
+            Instruction::Load(x) => (Access::Read, x as usize + 1),
+            Instruction::SaveRange { x, y } => (Access::Write, x.abs_diff(y) as usize + 1),
+            Instruction::LoadRange { x, y } => (Access::Read, x.abs_diff(y) as usize + 1),
+            _ => return None,
+        };
+
+        Some(MemoryAccess {
+            access,
+            address: self.memory.read_index_register() as usize,
+            length,
+        })
+    }
+
+    /// the instruction the program counter points at
+    pub fn current_instruction(&self) -> Result<Instruction, EmulatorError> {
+        let pc = self.memory.read_pc();
+        let opcode = self.memory.read_u16(pc as usize)?;
+        Instruction::decode(opcode, self.platform)
+            .ok_or(EmulatorError::UnknownOpcode { pc, opcode })
+    }
+
+    /// runs a single instruction, timers advance according to the time mode
+    pub fn tick(&mut self, io: &dyn IO) -> Result<TickOutcome, EmulatorError> {
+        if self.exited {
+            return Ok(TickOutcome::Exited);
+        }
+
+        self.run_instruction(io, &mut ())?;
+        if self.clock.tick() {
+            self.vblank = true;
+        }
+
+        Ok(if self.exited {
+            TickOutcome::Exited
+        } else {
+            TickOutcome::Running
+        })
     }
 
-    fn get_render_context(&self) -> RenderContext {
+    #[cfg(any(feature = "cli", feature = "wasm"))]
+    fn get_render_context(&self) -> RenderContext<'_> {
         RenderContext {
             platform: self.platform,
             title: &self.program_name,
             registries: self.memory.registers(),
             pixels: self.display.pixels(),
+            sound_playing: self.clock.sound_timer() > 0,
+            audio: &self.audio,
         }
     }
 
-    fn run_instruction(&mut self, io: &dyn IO) {
-        let instruction = self.memory.read_u16(self.memory.read_pc() as usize);
-        let instruction_parts = memory::u16_to_u4_array(instruction);
+    /// skips the next instruction, which is 4 bytes long for `F000 NNNN` on XO-CHIP
+    fn skip_instruction(&mut self) -> Result<(), EmulatorError> {
+        let next = self.memory.read_u16(self.memory.read_pc() as usize)?;
+        let size = Instruction::decode(next, self.platform).map_or(2, |next| next.size());
+
+        self.memory
+            .write_pc(self.memory.read_pc().wrapping_add(size));
+
+        Ok(())
+    }
+
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.memory.write_register(0xF, 0);
+        }
+    }
+
+    /// register read by the shift instructions
+    fn shift_source(&self, x: u8, y: u8) -> usize {
+        if self.quirks.shift {
+            x as usize
+        } else {
+            y as usize
+        }
+    }
+
+    fn increment_index_after_transfer(&mut self, x: u8) {
+        let increment = match self.quirks.memory_increment {
+            MemoryIncrement::None => return,
+            MemoryIncrement::X => x as u16,
+            MemoryIncrement::XPlusOne => x as u16 + 1,
+        };
+
+        let index = self.memory.read_index_register();
+        self.memory
+            .write_index_register(index.wrapping_add(increment));
+    }
+
+    /// registers from `x` to `y`, in descending order if `y` is smaller
+    fn register_range(x: u8, y: u8) -> Vec<usize> {
+        if x <= y {
+            (x as usize..=y as usize).collect()
+        } else {
+            (y as usize..=x as usize).rev().collect()
+        }
+    }
+
+    fn run_instruction(
+        &mut self,
+        io: &dyn IO,
+        inspector: &mut dyn Inspector,
+    ) -> Result<(), EmulatorError> {
+        let instruction = self.current_instruction()?;
+        inspector.before_instruction(self, instruction);
         self.memory.increment_pc();
 
-        match (
-            self.platform,
-            (
-                instruction_parts[0],
-                instruction_parts[1],
-                instruction_parts[2],
-                instruction_parts[3],
-            ),
-        ) {
-            (_, (0x0, 0x0, 0xE, 0x0) | (0x0, 0x0, 0xF, 0xF)) => {
+        self.execute(instruction, io)
+    }
+
+    fn execute(&mut self, instruction: Instruction, io: &dyn IO) -> Result<(), EmulatorError> {
+        match instruction {
+            Instruction::Cls => {
                 self.display.clear();
             }
-            (_, (0x0, 0x0, 0xE, 0xE)) => {
-                let stack = self.memory.pop_stack();
+            Instruction::ScrollDown(n) => {
+                self.display.scroll_down(n as usize);
+            }
+            Instruction::ScrollUp(n) => {
+                self.display.scroll_up(n as usize);
+            }
+            Instruction::ScrollRight => {
+                self.display.scroll_right(4);
+            }
+            Instruction::ScrollLeft => {
+                self.display.scroll_left(4);
+            }
+            Instruction::Exit => {
+                self.memory.decrement_pc()?;
+                self.exited = true;
+            }
+            Instruction::Lores => {
+                self.display.set_hires(false);
+            }
+            Instruction::Hires => {
+                self.display.set_hires(true);
+            }
+            Instruction::Ret => {
+                let stack = self.memory.pop_stack()?;
                 self.memory.write_pc(stack)
             }
-            (_, (0x1, a, b, c)) => {
-                self.memory.write_pc((a, b, c).to_u16());
+            Instruction::Jp(address) => {
+                self.memory.write_pc(address);
             }
-            (_, (0x2, a, b, c)) => {
-                self.memory.push_stack(self.memory.read_pc());
-                self.memory.write_pc((a, b, c).to_u16())
+            Instruction::Call(address) => {
+                self.memory.push_stack(self.memory.read_pc())?;
+                self.memory.write_pc(address)
             }
-            (_, (0x3, x, a, b)) => {
-                if self.memory.read_register(x as usize) == (a, b).to_u8() {
-                    self.memory.increment_pc();
+            Instruction::SeByte { x, byte } => {
+                if self.memory.read_register(x as usize) == byte {
+                    self.skip_instruction()?;
                 }
             }
-            (_, (0x4, x, a, b)) => {
-                if self.memory.read_register(x as usize) != (a, b).to_u8() {
-                    self.memory.increment_pc();
+            Instruction::SneByte { x, byte } => {
+                if self.memory.read_register(x as usize) != byte {
+                    self.skip_instruction()?;
                 }
             }
-            (_, (0x5, x, y, 0)) => {
+            Instruction::SeReg { x, y } => {
                 if self.memory.read_register(x as usize) == self.memory.read_register(y as usize) {
-                    self.memory.increment_pc();
+                    self.skip_instruction()?;
                 }
             }
-            (_, (0x6, x, a, b)) => {
-                self.memory.write_register(x as usize, (a, b).to_u8());
+            Instruction::SaveRange { x, y } => {
+                let index = self.memory.read_index_register() as usize;
+
+                for (offset, register) in Self::register_range(x, y).into_iter().enumerate() {
+                    self.memory
+                        .write_u8(index + offset, self.memory.read_register(register))?;
+                }
             }
-            (_, (0x7, x, a, b)) => {
+            Instruction::LoadRange { x, y } => {
+                let index = self.memory.read_index_register() as usize;
+
+                for (offset, register) in Self::register_range(x, y).into_iter().enumerate() {
+                    self.memory
+                        .write_register(register, self.memory.read_u8(index + offset)?);
+                }
+            }
+            Instruction::LdByte { x, byte } => {
+                self.memory.write_register(x as usize, byte);
+            }
+            Instruction::AddByte { x, byte } => {
                 let current = self.memory.read_register(x as usize);
-                let to_add = (a, b).to_u8();
+                let to_add = byte;
 
                 let result = (current as u16 + to_add as u16) & 255;
 
                 self.memory.write_register(x as usize, result as u8);
             }
-            (_, (0x8, x, y, 0x0)) => {
+            Instruction::LdReg { x, y } => {
                 self.memory
                     .write_register(x as usize, self.memory.read_register(y as usize));
             }
-            (_, (0x8, x, y, 0x1)) => {
+            Instruction::Or { x, y } => {
                 let x_val = self.memory.read_register(x as usize);
                 let y_val = self.memory.read_register(y as usize);
 
                 self.memory.write_register(x as usize, x_val | y_val);
+                self.reset_vf();
             }
-            (_, (0x8, x, y, 0x2)) => {
+            Instruction::And { x, y } => {
                 let x_val = self.memory.read_register(x as usize);
                 let y_val = self.memory.read_register(y as usize);
 
                 self.memory.write_register(x as usize, x_val & y_val);
+                self.reset_vf();
             }
-            (_, (0x8, x, y, 0x3)) => {
+            Instruction::Xor { x, y } => {
                 let x_val = self.memory.read_register(x as usize);
                 let y_val = self.memory.read_register(y as usize);
 
                 self.memory.write_register(x as usize, x_val ^ y_val);
+                self.reset_vf();
             }
-            (_, (0x8, x, y, 0x4)) => {
+            Instruction::AddReg { x, y } => {
                 let x_val = self.memory.read_register(x as usize);
                 let y_val = self.memory.read_register(y as usize);
 
@@ -171,7 +654,7 @@ impl Emulator {
                 self.memory
                     .write_register(0xF, if result > 255 { 1 } else { 0 });
             }
-            (_, (0x8, x, y, 0x5)) => {
+            Instruction::Sub { x, y } => {
                 let x_val = self.memory.read_register(x as usize);
                 let y_val = self.memory.read_register(y as usize);
 
@@ -181,8 +664,8 @@ impl Emulator {
                 self.memory
                     .write_register(0xF, if x_val >= y_val { 1 } else { 0 });
             }
-            (_, (0x8, x, y, 0x6)) => {
-                let mut y_val = self.memory.read_register(y as usize);
+            Instruction::Shr { x, y } => {
+                let mut y_val = self.memory.read_register(self.shift_source(x, y));
 
                 let rest = y_val & 0x01;
                 y_val >>= 1;
@@ -191,7 +674,7 @@ impl Emulator {
 
                 self.memory.write_register(0xF, rest);
             }
-            (_, (0x8, x, y, 0x7)) => {
+            Instruction::Subn { x, y } => {
                 let x_val = self.memory.read_register(x as usize);
                 let y_val = self.memory.read_register(y as usize);
 
@@ -201,8 +684,8 @@ impl Emulator {
                 self.memory
                     .write_register(0xF, if y_val >= x_val { 1 } else { 0 });
             }
-            (_, (0x8, x, y, 0xe)) => {
-                let mut y_val = self.memory.read_register(y as usize);
+            Instruction::Shl { x, y } => {
+                let mut y_val = self.memory.read_register(self.shift_source(x, y));
 
                 let rest = (y_val & 0x80) >> 7;
                 y_val <<= 1;
@@ -211,95 +694,120 @@ impl Emulator {
 
                 self.memory.write_register(0xF, rest);
             }
-            (_, (0x9, x, y, 0)) => {
+            Instruction::SneReg { x, y } => {
                 if self.memory.read_register(x as usize) != self.memory.read_register(y as usize) {
-                    self.memory.increment_pc();
+                    self.skip_instruction()?;
                 }
             }
-            (_, (0xA, a, b, c)) => {
-                self.memory.write_index_register((a, b, c).to_u16());
-            }
-            (_, (0xB, a, b, c)) => {
-                let offset = self.memory.read_register(0);
-                self.memory.write_pc((a, b, c).to_u16() + offset as u16);
-            }
-            (platform, (0xD, x, y, n)) => {
-                //todo move to gpu
-
-                let x = self.memory.read_register(x as usize) as usize % self.platform.width();
-                let y = self.memory.read_register(y as usize) as usize % self.platform.height();
-
-                let mut current_pointer = self.memory.read_index_register();
-
-                let mut was_turned_off = false;
+            Instruction::LdI(address) => {
+                self.memory.write_index_register(address);
+            }
+            Instruction::JpV0(address) => {
+                let offset = self.memory.read_register(if self.quirks.jump {
+                    (address >> 8) as usize
+                } else {
+                    0
+                });
+                self.memory.write_pc(address + offset as u16);
+            }
+            Instruction::Drw { x, y, n } => {
+                if self.quirks.display_wait {
+                    if !self.vblank {
+                        self.memory.decrement_pc()?;
+                        return Ok(());
+                    }
+                    self.vblank = false;
+                }
 
-                for y_off in 0..n as usize {
-                    let mut to_render = self.memory.read_u8(current_pointer as usize);
+                let x = self.memory.read_register(x as usize) as usize;
+                let y = self.memory.read_register(y as usize) as usize;
 
-                    for x_off in 0..8 {
-                        if (to_render & 0x01) == 0x01 {
-                            if self.display.flip_pixel(platform, x + 7 - x_off, y + y_off) {
-                                was_turned_off = true;
-                            }
-                        }
+                let wide = n == 0 && self.platform != Platform::Chip8;
+                let length =
+                    if wide { 32 } else { n as usize } * self.display.selected_plane_count();
 
-                        to_render >>= 1
-                    }
+                let index = self.memory.read_index_register() as usize;
+                let sprite = (index..index + length)
+                    .map(|address| self.memory.read_u8(address))
+                    .collect::<Result<Vec<u8>, _>>()?;
 
-                    current_pointer += 1;
-                }
+                let was_turned_off =
+                    self.display
+                        .draw_sprite(x, y, &sprite, wide, self.quirks.wrap);
 
                 self.memory
                     .write_register(0xF, if was_turned_off { 1 } else { 0 });
             }
-            (_, (0xC, x, a, b)) => {
-                let nn = (a, b).to_u8();
-                let mut rng = thread_rng();
-
-                self.memory.write_register(x as usize, rng.gen::<u8>() & nn);
+            Instruction::Rnd { x, byte } => {
+                let value = self.random.next_u8() & byte;
+                self.memory.write_register(x as usize, value);
             }
-            (_, (0xE, x, 0x9, 0xE)) => {
+            Instruction::Skp(x) => {
                 if io.is_code_pressed(self.memory.read_register(x as usize)) {
-                    self.memory.increment_pc();
+                    self.skip_instruction()?;
                 }
             }
-            (_, (0xE, x, 0xA, 0x1)) => {
+            Instruction::Sknp(x) => {
                 if io
                     .is_code_pressed(self.memory.read_register(x as usize))
                     .not()
                 {
-                    self.memory.increment_pc();
+                    self.skip_instruction()?;
                 }
             }
-            (_, (0xF, x, 0x0, 0x7)) => {
-                self.memory
-                    .write_register(x as usize, self.clock.delay_timer());
+            Instruction::LdILong => {
+                let address = self.memory.read_u16(self.memory.read_pc() as usize)?;
+                self.memory.increment_pc();
+
+                self.memory.write_index_register(address);
             }
-            (_, (0xF, x, 0x0, 0xA)) => {
-                self.memory.decrement_pc();
+            Instruction::Plane(n) => {
+                self.display.select_planes(n);
+            }
+            Instruction::Audio => {
+                let index = self.memory.read_index_register() as usize;
 
-                let just_pressed = io.get_just_pressed();
+                let mut pattern = [0; 16];
+                for (offset, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.memory.read_u8(index + offset)?;
+                }
 
-                if let Some(key) = just_pressed.get(0) {
-                    self.memory.write_register(x as usize, *key);
-                    self.memory.increment_pc();
+                self.audio.set_pattern(pattern);
+            }
+            Instruction::LdVxDt(x) => {
+                self.memory
+                    .write_register(x as usize, self.clock.delay_timer());
+            }
+            Instruction::LdVxK(x) => {
+                // like the COSMAC VIP, the key counts once it is released again
+                match self.awaited_key {
+                    Some(key) if !io.is_code_pressed(key) => {
+                        self.memory.write_register(x as usize, key);
+                        self.awaited_key = None;
+                    }
+                    Some(_) => self.memory.decrement_pc()?,
+                    None => {
+                        self.awaited_key = io.get_just_pressed().first().copied();
+                        self.memory.decrement_pc()?;
+                    }
                 }
             }
-            (_, (0xF, x, 0x1, 0x5)) => {
+            Instruction::LdDtVx(x) => {
                 self.clock
                     .set_delay_timer(self.memory.read_register(x as usize));
             }
-            (_, (0xF, x, 0x1, 0x8)) => {
+            Instruction::LdStVx(x) => {
                 self.clock
                     .set_sound_timer(self.memory.read_register(x as usize));
             }
-            (_, (0xF, x, 0x1, 0xE)) => {
+            Instruction::AddI(x) => {
                 let x = self.memory.read_register(x as usize);
                 let index = self.memory.read_index_register();
 
-                self.memory.write_index_register(index + x as u16);
+                self.memory
+                    .write_index_register(index.wrapping_add(x as u16));
             }
-            (_, (0xF, x, 0x3, 0x3)) => {
+            Instruction::Bcd(x) => {
                 let x = self.memory.read_register(x as usize);
 
                 let index = self.memory.read_index_register() as usize;
@@ -308,34 +816,61 @@ impl Emulator {
                 let second_digit = (x / 10) % 10;
                 let third_digit = x % 10;
 
-                self.memory.write_u8(index, first_digit);
-                self.memory.write_u8(index + 1, second_digit);
-                self.memory.write_u8(index + 2, third_digit);
+                self.memory.write_u8(index, first_digit)?;
+                self.memory.write_u8(index + 1, second_digit)?;
+                self.memory.write_u8(index + 2, third_digit)?;
             }
-            (_, (0xF, x, 0x5, 0x5)) => {
+            Instruction::Store(x) => {
                 for register in 0..=x {
                     self.memory.write_u8(
                         self.memory.read_index_register() as usize + register as usize,
                         self.memory.read_register(register as usize),
-                    )
+                    )?;
                 }
+
+                self.increment_index_after_transfer(x);
             }
-            (_, (0xF, x, 0x6, 0x5)) => {
+            Instruction::Load(x) => {
                 for register in 0..=x {
                     self.memory.write_register(
                         register as usize,
                         self.memory.read_u8(
                             self.memory.read_index_register() as usize + register as usize,
-                        ),
+                        )?,
                     );
                 }
+
+                self.increment_index_after_transfer(x);
             }
-            _ => {
-                panic!(
-                    "unimplemented instruction: {:#06x} for {:?}",
-                    instruction, self.platform
-                );
+            Instruction::LdF(x) => {
+                let digit = self.memory.read_register(x as usize) & 0xF;
+
+                self.memory
+                    .write_index_register(font::SMALL_FONT_ADDRESS + digit as u16 * 5);
+            }
+            Instruction::LdHf(x) => {
+                let digit = self.memory.read_register(x as usize) & 0xF;
+
+                self.memory
+                    .write_index_register(font::BIG_FONT_ADDRESS + digit as u16 * 10);
+            }
+            Instruction::Pitch(x) => {
+                self.audio.set_pitch(self.memory.read_register(x as usize));
+            }
+            Instruction::SaveFlags(x) => {
+                for register in 0..=x as usize {
+                    self.memory
+                        .write_flag(register, self.memory.read_register(register));
+                }
+            }
+            Instruction::LoadFlags(x) => {
+                for register in 0..=x as usize {
+                    self.memory
+                        .write_register(register, self.memory.read_flag(register));
+                }
             }
         }
+
+        Ok(())
     }
 }
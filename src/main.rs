@@ -1,7 +1,349 @@
 #[cfg(feature = "cli")]
-use chip_8::io::terminal_io::run;
+use chip_8::assembler::assemble;
+#[cfg(feature = "cli")]
+use chip_8::coverage::Coverage;
+#[cfg(feature = "cli")]
+use chip_8::dap;
+#[cfg(feature = "cli")]
+use chip_8::disassembler::{disassemble, disassemble_with_coverage};
+#[cfg(feature = "cli")]
+use chip_8::headless::{run_headless_inspected, InputScript};
+#[cfg(feature = "cli")]
+use chip_8::io::terminal_debugger;
+#[cfg(feature = "cli")]
+use chip_8::io::terminal_io::{load_emulator, read_program, run};
+#[cfg(feature = "cli")]
+use chip_8::io::NoInput;
+#[cfg(feature = "cli")]
+use chip_8::palette::Theme;
+#[cfg(feature = "cli")]
+use chip_8::recorder::{Recorder, RecordingFormat};
+#[cfg(feature = "cli")]
+use chip_8::trace::{parse_trace, TraceComparer};
+#[cfg(feature = "cli")]
+use chip_8::Platform;
+#[cfg(feature = "cli")]
+use std::fs::File;
+#[cfg(feature = "cli")]
+use std::io::{stdout, BufWriter, Write};
+#[cfg(feature = "cli")]
+use std::path::Path;
+#[cfg(feature = "cli")]
+use std::{env, fs, process};
 
 fn main() {
     #[cfg(feature = "cli")]
-    run()
+    {
+        let mut args = env::args().skip(1).peekable();
+        match args.peek().map(String::as_str) {
+            Some("disasm") => {
+                args.next();
+                disasm(args)
+            }
+            Some("asm") => {
+                args.next();
+                asm(args)
+            }
+            Some("debug") => {
+                args.next();
+                terminal_debugger::run(args)
+            }
+            Some("dap") => {
+                args.next();
+                dap::run(args)
+            }
+            Some("trace-diff") => {
+                args.next();
+                trace_diff(args)
+            }
+            Some("headless") => {
+                args.next();
+                headless(args)
+            }
+            _ => run(args),
+        }
+    }
+}
+
+/// `chip-8 disasm <rom> [--platform <platform>] [--coverage <map>...]` prints the rom as Octo source
+///
+/// coverage maps of runs of the rom tell code from data, several of them are combined,
+/// exits with 1 on errors
+#[cfg(feature = "cli")]
+fn disasm(args: impl Iterator<Item = String>) {
+    if let Err(err) = try_disasm(args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "cli")]
+fn try_disasm(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut program_path = None;
+    let mut platform = Platform::X0Chip;
+    let mut coverage = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => platform = args.next().unwrap_or_default().parse()?,
+            "--coverage" => {
+                let path = args.next().unwrap_or_default();
+                let map = fs::read_to_string(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|map| map.parse::<Coverage>())
+                    .map_err(|err| format!("{}: {}", path, err))?;
+                coverage.get_or_insert_with(Coverage::new).merge(&map);
+            }
+            _ => program_path = Some(arg),
+        }
+    }
+
+    let program_path = program_path.ok_or("Please specify a program path")?;
+    let program = read_program(&program_path)?;
+
+    // a closed pipe is not worth a panic
+    let mut stdout = stdout().lock();
+    let _ = match coverage {
+        Some(coverage) => {
+            let summary = coverage.summary(&program, platform);
+            let disassembly = disassemble_with_coverage(&program, platform, &coverage);
+            summary
+                .to_string()
+                .lines()
+                .try_for_each(|line| writeln!(stdout, "# {}", line))
+                .and_then(|_| write!(stdout, "\n{}", disassembly))
+        }
+        None => write!(stdout, "{}", disassemble(&program, platform)),
+    };
+    Ok(())
+}
+
+/// `chip-8 asm <source.8o> [-o <rom.ch8>]` assembles Octo source, by default next to the source
+///
+/// exits with 1 on errors
+#[cfg(feature = "cli")]
+fn asm(args: impl Iterator<Item = String>) {
+    if let Err(err) = try_asm(args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "cli")]
+fn try_asm(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut source_path = None;
+    let mut output_path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output_path = args.next(),
+            _ => source_path = Some(arg),
+        }
+    }
+
+    let source_path = source_path.ok_or("Please specify a source path")?;
+    let output_path = output_path.unwrap_or_else(|| {
+        Path::new(&source_path)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .to_string()
+    });
+
+    let source = fs::read_to_string(&source_path).map_err(|err| err.to_string())?;
+    let assembly = assemble(&source).map_err(|err| format!("{}:{}", source_path, err))?;
+    fs::write(&output_path, assembly.rom).map_err(|err| err.to_string())
+}
+
+/// `chip-8 trace-diff <reference> <rom> [options]` runs the rom until it differs from the reference trace
+///
+/// takes the same options as running a rom, no keys are pressed, exits with 1 on errors or when the run diverged
+#[cfg(feature = "cli")]
+fn trace_diff(args: impl Iterator<Item = String>) {
+    if let Err(err) = try_trace_diff(args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "cli")]
+fn try_trace_diff(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let reference_path = args.next().ok_or("Please specify a reference trace")?;
+    let reference = fs::read_to_string(&reference_path)
+        .map_err(|err| err.to_string())
+        .and_then(|trace| parse_trace(&trace))
+        .map_err(|err| format!("{}: {}", reference_path, err))?;
+    let cycles = reference.len();
+
+    let mut emulator = load_emulator(args)?;
+
+    let mut comparer = TraceComparer::new(reference);
+    let mut error = None;
+    while comparer.divergence().is_none() && !comparer.is_finished() && !emulator.has_exited() {
+        if let Err(err) = emulator.run_frame_inspected(&NoInput, &mut comparer) {
+            error = Some(err);
+            break;
+        }
+    }
+
+    if let Some(divergence) = comparer.divergence() {
+        println!(
+            "diverged at cycle {} in {}",
+            divergence.actual.cycle,
+            divergence.fields.join(", ")
+        );
+        if let Some(previous) = &divergence.previous {
+            println!("last match {}", previous);
+        }
+        println!("expected   {}", divergence.expected);
+        println!("actual     {}", divergence.actual);
+        process::exit(1);
+    }
+
+    if comparer.is_finished() {
+        println!("matched all {} cycles", cycles);
+        return Ok(());
+    }
+
+    match error {
+        Some(err) => println!("{} after {} of {} cycles", err, comparer.cycle(), cycles),
+        None => println!("exited after {} of {} cycles", comparer.cycle(), cycles),
+    }
+    process::exit(1);
+}
+
+/// `chip-8 headless <rom> [--frames <n>] [--input <script>] [--pbm <file>] [--png <file>] [--ascii <file>] [--expect <hash>] [options]`
+///
+/// runs the rom without a terminal for 60 frames unless told otherwise, then writes the display
+/// and prints its hash, the ASCII rendering goes to stdout without `--ascii`,
+/// takes the same options as running a rom and exits with 1 on errors or an unexpected hash
+///
+/// `--record <file.gif|file.y4m>` records every frame, `--scale <n>`, `--theme <name>` and `--palette <colors>`
+/// change how recordings and the PNG look
+#[cfg(feature = "cli")]
+fn headless(args: impl Iterator<Item = String>) {
+    if let Err(err) = try_headless(args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "cli")]
+fn try_headless(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut frames = 60;
+    let mut script = InputScript::new();
+    let mut pbm_path = None;
+    let mut png_path = None;
+    let mut ascii_path = None;
+    let mut expected_hash = None;
+    let mut record_path = None;
+    let mut scale = 1;
+    let mut theme = Theme::default();
+    let mut palette = None;
+    let mut rest = vec![];
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                let value = args.next().unwrap_or_default();
+                frames = value
+                    .parse()
+                    .map_err(|err| format!("invalid frames {:?}: {}", value, err))?;
+            }
+            "--input" => {
+                let path = args.next().unwrap_or_default();
+                script = fs::read_to_string(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|script| script.parse())
+                    .map_err(|err| format!("{}: {}", path, err))?;
+            }
+            "--pbm" => pbm_path = args.next(),
+            "--png" => png_path = args.next(),
+            "--ascii" => ascii_path = args.next(),
+            "--record" => record_path = args.next(),
+            "--scale" => {
+                let value = args.next().unwrap_or_default();
+                scale = match value.parse() {
+                    Ok(scale) if scale > 0 => scale,
+                    _ => return Err(format!("invalid scale {:?}", value)),
+                };
+            }
+            "--theme" => theme = args.next().unwrap_or_default().parse()?,
+            "--palette" => palette = args.next(),
+            "--expect" => {
+                let value = args.next().unwrap_or_default();
+                let hash = u64::from_str_radix(value.trim_start_matches("0x"), 16)
+                    .map_err(|err| format!("invalid hash {:?}: {}", value, err))?;
+                expected_hash = Some(hash);
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    let mut emulator = load_emulator(rest.into_iter())?;
+    let palette = match palette {
+        Some(colors) => theme.palette.with_colors(&colors)?,
+        None => theme.palette,
+    };
+
+    let mut recorder = match &record_path {
+        Some(path) => {
+            let format = RecordingFormat::from_path(path)?;
+            let display = emulator.display();
+            let recorder = File::create(path).and_then(|file| {
+                Recorder::new(
+                    BufWriter::new(file),
+                    format,
+                    display.screen_width(),
+                    display.screen_height(),
+                    scale,
+                    &palette,
+                )
+            });
+            Some(recorder.map_err(|err| format!("{}: {}", path, err))?)
+        }
+        None => None,
+    };
+
+    let run = run_headless_inspected(&mut emulator, frames, &script, &mut recorder)
+        .map_err(|err| err.to_string())?;
+    let framebuffer = run.framebuffer;
+
+    if let (Some(recorder), Some(path)) = (recorder, &record_path) {
+        recorder
+            .finish()
+            .map_err(|err| format!("{}: {}", path, err))?;
+    }
+
+    let write = |path: &str, contents: &[u8]| {
+        fs::write(path, contents).map_err(|err| format!("{}: {}", path, err))
+    };
+    if let Some(path) = pbm_path {
+        write(&path, &framebuffer.to_pbm())?;
+    }
+    if let Some(path) = png_path {
+        let png = framebuffer
+            .to_png(scale, &palette)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        write(&path, &png)?;
+    }
+    match ascii_path {
+        Some(path) => write(&path, framebuffer.to_string().as_bytes())?,
+        None => print!("{}", framebuffer),
+    }
+
+    if run.exited {
+        println!("exited after {} frames", run.frames);
+    } else {
+        println!("ran {} frames", run.frames);
+    }
+    let hash = framebuffer.hash();
+    println!("hash {:016x}", hash);
+
+    match expected_hash {
+        Some(expected) if expected != hash => Err(format!(
+            "expected hash {:016x} but got {:016x}",
+            expected, hash
+        )),
+        _ => Ok(()),
+    }
 }
@@ -1,3 +1,29 @@
+use crate::error::EmulatorError;
+use crate::state::{StateError, StateReader, StateWriter};
+use serde::Serialize;
+
+pub const STACK_SIZE: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// bytes an instruction reads or writes through the index register
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub access: Access,
+    pub address: usize,
+    pub length: usize,
+}
+
+impl MemoryAccess {
+    pub fn contains(&self, address: usize) -> bool {
+        (self.address..self.address + self.length).contains(&address)
+    }
+}
+
 #[derive(Debug)]
 pub struct Memory {
     mem: Vec<u8>,
@@ -5,6 +31,7 @@ pub struct Memory {
     index_register: u16,
     stack: Vec<u16>,
     pc: u16,
+    flags: [u8; 16],
 }
 
 impl Memory {
@@ -15,39 +42,75 @@ impl Memory {
             index_register: 0,
             stack: vec![],
             pc: 0,
+            flags: [0; 16],
         }
     }
 
+    pub fn size(&self) -> usize {
+        self.mem.len()
+    }
+
     pub fn write_pc(&mut self, pc: u16) {
         self.pc = pc;
     }
 
-    pub fn decrement_pc(&mut self) {
-        self.pc -= 2;
+    pub fn decrement_pc(&mut self) -> Result<(), EmulatorError> {
+        self.pc = self
+            .pc
+            .checked_sub(2)
+            .ok_or(EmulatorError::ProgramCounterUnderflow)?;
+        Ok(())
     }
 
     pub fn increment_pc(&mut self) {
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     pub fn read_pc(&self) -> u16 {
         self.pc
     }
 
-    pub fn write_slice(&mut self, index: usize, data: &[u8]) {
-        self.mem[index..index + data.len()].copy_from_slice(data);
+    pub fn write_slice(&mut self, index: usize, data: &[u8]) -> Result<(), EmulatorError> {
+        let target = self.mem.get_mut(index..index + data.len()).ok_or(
+            EmulatorError::MemoryOutOfBounds {
+                address: (index + data.len()).saturating_sub(1),
+            },
+        )?;
+
+        target.copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn read_slice(&self, index: usize, length: usize) -> Result<&[u8], EmulatorError> {
+        self.mem
+            .get(index..index + length)
+            .ok_or(EmulatorError::MemoryOutOfBounds {
+                address: (index + length).saturating_sub(1),
+            })
     }
 
-    pub fn read_u8(&self, index: usize) -> u8 {
-        self.mem[index]
+    pub fn read_u8(&self, index: usize) -> Result<u8, EmulatorError> {
+        self.mem
+            .get(index)
+            .copied()
+            .ok_or(EmulatorError::MemoryOutOfBounds { address: index })
     }
 
-    pub fn write_u8(&mut self, index: usize, data: u8) {
-        self.mem[index] = data;
+    pub fn write_u8(&mut self, index: usize, data: u8) -> Result<(), EmulatorError> {
+        let target = self
+            .mem
+            .get_mut(index)
+            .ok_or(EmulatorError::MemoryOutOfBounds { address: index })?;
+
+        *target = data;
+        Ok(())
     }
 
-    pub fn read_u16(&self, index: usize) -> u16 {
-        u16::from_be_bytes([self.mem[index], self.mem[index + 1]])
+    pub fn read_u16(&self, index: usize) -> Result<u16, EmulatorError> {
+        Ok(u16::from_be_bytes([
+            self.read_u8(index)?,
+            self.read_u8(index + 1)?,
+        ]))
     }
 
     pub fn registers(&self) -> &[u8; 16] {
@@ -70,12 +133,68 @@ impl Memory {
         self.index_register = index;
     }
 
-    pub fn push_stack(&mut self, value: u16) {
+    pub fn read_flag(&self, index: usize) -> u8 {
+        self.flags[index]
+    }
+
+    pub fn write_flag(&mut self, index: usize, value: u8) {
+        self.flags[index] = value;
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn push_stack(&mut self, value: u16) -> Result<(), EmulatorError> {
+        if self.stack.len() >= STACK_SIZE {
+            return Err(EmulatorError::StackOverflow);
+        }
+
         self.stack.push(value);
+        Ok(())
     }
 
-    pub fn pop_stack(&mut self) -> u16 {
-        self.stack.pop().unwrap()
+    pub fn pop_stack(&mut self) -> Result<u16, EmulatorError> {
+        self.stack.pop().ok_or(EmulatorError::StackUnderflow)
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.bytes(&self.mem);
+        writer.bytes(&self.registers);
+        writer.u16(self.index_register);
+        writer.u8(self.stack.len() as u8);
+        for &address in &self.stack {
+            writer.u16(address);
+        }
+        writer.u16(self.pc);
+        writer.bytes(&self.flags);
+    }
+
+    /// restores memory of `size` bytes
+    pub(crate) fn load_state(reader: &mut StateReader, size: usize) -> Result<Memory, StateError> {
+        let mem = reader.bytes()?;
+        if mem.len() != size {
+            return Err(StateError::Invalid { field: "memory" });
+        }
+
+        let registers = reader.array("registers")?;
+        let index_register = reader.u16()?;
+        let stack_size = reader.u8()? as usize;
+        if stack_size > STACK_SIZE {
+            return Err(StateError::Invalid { field: "stack" });
+        }
+        let stack = (0..stack_size)
+            .map(|_| reader.u16())
+            .collect::<Result<_, _>>()?;
+
+        Ok(Memory {
+            mem: mem.to_vec(),
+            registers,
+            index_register,
+            stack,
+            pc: reader.u16()?,
+            flags: reader.array("flags")?,
+        })
     }
 }
 
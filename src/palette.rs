@@ -0,0 +1,194 @@
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// colors of the four plane combinations: off, plane 1, plane 2 and both planes
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct Palette {
+    pub colors: [[u8; 3]; 4],
+}
+
+/// the palette of the default theme
+impl Default for Palette {
+    fn default() -> Self {
+        Theme::default().palette
+    }
+}
+
+impl Palette {
+    /// the color of a pixel with the plane bits `pixel`
+    pub fn color(&self, pixel: u8) -> [u8; 3] {
+        self.colors[(pixel & 0b11) as usize]
+    }
+
+    /// the color of pixels without any plane set
+    pub fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    /// replaces the first colors with two to four comma separated hex colors like `#000000,#ffffff`
+    pub fn with_colors(mut self, value: &str) -> Result<Palette, String> {
+        let colors = value.split(',').collect::<Vec<_>>();
+        if !(2..=4).contains(&colors.len()) {
+            return Err(format!(
+                "expected 2 to 4 colors but found {} in {:?}",
+                colors.len(),
+                value
+            ));
+        }
+
+        for (color, text) in self.colors.iter_mut().zip(colors) {
+            let hex = text.trim().trim_start_matches('#');
+            // from_str_radix alone would take a sign like `+fffff`
+            let rgb = Some(hex)
+                .filter(|hex| hex.len() == 6 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("{:?} is not a color like #ff8800", text))?;
+            let [_, r, g, b] = rgb.to_be_bytes();
+            *color = [r, g, b];
+        }
+        Ok(self)
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// two to four comma separated hex colors like `#000000,#ffffff`, missing ones keep their default
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Palette::default().with_colors(value)
+    }
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let colors = self
+            .colors
+            .iter()
+            .map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b))
+            .collect::<Vec<_>>();
+        write!(f, "{}", colors.join(","))
+    }
+}
+
+/// the display palette and the colors of everything around it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct Theme {
+    pub name: &'static str,
+    /// background and plane colors of the display
+    pub palette: Palette,
+    /// text like the title, registers and keys
+    pub foreground: [u8; 3],
+    /// frames around the display and the keypad
+    pub border: [u8; 3],
+}
+
+impl Theme {
+    /// the colors Octo starts with
+    pub const OCTO: Theme = Theme {
+        name: "octo",
+        palette: Palette {
+            colors: [
+                [0x99, 0x66, 0x00],
+                [0xFF, 0xCC, 0x00],
+                [0xFF, 0x66, 0x00],
+                [0x66, 0x22, 0x00],
+            ],
+        },
+        foreground: [0xFF, 0xCC, 0x00],
+        border: [0xFF, 0xAA, 0x00],
+    };
+
+    /// white on black with gray for the second plane
+    pub const CLASSIC: Theme = Theme {
+        name: "classic",
+        palette: Palette {
+            colors: [[0x00; 3], [0xFF; 3], [0xAA; 3], [0x55; 3]],
+        },
+        foreground: [0xFF; 3],
+        border: [0xAA; 3],
+    };
+
+    pub const AMBER: Theme = Theme {
+        name: "amber",
+        palette: Palette {
+            colors: [
+                [0x1A, 0x10, 0x00],
+                [0xFF, 0xB0, 0x00],
+                [0x99, 0x5C, 0x00],
+                [0xFF, 0xD8, 0x80],
+            ],
+        },
+        foreground: [0xFF, 0xB0, 0x00],
+        border: [0x99, 0x5C, 0x00],
+    };
+
+    pub const GREEN_PHOSPHOR: Theme = Theme {
+        name: "green-phosphor",
+        palette: Palette {
+            colors: [
+                [0x00, 0x14, 0x00],
+                [0x33, 0xFF, 0x33],
+                [0x1A, 0x99, 0x1A],
+                [0xB3, 0xFF, 0xB3],
+            ],
+        },
+        foreground: [0x33, 0xFF, 0x33],
+        border: [0x1A, 0x99, 0x1A],
+    };
+
+    /// colors that are easy to tell apart, for low vision and bright rooms
+    pub const HIGH_CONTRAST: Theme = Theme {
+        name: "high-contrast",
+        palette: Palette {
+            colors: [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0xFF, 0xFF, 0x00],
+                [0x00, 0xFF, 0xFF],
+            ],
+        },
+        foreground: [0xFF, 0xFF, 0xFF],
+        border: [0xFF, 0xFF, 0x00],
+    };
+
+    pub const ALL: [Theme; 5] = [
+        Theme::OCTO,
+        Theme::CLASSIC,
+        Theme::AMBER,
+        Theme::GREEN_PHOSPHOR,
+        Theme::HIGH_CONTRAST,
+    ];
+
+    /// the theme with `palette` in place of its own
+    pub fn with_palette(mut self, palette: Palette) -> Theme {
+        self.palette = palette;
+        self
+    }
+
+    /// the color behind the display
+    pub fn background(&self) -> [u8; 3] {
+        self.palette.background()
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::OCTO
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let name = value.to_lowercase().replace(['_', ' '], "-");
+        Theme::ALL
+            .into_iter()
+            .find(|theme| theme.name == name)
+            .ok_or_else(|| {
+                let names = Theme::ALL.map(|theme| theme.name);
+                format!("unknown theme: {}, try one of {}", value, names.join(", "))
+            })
+    }
+}
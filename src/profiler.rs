@@ -0,0 +1,246 @@
+use crate::inspector::Inspector;
+use crate::instruction::Instruction;
+use crate::Emulator;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+
+/// rows shown in each table of the report
+const REPORT_ROWS: usize = 20;
+
+/// how often something ran
+#[derive(Debug, Copy, Clone)]
+pub struct HotSpot {
+    pub address: u16,
+    pub count: u64,
+    /// the last instruction run at the address
+    pub instruction: Instruction,
+}
+
+/// cycles spent in a subroutine, the program itself is the subroutine at `None`
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SubroutineProfile {
+    pub calls: u64,
+    /// instructions run by the subroutine itself
+    pub own: u64,
+    /// instructions run by the subroutine and everything it called
+    pub total: u64,
+}
+
+/// counts what the emulator runs, per address, opcode class, subroutine and frame
+///
+/// subroutines are followed through `2NNN` and `00EE`, so jumping out of one is not noticed
+#[derive(Default)]
+pub struct Profiler {
+    instructions: u64,
+    addresses: HashMap<u16, HotSpot>,
+    patterns: BTreeMap<&'static str, u64>,
+    calls: HashMap<u16, u64>,
+    /// addresses of the called subroutines, innermost last
+    stack: Vec<u16>,
+    /// instructions run with exactly this call stack
+    stacks: HashMap<Vec<u16>, u64>,
+    frames: Vec<u64>,
+    frame_instructions: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// instructions run so far
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// instructions run in every finished frame
+    pub fn frames(&self) -> &[u64] {
+        &self.frames
+    }
+
+    /// every address that ran, the most run first
+    pub fn hot_spots(&self) -> Vec<HotSpot> {
+        let mut hot_spots = self.addresses.values().copied().collect::<Vec<_>>();
+        hot_spots.sort_by(|a, b| b.count.cmp(&a.count).then(a.address.cmp(&b.address)));
+        hot_spots
+    }
+
+    /// instructions run per opcode pattern, e.g. `DXYN`
+    pub fn patterns(&self) -> &BTreeMap<&'static str, u64> {
+        &self.patterns
+    }
+
+    /// every subroutine that ran, keyed by its address
+    pub fn subroutines(&self) -> BTreeMap<Option<u16>, SubroutineProfile> {
+        let mut subroutines = BTreeMap::<Option<u16>, SubroutineProfile>::new();
+        for (&address, &calls) in &self.calls {
+            subroutines.entry(Some(address)).or_default().calls = calls;
+        }
+
+        for (stack, &count) in &self.stacks {
+            subroutines.entry(stack.last().copied()).or_default().own += count;
+
+            // a recursive subroutine only counts once
+            let mut counted = HashSet::new();
+            subroutines.entry(None).or_default().total += count;
+            for &address in stack {
+                if counted.insert(address) {
+                    subroutines.entry(Some(address)).or_default().total += count;
+                }
+            }
+        }
+
+        subroutines
+    }
+
+    /// writes the hot spots, opcode classes, subroutines and frames as text
+    pub fn write_report(&self, output: &mut impl Write, name: &str) -> std::io::Result<()> {
+        writeln!(output, "profile of {}", name)?;
+        writeln!(
+            output,
+            "{} instructions in {} frames",
+            self.instructions,
+            self.frames.len()
+        )?;
+
+        let percent = |count: u64| count as f64 * 100.0 / self.instructions.max(1) as f64;
+
+        writeln!(output, "\nhot spots")?;
+        writeln!(
+            output,
+            "{:>10} {:>7}  address  instruction",
+            "count", "share"
+        )?;
+        for hot_spot in self.hot_spots().iter().take(REPORT_ROWS) {
+            writeln!(
+                output,
+                "{:>10} {:>6.2}%  {:#06x}   {}",
+                hot_spot.count,
+                percent(hot_spot.count),
+                hot_spot.address,
+                hot_spot.instruction
+            )?;
+        }
+
+        let mut patterns = self.patterns.iter().collect::<Vec<_>>();
+        patterns.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        writeln!(output, "\nopcode classes")?;
+        writeln!(output, "{:>10} {:>7}  opcode", "count", "share")?;
+        for (pattern, &count) in patterns {
+            writeln!(
+                output,
+                "{:>10} {:>6.2}%  {}",
+                count,
+                percent(count),
+                pattern
+            )?;
+        }
+
+        let mut subroutines = self.subroutines().into_iter().collect::<Vec<_>>();
+        subroutines.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(&b.0)));
+        writeln!(output, "\nsubroutines")?;
+        writeln!(
+            output,
+            "{:>10} {:>10} {:>7} {:>8}  subroutine",
+            "total", "own", "share", "calls"
+        )?;
+        for (address, profile) in subroutines.iter().take(REPORT_ROWS) {
+            writeln!(
+                output,
+                "{:>10} {:>10} {:>6.2}% {:>8}  {}",
+                profile.total,
+                profile.own,
+                percent(profile.total),
+                profile.calls,
+                subroutine_name(*address)
+            )?;
+        }
+
+        if let (Some(min), Some(max)) = (self.frames.iter().min(), self.frames.iter().max()) {
+            let instructions = self.frames.iter().sum::<u64>();
+            writeln!(output, "\ninstructions per frame")?;
+            writeln!(
+                output,
+                "min {}  average {:.1}  max {}",
+                min,
+                instructions as f64 / self.frames.len() as f64,
+                max
+            )?;
+
+            let mut frames = self.frames.iter().enumerate().collect::<Vec<_>>();
+            frames.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(&b.0)));
+            writeln!(output, "\nbusiest frames")?;
+            writeln!(output, "{:>10} {:>12}", "frame", "instructions")?;
+            for (frame, count) in frames.iter().take(REPORT_ROWS) {
+                writeln!(output, "{:>10} {:>12}", frame, count)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// writes one line per call stack and the instructions run in it, as flame graph tools read them
+    pub fn write_folded(&self, output: &mut impl Write) -> std::io::Result<()> {
+        let mut stacks = self
+            .stacks
+            .iter()
+            .map(|(stack, count)| {
+                let names = std::iter::once(subroutine_name(None))
+                    .chain(stack.iter().map(|&address| subroutine_name(Some(address))))
+                    .collect::<Vec<_>>();
+                (names.join(";"), count)
+            })
+            .collect::<Vec<_>>();
+        stacks.sort();
+
+        for (stack, count) in stacks {
+            writeln!(output, "{} {}", stack, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// names subroutines like the disassembler labels them
+fn subroutine_name(address: Option<u16>) -> String {
+    match address {
+        Some(address) => format!("sub_{:03x}", address),
+        None => "main".to_string(),
+    }
+}
+
+impl Inspector for Profiler {
+    fn before_instruction(&mut self, emulator: &Emulator, instruction: Instruction) {
+        let address = emulator.memory().read_pc();
+
+        // returns and anything else emptying the stack, like loading a state, end subroutines
+        self.stack.truncate(emulator.memory().stack().len());
+
+        self.instructions += 1;
+        self.frame_instructions += 1;
+        let hot_spot = self.addresses.entry(address).or_insert(HotSpot {
+            address,
+            count: 0,
+            instruction,
+        });
+        hot_spot.count += 1;
+        hot_spot.instruction = instruction;
+        *self.patterns.entry(instruction.pattern()).or_default() += 1;
+
+        match self.stacks.get_mut(self.stack.as_slice()) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.stack.clone(), 1);
+            }
+        }
+
+        if let Instruction::Call(target) = instruction {
+            self.stack.push(target);
+            *self.calls.entry(target).or_default() += 1;
+        }
+    }
+
+    fn after_frame(&mut self, _emulator: &Emulator) {
+        self.frames.push(self.frame_instructions);
+        self.frame_instructions = 0;
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 
-#[derive(Deserialize, Serialize, EnumIter)]
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, EnumIter)]
 pub enum Program {
     Chip8Logo,
     Corax,
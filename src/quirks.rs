@@ -0,0 +1,92 @@
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::Platform;
+use serde::{Deserialize, Serialize};
+
+/// how `FX55` and `FX65` change the index register
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryIncrement {
+    None,
+    X,
+    XPlusOne,
+}
+
+/// behaviours the different CHIP-8 interpreters disagree on
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quirks {
+    /// `8XY6` and `8XYE` shift VX in place instead of storing the shifted VY
+    pub shift: bool,
+    /// `FX55` and `FX65` leave I in place, add X or add X + 1 to it
+    pub memory_increment: MemoryIncrement,
+    /// `BXNN` jumps to `XNN + VX` instead of `BNNN + V0`
+    pub jump: bool,
+    /// `8XY1`, `8XY2` and `8XY3` reset VF to 0
+    pub vf_reset: bool,
+    /// sprites wrap around the screen edges instead of getting clipped
+    pub wrap: bool,
+    /// `DXYN` waits for the vertical blank, so at most one sprite is drawn per frame
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    pub fn preset(platform: Platform) -> Quirks {
+        match platform {
+            Platform::Chip8 => Quirks {
+                shift: false,
+                memory_increment: MemoryIncrement::XPlusOne,
+                jump: false,
+                vf_reset: true,
+                wrap: false,
+                display_wait: true,
+            },
+            Platform::SuperChip => Quirks {
+                shift: true,
+                memory_increment: MemoryIncrement::None,
+                jump: true,
+                vf_reset: false,
+                wrap: false,
+                display_wait: false,
+            },
+            Platform::X0Chip => Quirks {
+                shift: false,
+                memory_increment: MemoryIncrement::XPlusOne,
+                jump: false,
+                vf_reset: false,
+                wrap: true,
+                display_wait: false,
+            },
+        }
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.bool(self.shift);
+        writer.u8(match self.memory_increment {
+            MemoryIncrement::None => 0,
+            MemoryIncrement::X => 1,
+            MemoryIncrement::XPlusOne => 2,
+        });
+        writer.bool(self.jump);
+        writer.bool(self.vf_reset);
+        writer.bool(self.wrap);
+        writer.bool(self.display_wait);
+    }
+
+    pub(crate) fn load_state(reader: &mut StateReader) -> Result<Quirks, StateError> {
+        Ok(Quirks {
+            shift: reader.bool()?,
+            memory_increment: match reader.u8()? {
+                0 => MemoryIncrement::None,
+                1 => MemoryIncrement::X,
+                2 => MemoryIncrement::XPlusOne,
+                _ => {
+                    return Err(StateError::Invalid {
+                        field: "memory increment",
+                    })
+                }
+            },
+            jump: reader.bool()?,
+            vf_reset: reader.bool()?,
+            wrap: reader.bool()?,
+            display_wait: reader.bool()?,
+        })
+    }
+}
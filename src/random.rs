@@ -0,0 +1,100 @@
+use std::fmt::Debug;
+
+/// source of the random bytes `CXNN` masks
+///
+/// the state is part of the machine state, restoring it makes the generator repeat its output
+pub trait RandomSource: Debug + Send {
+    fn next_u8(&mut self) -> u8;
+
+    fn state(&self) -> Vec<u8>;
+
+    /// goes back to a `state`, the source stays untouched if it doesn't fit
+    fn restore(&mut self, state: &[u8]) -> Result<(), String>;
+}
+
+/// xorshift64* generator, the default source
+#[derive(Debug, Clone)]
+pub struct Xorshift {
+    state: u64,
+}
+
+impl Xorshift {
+    pub fn new(seed: u64) -> Xorshift {
+        Xorshift {
+            state: Self::scramble(seed),
+        }
+    }
+
+    /// spreads the seed over all bits with splitmix64, xorshift gets stuck on a state of 0
+    fn scramble(seed: u64) -> u64 {
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        (z ^ (z >> 31)).max(1)
+    }
+}
+
+impl RandomSource for Xorshift {
+    fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.state.to_be_bytes().to_vec()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), String> {
+        self.state = u64::from_be_bytes(state_bytes(state)?);
+        Ok(())
+    }
+}
+
+/// repeats a fixed list of bytes, useful to force specific outcomes in tests
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl Sequence {
+    pub fn new(bytes: Vec<u8>) -> Sequence {
+        Sequence { bytes, position: 0 }
+    }
+}
+
+impl RandomSource for Sequence {
+    fn next_u8(&mut self) -> u8 {
+        let Some(&byte) = self.bytes.get(self.position) else {
+            return 0;
+        };
+        self.position = (self.position + 1) % self.bytes.len();
+        byte
+    }
+
+    fn state(&self) -> Vec<u8> {
+        (self.position as u64).to_be_bytes().to_vec()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), String> {
+        let position = u64::from_be_bytes(state_bytes(state)?) as usize;
+        if position > 0 && position >= self.bytes.len() {
+            return Err(format!(
+                "position {} is past the {} bytes",
+                position,
+                self.bytes.len()
+            ));
+        }
+        self.position = position;
+        Ok(())
+    }
+}
+
+/// the eight bytes of a `u64` state
+fn state_bytes(state: &[u8]) -> Result<[u8; 8], String> {
+    state
+        .try_into()
+        .map_err(|_| format!("expected 8 bytes of state but got {}", state.len()))
+}
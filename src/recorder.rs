@@ -0,0 +1,250 @@
+use crate::framebuffer::Framebuffer;
+use crate::inspector::Inspector;
+use crate::palette::Palette;
+use crate::Emulator;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// frames per second of the emulated display
+const FPS: u64 = 60;
+
+/// shortest delay between GIF frames in hundredths of a second, most viewers slow down anything faster
+const MIN_GIF_DELAY: u64 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// an animated GIF, frames that change too quickly to show are dropped
+    Gif,
+    /// an uncompressed YUV4MPEG2 stream with every frame, for ffmpeg and friends
+    Y4m,
+}
+
+impl RecordingFormat {
+    /// the format matching the extension of `path`
+    pub fn from_path(path: &str) -> Result<RecordingFormat, String> {
+        Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .parse()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::Y4m => "y4m",
+        }
+    }
+}
+
+impl FromStr for RecordingFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "gif" => Ok(RecordingFormat::Gif),
+            "y4m" => Ok(RecordingFormat::Y4m),
+            _ => Err(format!("unknown recording format: {}", value)),
+        }
+    }
+}
+
+enum Encoder<W: Write> {
+    Gif {
+        encoder: gif::Encoder<W>,
+        /// the frame waiting for its delay and the frame it first appeared in
+        pending: Option<(Vec<u8>, u64)>,
+    },
+    Y4m {
+        output: W,
+        /// the Y, U and V plane values of every palette color
+        planes: [[u8; 4]; 3],
+    },
+}
+
+/// writes every frame of the display to a GIF or Y4M stream
+pub struct Recorder<W: Write> {
+    encoder: Encoder<W>,
+    scale: usize,
+    width: usize,
+    height: usize,
+    frame: u64,
+    error: Option<std::io::Error>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// starts a recording of a `width` by `height` display with every pixel scaled by `scale`
+    pub fn new(
+        mut output: W,
+        format: RecordingFormat,
+        width: usize,
+        height: usize,
+        scale: usize,
+        palette: &Palette,
+    ) -> std::io::Result<Recorder<W>> {
+        let (scaled_width, scaled_height) = (width * scale, height * scale);
+
+        let encoder = match format {
+            RecordingFormat::Gif => {
+                let size = |value: usize| u16::try_from(value).ok();
+                let (Some(gif_width), Some(gif_height)) = (size(scaled_width), size(scaled_height))
+                else {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!(
+                            "a {}x{} GIF is larger than the format allows",
+                            scaled_width, scaled_height
+                        ),
+                    ));
+                };
+                let mut encoder =
+                    gif::Encoder::new(output, gif_width, gif_height, &palette.colors.concat())
+                        .map_err(std::io::Error::other)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(std::io::Error::other)?;
+                Encoder::Gif {
+                    encoder,
+                    pending: None,
+                }
+            }
+            RecordingFormat::Y4m => {
+                writeln!(
+                    output,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    scaled_width, scaled_height, FPS
+                )?;
+                let colors = palette.colors.map(yuv);
+                Encoder::Y4m {
+                    output,
+                    planes: [0, 1, 2].map(|component| colors.map(|color| color[component])),
+                }
+            }
+        };
+
+        Ok(Recorder {
+            encoder,
+            scale,
+            width,
+            height,
+            frame: 0,
+            error: None,
+        })
+    }
+
+    /// frames recorded so far
+    pub fn frames(&self) -> u64 {
+        self.frame
+    }
+
+    /// adds the next frame, the framebuffer has to be as large as the recording
+    pub fn frame(&mut self, framebuffer: &Framebuffer) -> std::io::Result<()> {
+        if framebuffer.width != self.width || framebuffer.height != self.height {
+            return Err(std::io::Error::other(format!(
+                "expected a {}x{} frame but got {}x{}",
+                self.width, self.height, framebuffer.width, framebuffer.height
+            )));
+        }
+
+        let pixels = framebuffer.scaled(self.scale);
+        let frame = self.frame;
+        self.frame += 1;
+
+        match &mut self.encoder {
+            Encoder::Gif { encoder, pending } => {
+                let Some((pending_pixels, start)) = pending else {
+                    *pending = Some((pixels, frame));
+                    return Ok(());
+                };
+                if *pending_pixels == pixels {
+                    return Ok(());
+                }
+
+                let delay = gif_delay(*start, frame);
+                if delay < MIN_GIF_DELAY {
+                    // too short to be seen, the new frame takes over its time
+                    *pending_pixels = pixels;
+                    return Ok(());
+                }
+
+                let (width, height) = (self.width * self.scale, self.height * self.scale);
+                write_gif_frame(encoder, width, height, pending_pixels, delay)?;
+                *pending = Some((pixels, frame));
+                Ok(())
+            }
+            Encoder::Y4m { output, planes } => {
+                output.write_all(b"FRAME\n")?;
+                for colors in planes {
+                    let plane = pixels
+                        .iter()
+                        .map(|&pixel| colors[pixel as usize])
+                        .collect::<Vec<_>>();
+                    output.write_all(&plane)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// writes what is left of the recording, fails with the first error any frame ran into
+    pub fn finish(self) -> std::io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let (width, height) = (self.width * self.scale, self.height * self.scale);
+        match self.encoder {
+            Encoder::Gif {
+                mut encoder,
+                pending,
+            } => {
+                if let Some((pixels, start)) = pending {
+                    let delay = gif_delay(start, self.frame).max(MIN_GIF_DELAY);
+                    write_gif_frame(&mut encoder, width, height, &pixels, delay)?;
+                }
+                encoder.into_inner().map_err(std::io::Error::other)
+            }
+            Encoder::Y4m { mut output, .. } => {
+                output.flush()?;
+                Ok(output)
+            }
+        }
+    }
+}
+
+/// records the display after every frame, recording stops at the first error
+impl<W: Write> Inspector for Recorder<W> {
+    fn after_frame(&mut self, emulator: &Emulator) {
+        if self.error.is_none() {
+            self.error = self.frame(&Framebuffer::capture(emulator.display())).err();
+        }
+    }
+}
+
+/// hundredths of a second from the start of frame `start` to the start of frame `end`
+fn gif_delay(start: u64, end: u64) -> u64 {
+    let time = |frame: u64| (frame * 100 + FPS / 2) / FPS;
+    time(end) - time(start)
+}
+
+fn write_gif_frame<W: Write>(
+    encoder: &mut gif::Encoder<W>,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+    delay: u64,
+) -> std::io::Result<()> {
+    let mut frame = gif::Frame::from_indexed_pixels(width as u16, height as u16, pixels, None);
+    frame.delay = delay.min(u16::MAX as u64) as u16;
+    encoder.write_frame(&frame).map_err(std::io::Error::other)
+}
+
+/// the BT.601 studio range YUV of an RGB color
+fn yuv([r, g, b]: [u8; 3]) -> [u8; 3] {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = 16 + ((66 * r + 129 * g + 25 * b + 128) >> 8);
+    let u = 128 + ((-38 * r - 74 * g + 112 * b + 128) >> 8);
+    let v = 128 + ((112 * r - 94 * g - 18 * b + 128) >> 8);
+    [y as u8, u as u8, v as u8]
+}
@@ -0,0 +1,217 @@
+use crate::Emulator;
+use std::collections::VecDeque;
+
+/// a frame of every `KEYFRAME_INTERVAL` is stored on its own, the others as a delta against it
+const KEYFRAME_INTERVAL: usize = 60;
+
+/// a keyframe and the frames recorded after it
+#[derive(Debug)]
+struct Group {
+    /// run length encoded save state
+    keyframe: Vec<u8>,
+    /// length of the decoded keyframe, deltas only work on states of the same size
+    size: usize,
+    /// run length encoded xor of each state against the keyframe
+    deltas: Vec<Vec<u8>>,
+    /// the decoded keyframe while frames get added to this group
+    decoded: Option<Vec<u8>>,
+}
+
+impl Group {
+    fn frames(&self) -> usize {
+        1 + self.deltas.len()
+    }
+}
+
+/// ring buffer of the last frames, compressed by delta encoding them against keyframes
+#[derive(Debug)]
+pub struct Rewind {
+    capacity: usize,
+    groups: VecDeque<Group>,
+    frames: usize,
+}
+
+impl Rewind {
+    /// keeps up to `capacity` frames, 60 per second of gameplay
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            capacity: capacity.max(1),
+            groups: VecDeque::new(),
+            frames: 0,
+        }
+    }
+
+    /// amount of frames that can be rewound
+    pub fn len(&self) -> usize {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.frames = 0;
+    }
+
+    /// bytes used by the compressed frames
+    pub fn memory_usage(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|group| group.keyframe.len() + group.deltas.iter().map(Vec::len).sum::<usize>())
+            .sum()
+    }
+
+    /// stores the current state, meant to be called once per frame
+    pub fn record(&mut self, emulator: &Emulator) {
+        let state = emulator.save_state();
+
+        match self.groups.back_mut() {
+            Some(group) if group.frames() < KEYFRAME_INTERVAL && group.size == state.len() => {
+                let keyframe = group
+                    .decoded
+                    .get_or_insert_with(|| decode(&group.keyframe, None));
+                let delta = state
+                    .iter()
+                    .zip(keyframe.iter())
+                    .map(|(a, b)| a ^ b)
+                    .collect::<Vec<_>>();
+                group.deltas.push(encode(&delta));
+            }
+            _ => {
+                if let Some(group) = self.groups.back_mut() {
+                    group.decoded = None;
+                }
+                self.groups.push_back(Group {
+                    keyframe: encode(&state),
+                    size: state.len(),
+                    deltas: vec![],
+                    decoded: Some(state),
+                });
+            }
+        }
+        self.frames += 1;
+
+        // whole groups are dropped, so no delta outlives its keyframe
+        while self.frames > self.capacity {
+            match self.groups.pop_front() {
+                Some(group) => self.frames -= group.frames(),
+                None => break,
+            }
+        }
+    }
+
+    /// goes back `frames` recorded frames, returns how many frames were actually rewound
+    ///
+    /// the emulator is left at the newest remaining frame, or the oldest one once the buffer runs empty
+    pub fn rewind(&mut self, emulator: &mut Emulator, frames: usize) -> usize {
+        let mut oldest = None;
+        let mut rewound = 0;
+
+        while rewound < frames {
+            let Some(group) = self.groups.back_mut() else {
+                break;
+            };
+
+            if group.deltas.pop().is_none() {
+                oldest = self.groups.pop_back().map(|group| group.keyframe);
+            }
+            self.frames -= 1;
+            rewound += 1;
+        }
+
+        let state = match self.groups.back() {
+            Some(group) => {
+                let keyframe = match &group.decoded {
+                    Some(decoded) => decoded.clone(),
+                    None => decode(&group.keyframe, None),
+                };
+                match group.deltas.last() {
+                    Some(delta) => decode(delta, Some(&keyframe)),
+                    None => keyframe,
+                }
+            }
+            None => match oldest {
+                Some(keyframe) => decode(&keyframe, None),
+                None => return rewound,
+            },
+        };
+
+        // the states were taken from a running emulator, so they are always valid
+        let _ = emulator.load_state(&state);
+        rewound
+    }
+}
+
+/// stores runs of zeros as their length, everything else as literals
+///
+/// the output is a sequence of `zeros, literals, literal bytes` with both counts as varints
+fn encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![];
+    let mut index = 0;
+
+    while index < data.len() {
+        let zeros = data[index..].iter().take_while(|&&byte| byte == 0).count();
+        index += zeros;
+
+        // single zeros are cheaper as literals than as a run
+        let start = index;
+        while index < data.len()
+            && (data[index] != 0 || matches!(data.get(index + 1), Some(&byte) if byte != 0))
+        {
+            index += 1;
+        }
+
+        write_varint(&mut encoded, zeros);
+        write_varint(&mut encoded, index - start);
+        encoded.extend(&data[start..index]);
+    }
+
+    encoded
+}
+
+/// reverses `encode`, xoring the result onto `base` when given
+fn decode(encoded: &[u8], base: Option<&[u8]>) -> Vec<u8> {
+    let mut data = vec![];
+    let mut index = 0;
+
+    while index < encoded.len() {
+        let zeros = read_varint(encoded, &mut index);
+        let literals = read_varint(encoded, &mut index);
+        data.resize(data.len() + zeros, 0);
+        data.extend(&encoded[index..index + literals]);
+        index += literals;
+    }
+
+    if let Some(base) = base {
+        data.resize(base.len(), 0);
+        for (byte, base) in data.iter_mut().zip(base) {
+            *byte ^= base;
+        }
+    }
+
+    data
+}
+
+fn write_varint(encoded: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        encoded.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    encoded.push(value as u8);
+}
+
+fn read_varint(encoded: &[u8], index: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some(&byte) = encoded.get(*index) {
+        *index += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
@@ -0,0 +1,173 @@
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// first bytes of every save state
+pub const STATE_MAGIC: [u8; 4] = *b"C8ST";
+
+/// bumped whenever the layout changes, states of other versions are rejected
+pub const STATE_VERSION: u16 = 2;
+
+/// bytes of magic and version in front of the machine state
+const HEADER_SIZE: usize = 6;
+/// bytes of the trailing checksum
+const CHECKSUM_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum StateError {
+    NotAState,
+    UnsupportedVersion { version: u16 },
+    ChecksumMismatch,
+    Truncated,
+    Invalid { field: &'static str },
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::NotAState => write!(f, "not a save state"),
+            StateError::UnsupportedVersion { version } => write!(
+                f,
+                "save state version {} is not supported, expected {}",
+                version, STATE_VERSION
+            ),
+            StateError::ChecksumMismatch => write!(f, "save state is corrupted"),
+            StateError::Truncated => write!(f, "save state ends unexpectedly"),
+            StateError::Invalid { field } => write!(f, "save state has an invalid {}", field),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+/// CRC-32 as used by zip and png
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// writes big endian values after the header, `finish` appends the checksum
+pub(crate) struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        let mut bytes = STATE_MAGIC.to_vec();
+        bytes.extend(STATE_VERSION.to_be_bytes());
+        StateWriter { bytes }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes.extend(value.to_be_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes.extend(value.to_be_bytes());
+    }
+
+    /// writes the length in front of the bytes
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.bytes.extend(value);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let checksum = crc32(&self.bytes);
+        self.u32(checksum);
+        self.bytes
+    }
+}
+
+/// reads what a [StateWriter] wrote, the header and checksum are checked up front
+pub(crate) struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(state: &'a [u8]) -> Result<StateReader<'a>, StateError> {
+        if state.len() < HEADER_SIZE + CHECKSUM_SIZE || state[..4] != STATE_MAGIC {
+            return Err(StateError::NotAState);
+        }
+
+        let version = u16::from_be_bytes([state[4], state[5]]);
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion { version });
+        }
+
+        let (content, checksum) = state.split_at(state.len() - CHECKSUM_SIZE);
+        if crc32(content).to_be_bytes() != checksum {
+            return Err(StateError::ChecksumMismatch);
+        }
+
+        Ok(StateReader {
+            bytes: &content[HEADER_SIZE..],
+        })
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], StateError> {
+        if self.bytes.len() < count {
+            return Err(StateError::Truncated);
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], StateError> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+
+    /// reads bytes that have to be exactly `N` long
+    pub fn array<const N: usize>(&mut self, field: &'static str) -> Result<[u8; N], StateError> {
+        self.bytes()?
+            .try_into()
+            .map_err(|_| StateError::Invalid { field })
+    }
+
+    /// fails unless everything has been read
+    pub fn finish(self) -> Result<(), StateError> {
+        match self.bytes.is_empty() {
+            true => Ok(()),
+            false => Err(StateError::Invalid { field: "length" }),
+        }
+    }
+}
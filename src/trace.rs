@@ -0,0 +1,264 @@
+use crate::inspector::Inspector;
+use crate::instruction::Instruction;
+use crate::Emulator;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::FromStr;
+
+/// first line of every trace, names the columns of the lines after it
+///
+/// lines starting with `#` are comments, everything after ` ; ` is the instruction for humans to read
+pub const TRACE_HEADER: &str =
+    "# cycle pc opcode v0 v1 v2 v3 v4 v5 v6 v7 v8 v9 va vb vc vd ve vf i sp dt st ; instruction";
+
+/// amount of whitespace separated fields in front of the instruction
+const FIELDS: usize = 23;
+
+/// the machine state right before an instruction ran, a line of a trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// instructions run before this one
+    pub cycle: u64,
+    pub pc: u16,
+    /// the first two bytes of the instruction
+    pub opcode: u16,
+    pub registers: [u8; 16],
+    pub i: u16,
+    /// depth of the call stack
+    pub sp: u8,
+    pub dt: u8,
+    pub st: u8,
+    pub instruction: String,
+}
+
+impl TraceEntry {
+    pub fn capture(cycle: u64, emulator: &Emulator, instruction: Instruction) -> TraceEntry {
+        let memory = emulator.memory();
+        let pc = memory.read_pc();
+        let long_address = match instruction {
+            Instruction::LdILong => memory.read_u16(pc as usize + 2).ok(),
+            _ => None,
+        };
+
+        TraceEntry {
+            cycle,
+            pc,
+            // the instruction was just decoded from here, so this can't fail
+            opcode: memory.read_u16(pc as usize).unwrap_or_default(),
+            registers: *memory.registers(),
+            i: memory.read_index_register(),
+            sp: memory.stack().len() as u8,
+            dt: emulator.clock().delay_timer(),
+            st: emulator.clock().sound_timer(),
+            instruction: instruction.to_octo(&|address| format!("{:#05x}", address), long_address),
+        }
+    }
+
+    /// names of the fields that differ
+    ///
+    /// the instruction text is ignored, other emulators spell instructions differently
+    pub fn differences(&self, other: &TraceEntry) -> Vec<String> {
+        let mut fields = vec![];
+        let mut compare = |name: &str, equal: bool| {
+            if !equal {
+                fields.push(name.to_string());
+            }
+        };
+
+        compare("cycle", self.cycle == other.cycle);
+        compare("pc", self.pc == other.pc);
+        compare("opcode", self.opcode == other.opcode);
+        for (x, (own, other)) in self.registers.iter().zip(&other.registers).enumerate() {
+            compare(&format!("v{:x}", x), own == other);
+        }
+        compare("i", self.i == other.i);
+        compare("sp", self.sp == other.sp);
+        compare("dt", self.dt == other.dt);
+        compare("st", self.st == other.st);
+
+        fields
+    }
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:04X} {:04X}", self.cycle, self.pc, self.opcode)?;
+        for register in self.registers {
+            write!(f, " {:02X}", register)?;
+        }
+        write!(
+            f,
+            " {:04X} {:02X} {:02X} {:02X} ; {}",
+            self.i, self.sp, self.dt, self.st, self.instruction
+        )
+    }
+}
+
+impl FromStr for TraceEntry {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (fields, instruction) = line.split_once(" ; ").unwrap_or((line, ""));
+        let fields = fields.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != FIELDS {
+            return Err(format!(
+                "expected {} fields but found {}",
+                FIELDS,
+                fields.len()
+            ));
+        }
+
+        let hex = |index: usize| {
+            u16::from_str_radix(fields[index], 16)
+                .map_err(|_| format!("{:?} is not a hexadecimal number", fields[index]))
+        };
+        let byte = |index: usize| {
+            hex(index).and_then(|value| {
+                u8::try_from(value).map_err(|_| format!("{:?} is not a byte", fields[index]))
+            })
+        };
+
+        let mut registers = [0; 16];
+        for (x, register) in registers.iter_mut().enumerate() {
+            *register = byte(3 + x)?;
+        }
+
+        Ok(TraceEntry {
+            cycle: fields[0]
+                .parse()
+                .map_err(|_| format!("{:?} is not a cycle", fields[0]))?,
+            pc: hex(1)?,
+            opcode: hex(2)?,
+            registers,
+            i: hex(19)?,
+            sp: byte(20)?,
+            dt: byte(21)?,
+            st: byte(22)?,
+            instruction: instruction.trim().to_string(),
+        })
+    }
+}
+
+/// parses a whole trace, skipping comments and blank lines
+pub fn parse_trace(trace: &str) -> Result<Vec<TraceEntry>, String> {
+    trace
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            line.parse()
+                .map_err(|err| format!("line {}: {}", index + 1, err))
+        })
+        .collect()
+}
+
+/// writes a line for every instruction the emulator runs
+pub struct Tracer<W: Write> {
+    output: W,
+    cycle: u64,
+    error: Option<std::io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(mut output: W) -> Tracer<W> {
+        let error = writeln!(output, "{}", TRACE_HEADER).err();
+        Tracer {
+            output,
+            cycle: 0,
+            error,
+        }
+    }
+
+    /// instructions traced so far
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// flushes the trace, fails with the first error writing it, tracing stops at that error
+    pub fn finish(mut self) -> std::io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+impl<W: Write> Inspector for Tracer<W> {
+    fn before_instruction(&mut self, emulator: &Emulator, instruction: Instruction) {
+        if self.error.is_none() {
+            let entry = TraceEntry::capture(self.cycle, emulator, instruction);
+            self.error = writeln!(self.output, "{}", entry).err();
+        }
+        self.cycle += 1;
+    }
+}
+
+/// the first instruction at which a run differs from its reference
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub expected: TraceEntry,
+    pub actual: TraceEntry,
+    /// the fields that differ
+    pub fields: Vec<String>,
+    /// the last instruction both agreed on
+    pub previous: Option<TraceEntry>,
+}
+
+/// compares every instruction the emulator runs against a reference trace
+pub struct TraceComparer {
+    reference: Vec<TraceEntry>,
+    cycle: u64,
+    previous: Option<TraceEntry>,
+    divergence: Option<Divergence>,
+}
+
+impl TraceComparer {
+    pub fn new(reference: Vec<TraceEntry>) -> TraceComparer {
+        TraceComparer {
+            reference,
+            cycle: 0,
+            previous: None,
+            divergence: None,
+        }
+    }
+
+    /// instructions compared so far
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// whether every instruction of the reference has been compared
+    pub fn is_finished(&self) -> bool {
+        self.cycle >= self.reference.len() as u64
+    }
+
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+}
+
+impl Inspector for TraceComparer {
+    fn before_instruction(&mut self, emulator: &Emulator, instruction: Instruction) {
+        if self.divergence.is_some() || self.is_finished() {
+            return;
+        }
+
+        let expected = &self.reference[self.cycle as usize];
+        let actual = TraceEntry::capture(self.cycle, emulator, instruction);
+        let fields = expected.differences(&actual);
+
+        if fields.is_empty() {
+            self.previous = Some(actual);
+        } else {
+            self.divergence = Some(Divergence {
+                expected: expected.clone(),
+                actual,
+                fields,
+                previous: self.previous.take(),
+            });
+        }
+        self.cycle += 1;
+    }
+}
@@ -0,0 +1,207 @@
+//! assembles small Octo programs and checks the bytes they turn into
+
+use chip_8::assembler::{assemble, AssemblerError};
+use chip_8::disassembler::disassemble;
+use chip_8::programs::Program;
+use chip_8::Platform;
+use strum::IntoEnumIterator;
+
+fn rom(source: &str) -> Vec<u8> {
+    assemble(source)
+        .unwrap_or_else(|err| panic!("{}\n{}", err, source))
+        .rom
+}
+
+fn error(source: &str) -> AssemblerError {
+    assemble(source).expect_err(source)
+}
+
+#[test]
+fn labels() {
+    let source = "
+        : main
+          jump end
+        : sub
+          return
+        : end
+          sub
+    ";
+    let assembly = assemble(source).unwrap();
+
+    assert_eq!(assembly.rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+    assert_eq!(assembly.labels["main"], 0x200);
+    assert_eq!(assembly.labels["sub"], 0x202);
+    assert_eq!(assembly.labels["end"], 0x204);
+}
+
+#[test]
+fn jump_to_main() {
+    // programs not starting with main jump there first
+    assert_eq!(
+        rom(": sub return : main sub"),
+        [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]
+    );
+}
+
+#[test]
+fn constants_and_aliases() {
+    let source = "
+        :const speed 5
+        :alias x v3
+        : main
+          x := speed
+          x += x
+          :const target main
+          jump target
+    ";
+    // the definitions come first, so the program jumps to main
+    assert_eq!(
+        rom(source),
+        [0x12, 0x02, 0x63, 0x05, 0x83, 0x34, 0x12, 0x02]
+    );
+}
+
+#[test]
+fn if_then() {
+    assert_eq!(
+        rom(": main if v0 == 1 then v1 := 2"),
+        [0x40, 0x01, 0x61, 0x02]
+    );
+    assert_eq!(
+        rom(": main if v0 != v2 then v1 := 2"),
+        [0x50, 0x20, 0x61, 0x02]
+    );
+    assert_eq!(rom(": main if v4 key then clear"), [0xE4, 0xA1, 0x00, 0xE0]);
+}
+
+#[test]
+fn if_begin_else_end() {
+    let source = "
+        : main
+          if v0 != 3 begin
+            v1 := 1
+          else
+            v1 := 2
+          end
+    ";
+    assert_eq!(
+        rom(source),
+        [0x40, 0x03, 0x12, 0x08, 0x61, 0x01, 0x12, 0x0A, 0x61, 0x02]
+    );
+}
+
+#[test]
+fn loop_while_again() {
+    let source = "
+        : main
+          loop
+            v0 += 1
+            while v0 != 5
+          again
+    ";
+    assert_eq!(
+        rom(source),
+        [0x70, 0x01, 0x40, 0x05, 0x12, 0x08, 0x12, 0x00]
+    );
+}
+
+#[test]
+fn bytes_and_org() {
+    let source = "
+        : main
+          jump data
+        :org 0x300
+        : data
+          :byte 1 :byte 0xFF :byte { 2 * 3 }
+    ";
+    let rom = rom(source);
+
+    assert_eq!(rom.len(), 0x103);
+    assert_eq!(rom[..2], [0x13, 0x00]);
+    assert!(rom[2..0x100].iter().all(|byte| *byte == 0));
+    assert_eq!(rom[0x100..], [0x01, 0xFF, 0x06]);
+}
+
+#[test]
+fn error_lines() {
+    let err = error(": main\n  v0 := 1\n\n  v1 := 300\n");
+    assert_eq!((err.line, err.column), (4, 9));
+
+    let err = error(": main\n  jump nowhere\n");
+    assert_eq!(err.line, 2);
+
+    let err = error(": main\n  end\n");
+    assert_eq!(err.line, 2);
+
+    let err = error(": main\n  loop\n  v0 += 1\n");
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn loop_out_of_reach() {
+    // a jump only reaches the first 4 KB
+    let err = error(": main\n  :org 0x1000\n  loop\n  again\n");
+    assert_eq!(err.line, 4);
+    assert_eq!(err.message, "loop starts out of reach of a jump");
+
+    let err = error(": main\n  if v0 == 1 begin\n  :org 0x1000\n  end\n");
+    assert_eq!(err.line, 4);
+}
+
+#[test]
+fn disassembly_round_trip() {
+    for program in Program::iter() {
+        let original = program.source();
+        let source = disassemble(&original, Platform::X0Chip).to_string();
+
+        let assembled = assemble(&source).unwrap_or_else(|err| panic!("{:?}: {}", program, err));
+        assert_eq!(assembled.rom, original, "{:?}", program);
+    }
+}
+
+#[test]
+fn shifts_out_of_range() {
+    assert_eq!(
+        rom(":calc big { 1 << 7 }\n: main\n  :byte big"),
+        [0x12, 0x02, 0x80]
+    );
+
+    let err = error(":calc big { 1 << 70 }\n: main\n");
+    assert_eq!((err.line, err.column), (1, 15));
+    assert_eq!(err.message, "`<<` by 70 is out of range");
+
+    let err = error(":calc small { 256 >> -1 }\n: main\n");
+    assert_eq!((err.line, err.column), (1, 19));
+}
+
+#[test]
+fn recursive_macros() {
+    let err = error(":macro m { m }\n: main\n  m\n");
+    assert_eq!((err.line, err.column), (1, 12));
+    assert_eq!(err.message, "macro `m` expands itself");
+
+    let err = error(":macro a { b }\n:macro b { a }\n: main\n  a\n");
+    assert_eq!(err.message, "macro `a` expands itself");
+
+    // a macro passed to itself is expanded by itself as well
+    let err = error(":macro twice x { x x }\n: main\n  twice twice clear\n");
+    assert_eq!(err.message, "macro `twice` expands itself");
+
+    // using a macro several times and from other macros is fine
+    let source =
+        ":macro twice x { x x }\n:macro two { twice clear }\n: main\n  two\n  twice clear\n";
+    assert_eq!(
+        rom(source),
+        [0x12, 0x02, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0xE0]
+    );
+}
+
+#[test]
+fn byte_expression_range() {
+    assert_eq!(rom(": main :byte { 255 } :byte { -128 }"), [0xFF, 0x80]);
+
+    let err = error(": main\n  :byte { 300 }\n");
+    assert_eq!((err.line, err.column), (2, 3));
+    assert_eq!(err.message, "300 does not fit between -128 and 255");
+    error(": main\n  :byte { -129 }\n");
+}
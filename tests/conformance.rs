@@ -0,0 +1,139 @@
+//! runs the bundled Timendus test roms headlessly and compares their final screens against `tests/golden`
+//!
+//! after an intended change to what a rom shows, run with `UPDATE_GOLDEN=1` to rewrite the golden screens
+
+use chip_8::headless::{run_headless, InputScript};
+use chip_8::programs::Program;
+use chip_8::{Emulator, Platform};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const PLATFORMS: [Platform; 3] = [Platform::Chip8, Platform::SuperChip, Platform::X0Chip];
+
+fn platform_name(platform: Platform) -> &'static str {
+    match platform {
+        Platform::Chip8 => "chip8",
+        Platform::SuperChip => "schip",
+        Platform::X0Chip => "xo",
+    }
+}
+
+/// runs `program` and compares the screen against the golden screen of `name` on the platform
+fn check(name: &str, program: Program, platform: Platform, frames: u64, script: &str) {
+    let script = script.parse::<InputScript>().unwrap();
+    let mut emulator = Emulator::new(program.source(), name.to_string(), platform).unwrap();
+    let run = run_headless(&mut emulator, frames, &script).unwrap();
+    assert!(!run.exited, "{} exited after {} frames", name, run.frames);
+
+    let file_name = format!("{}-{}.txt", name, platform_name(platform));
+    let golden_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(&file_name);
+    let actual = run.framebuffer.to_string();
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        fs::write(&golden_path, &actual).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(&golden_path)
+        .unwrap_or_else(|err| panic!("{}: {}", golden_path.display(), err));
+    if golden != actual {
+        let actual_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(&file_name);
+        fs::write(&actual_path, &actual).unwrap();
+        panic!(
+            "{} differs from {}, the screen was written to {}\n{}",
+            file_name,
+            golden_path.display(),
+            actual_path.display(),
+            actual
+        );
+    }
+}
+
+fn check_all_platforms(name: &str, program: Program, frames: u64, script: &str) {
+    for platform in PLATFORMS {
+        check(name, program, platform, frames, script);
+    }
+}
+
+#[test]
+fn chip8_logo() {
+    check_all_platforms("chip8-logo", Program::Chip8Logo, 60, "");
+}
+
+#[test]
+fn ibm_logo() {
+    check_all_platforms("ibm-logo", Program::IBM, 60, "");
+}
+
+#[test]
+fn corax() {
+    check_all_platforms("corax", Program::Corax, 300, "");
+}
+
+#[test]
+fn flags() {
+    check_all_platforms("flags", Program::Flags, 300, "");
+}
+
+#[test]
+fn quirks() {
+    // the menu picks the platform, super-chip asks for modern or legacy behaviour next
+    check(
+        "quirks",
+        Program::Quirks,
+        Platform::Chip8,
+        900,
+        "60 1\n62\n",
+    );
+    check(
+        "quirks",
+        Program::Quirks,
+        Platform::SuperChip,
+        900,
+        "60 2\n62\n90 1\n92\n",
+    );
+    check(
+        "quirks",
+        Program::Quirks,
+        Platform::X0Chip,
+        900,
+        "60 3\n62\n",
+    );
+}
+
+#[test]
+fn keypad_down() {
+    // EX9E highlights held keys, 5 and A are still held at the end
+    check_all_platforms(
+        "keypad-down",
+        Program::Keypad,
+        150,
+        "60 1\n62\n90 1 F\n100\n120 5 A\n",
+    );
+}
+
+#[test]
+fn keypad_up() {
+    // EXA1 highlights held keys as well
+    check_all_platforms(
+        "keypad-up",
+        Program::Keypad,
+        150,
+        "60 2\n62\n90 1 F\n100\n120 5 A\n",
+    );
+}
+
+#[test]
+fn keypad_getkey() {
+    // FX0A waits for 5 to be released before it returns it
+    check_all_platforms(
+        "keypad-getkey",
+        Program::Keypad,
+        150,
+        "60 3\n62\n90 5\n100\n",
+    );
+}
@@ -0,0 +1,163 @@
+//! records how a small program uses memory and reads and writes coverage maps
+
+use chip_8::assembler::assemble;
+use chip_8::coverage::{Coverage, Usage, MAP_HEADER};
+use chip_8::disassembler::{disassemble, disassemble_with_coverage, LineKind};
+use chip_8::io::NoInput;
+use chip_8::{Emulator, Platform};
+
+/// draws a sprite, saves two registers, loads a table and jumps over the table
+const SOURCE: &str = "
+    : main
+      i := sprite
+      sprite v0 v0 2
+      i := buffer
+      save v1
+      i := table
+      load v1
+      v0 := 1
+      if v0 == 1 then jump done
+    : table
+      :byte 0x60 :byte 0x05
+    : done
+      exit
+    : sprite
+      :byte 0xFF :byte 0x81
+    : buffer
+      :byte 0 :byte 0
+";
+
+fn run() -> (Vec<u8>, Coverage) {
+    let rom = assemble(SOURCE).unwrap().rom;
+    let mut emulator =
+        Emulator::new(rom.clone(), "coverage".to_string(), Platform::SuperChip).unwrap();
+    let mut coverage = Coverage::new();
+    while !emulator.has_exited() {
+        emulator
+            .run_frame_inspected(&NoInput, &mut coverage)
+            .unwrap();
+    }
+    (rom, coverage)
+}
+
+fn write_map(coverage: &Coverage) -> String {
+    let mut map = vec![];
+    coverage.write_map(&mut map).unwrap();
+    String::from_utf8(map).unwrap()
+}
+
+#[test]
+fn usage() {
+    let (_, coverage) = run();
+    let labels = assemble(SOURCE).unwrap().labels;
+    let usage = |label: &str, offset: usize| coverage.usage(labels[label] as usize + offset);
+
+    assert_eq!(coverage.usage(0x200), Usage::EXECUTED | Usage::INSTRUCTION);
+    assert_eq!(coverage.usage(0x201), Usage::EXECUTED);
+
+    // DXYN draws, FX55 writes and FX65 reads exactly as many bytes as they use
+    for offset in 0..2 {
+        assert_eq!(usage("sprite", offset), Usage::SPRITE);
+        assert_eq!(usage("buffer", offset), Usage::WRITTEN);
+        assert_eq!(usage("table", offset), Usage::READ);
+    }
+    assert_eq!(usage("buffer", 2), Usage::NONE);
+
+    assert!(coverage.data().contains(&labels["table"]));
+    assert!(!coverage.instructions().contains(&labels["table"]));
+    assert!(coverage.instructions().contains(&labels["done"]));
+}
+
+#[test]
+fn map_round_trip() {
+    let (_, coverage) = run();
+    let map = write_map(&coverage);
+    assert!(map.starts_with(MAP_HEADER));
+
+    let parsed = map.parse::<Coverage>().unwrap();
+    assert_eq!(write_map(&parsed), map);
+    for address in 0..0x300 {
+        assert_eq!(parsed.usage(address), coverage.usage(address));
+    }
+}
+
+#[test]
+fn map_errors() {
+    let error = |map: &str| map.parse::<Coverage>().unwrap_err();
+
+    assert_eq!(
+        error("# header\n0200 01"),
+        "line 2: expected an address followed by :"
+    );
+    assert_eq!(error("xyz: 01"), "line 1: \"xyz\" is not an address");
+    assert_eq!(
+        error("0200: 01 1FF"),
+        "line 1: \"1FF\" is not a hexadecimal byte"
+    );
+    assert_eq!(
+        error("0200: 01\nFFFFFFFFFFFFFFFF: 01 01"),
+        "line 2: FFFFFFFFFFFFFFFF is past the end of memory"
+    );
+    assert_eq!(
+        error("1000000000: 01"),
+        "line 1: 1000000000 is past the end of memory"
+    );
+    assert_eq!(
+        error("FFF0: 01 01 01 01 01 01 01 01 01 01 01 01 01 01 01 01 01"),
+        "line 1: 10000 is past the end of memory"
+    );
+}
+
+#[test]
+fn marks_past_the_end_are_ignored() {
+    let mut coverage = Coverage::new();
+    coverage.mark(usize::MAX, 2, Usage::READ);
+    coverage.mark(0xFFFF, 2, Usage::READ);
+    coverage.mark(0xFFFF, 1, Usage::WRITTEN);
+
+    assert_eq!(coverage.usage(0xFFFF), Usage::WRITTEN);
+    assert_eq!(coverage.data().into_iter().collect::<Vec<_>>(), [0xFFFF]);
+}
+
+#[test]
+fn merge() {
+    let mut first = Coverage::new();
+    first.mark(0x200, 2, Usage::EXECUTED);
+    first.mark(0x200, 1, Usage::INSTRUCTION);
+    first.mark(0x300, 1, Usage::READ);
+
+    let mut second = Coverage::new();
+    second.mark(0x300, 1, Usage::WRITTEN);
+    second.mark(0x400, 4, Usage::SPRITE);
+
+    first.merge(&second);
+    assert_eq!(first.usage(0x200), Usage::EXECUTED | Usage::INSTRUCTION);
+    assert_eq!(first.usage(0x201), Usage::EXECUTED);
+    assert_eq!(first.usage(0x300), Usage::READ | Usage::WRITTEN);
+    assert_eq!(first.usage(0x403), Usage::SPRITE);
+    assert_eq!(first.usage(0x404), Usage::NONE);
+    assert_eq!(
+        first.instructions().into_iter().collect::<Vec<_>>(),
+        [0x200]
+    );
+}
+
+#[test]
+fn data_stays_out_of_the_code() {
+    let (rom, coverage) = run();
+    let table = assemble(SOURCE).unwrap().labels["table"];
+
+    // the skip makes the table look like code, but the run only ever read it
+    let plain = disassemble(&rom, Platform::SuperChip);
+    assert!(matches!(
+        plain.line_at(table).unwrap().kind,
+        LineKind::Code { .. }
+    ));
+
+    let guided = disassemble_with_coverage(&rom, Platform::SuperChip, &coverage);
+    assert_eq!(guided.line_at(table).unwrap().kind, LineKind::Data);
+    assert!(matches!(
+        guided.line_at(table + 2).unwrap().kind,
+        LineKind::Code { .. }
+    ));
+}
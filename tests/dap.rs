@@ -0,0 +1,293 @@
+#![cfg(feature = "cli")]
+
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// talks to `chip-8 dap` over stdio like an editor would
+struct Client {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    seq: u64,
+    /// events that arrived while waiting for something else
+    events: Vec<Value>,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_chip-8"))
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        Client {
+            input: child.stdin.take().unwrap(),
+            output: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            seq: 0,
+            events: vec![],
+        }
+    }
+
+    fn read(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            assert!(
+                self.output.read_line(&mut header).unwrap() > 0,
+                "server closed the connection"
+            );
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.output.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// writes `body` as a message without looking at it
+    fn send(&mut self, body: &str) {
+        write!(self.input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.input.flush().unwrap();
+    }
+
+    /// sends a request and waits for its response
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let body = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        self.send(&body);
+
+        loop {
+            let message = self.read();
+            if message["type"] == "response" && message["request_seq"] == self.seq {
+                assert_eq!(message["command"], command);
+                return message;
+            }
+            self.events.push(message);
+        }
+    }
+
+    /// sends a request that has to succeed and returns the response body
+    fn ok(&mut self, command: &str, arguments: Value) -> Value {
+        let response = self.request(command, arguments);
+        assert_eq!(
+            response["success"], true,
+            "{} failed: {}",
+            command, response
+        );
+        response["body"].clone()
+    }
+
+    fn event(&mut self, event: &str) -> Value {
+        if let Some(index) = self
+            .events
+            .iter()
+            .position(|message| message["event"] == event)
+        {
+            return self.events.remove(index)["body"].clone();
+        }
+
+        loop {
+            let message = self.read();
+            if message["event"] == event {
+                return message["body"].clone();
+            }
+            self.events.push(message);
+        }
+    }
+
+    fn register(&mut self, name: &str) -> Value {
+        let variables = self.ok("variables", json!({ "variablesReference": 1 }));
+        variables["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|variable| variable["name"] == name)
+            .unwrap()["value"]
+            .clone()
+    }
+
+    fn top_frame(&mut self) -> Value {
+        self.ok("stackTrace", json!({ "threadId": 1 }))["stackFrames"][0].clone()
+    }
+
+    fn disconnect(mut self) {
+        self.ok("disconnect", json!({}));
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn write_source(name: &str, source: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, source).unwrap();
+    path
+}
+
+const PROGRAM: &str = "\
+: main
+  v3 := 0
+  loop
+    v3 += 1
+    sub
+  again
+
+: sub
+  v1 := v3
+  inner
+  ;
+
+: inner
+  v2 := 7
+  ;
+";
+
+#[test]
+fn debugs_octo_source() {
+    let path = write_source("dap.8o", PROGRAM);
+    let path = path.to_str().unwrap();
+    let mut client = Client::start();
+
+    let capabilities = client.ok("initialize", json!({ "adapterID": "chip-8" }));
+    assert_eq!(capabilities["supportsReadMemoryRequest"], true);
+
+    client.ok("launch", json!({ "program": path, "platform": "schip" }));
+    client.event("initialized");
+
+    // a blank line moves to the next instruction, `: sub` has none of its own
+    let breakpoints = client.ok(
+        "setBreakpoints",
+        json!({
+            "source": { "path": path },
+            "breakpoints": [{ "line": 7 }, { "line": 9, "condition": "V3 == 0x03" }, { "line": 40 }],
+        }),
+    );
+    let breakpoints = &breakpoints["breakpoints"];
+    assert_eq!(breakpoints[0]["line"], 9);
+    assert_eq!(breakpoints[1]["line"], 9);
+    assert_eq!(breakpoints[1]["instructionReference"], "0x0208");
+    assert_eq!(breakpoints[2]["verified"], false);
+
+    // both breakpoints are on 0x208, the later one with its condition wins
+    client.ok("configurationDone", json!({}));
+    assert_eq!(client.event("stopped")["reason"], "breakpoint");
+    assert_eq!(client.register("V3"), "0x03");
+    assert_eq!(client.register("SP"), "1");
+
+    let trace = client.ok("stackTrace", json!({ "threadId": 1 }));
+    let frames = &trace["stackFrames"];
+    assert_eq!(frames[0]["name"], "sub");
+    assert_eq!(frames[0]["line"], 9);
+    assert_eq!(frames[1]["name"], "main");
+    assert_eq!(frames[1]["line"], 5);
+
+    client.ok("stepIn", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "step");
+    assert_eq!(client.top_frame()["line"], 10);
+
+    // steps over the call to `inner`
+    client.ok("next", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "step");
+    assert_eq!(client.top_frame()["line"], 11);
+    assert_eq!(client.register("V2"), "0x07");
+
+    client.ok("stepOut", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "step");
+    assert_eq!(client.top_frame()["line"], 6);
+
+    let memory = client.ok(
+        "readMemory",
+        json!({ "memoryReference": "0x200", "count": 4 }),
+    );
+    assert_eq!(memory["data"], "YwBzAQ==");
+    assert_eq!(memory["unreadableBytes"], 0);
+
+    client.ok(
+        "setVariable",
+        json!({ "variablesReference": 1, "name": "V3", "value": "0x10" }),
+    );
+    assert_eq!(client.register("V3"), "0x10");
+
+    client.ok(
+        "setBreakpoints",
+        json!({ "source": { "path": path }, "breakpoints": [] }),
+    );
+    let breakpoints = client.ok(
+        "setInstructionBreakpoints",
+        json!({ "breakpoints": [{ "instructionReference": "0x020e" }] }),
+    );
+    assert_eq!(breakpoints["breakpoints"][0]["verified"], true);
+    client.ok("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "instruction breakpoint");
+    assert_eq!(client.register("V3"), "0x11");
+
+    let disassembly = client.ok(
+        "disassemble",
+        json!({ "memoryReference": "0x208", "instructionOffset": -1, "instructionCount": 3 }),
+    );
+    let instructions = &disassembly["instructions"];
+    assert_eq!(instructions[0]["address"], "0x0206");
+    assert_eq!(instructions[1]["instruction"], "v1 := v3");
+    assert_eq!(instructions[1]["line"], 9);
+    assert_eq!(instructions[2]["address"], "0x020a");
+
+    client.ok("setInstructionBreakpoints", json!({ "breakpoints": [] }));
+    client.ok("continue", json!({ "threadId": 1 }));
+    client.ok("pause", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "pause");
+
+    client.disconnect();
+}
+
+#[test]
+fn reports_entry_and_exit() {
+    let path = write_source("exit.8o", ": main\n  v0 := 1\n  exit\n");
+    let mut client = Client::start();
+    client.ok("initialize", json!({}));
+
+    let missing = client.request("launch", json!({ "program": "missing.ch8" }));
+    assert_eq!(missing["success"], false);
+
+    client.ok("launch", json!({ "program": path, "stopOnEntry": true }));
+    client.ok("configurationDone", json!({}));
+    assert_eq!(client.event("stopped")["reason"], "entry");
+    assert_eq!(client.register("PC"), "0x0200");
+
+    client.ok("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("exited")["exitCode"], 0);
+    client.event("terminated");
+
+    client.disconnect();
+}
+
+#[test]
+fn skips_malformed_messages() {
+    let path = write_source("malformed.8o", ": main\n  loop again\n");
+    let mut client = Client::start();
+    client.ok("initialize", json!({}));
+
+    // the session goes on with the next message
+    client.send("{\"type\": ");
+    client.send("not json");
+    client.ok("launch", json!({ "program": path, "stopOnEntry": true }));
+    client.ok("configurationDone", json!({}));
+    assert_eq!(client.event("stopped")["reason"], "entry");
+
+    client.disconnect();
+}
@@ -0,0 +1,227 @@
+//! runs small assembled programs under the debugger and checks where it stops
+
+use chip_8::assembler::{assemble, Assembly};
+use chip_8::debugger::{Debugger, Register, StopReason, Watch, STEP_FRAME_LIMIT};
+use chip_8::io::NoInput;
+use chip_8::memory::Access;
+use chip_8::{Emulator, Platform};
+
+/// counts V0 up and stores it through a subroutine, V1 follows V0
+const COUNTER: &str = "
+    : main
+      v0 := 0
+      loop
+        v0 += 1
+        store
+      again
+    : store
+      i := data
+      save v0
+      v1 := v0
+      return
+    : data
+      0
+";
+
+fn debugger(source: &str) -> (Debugger, Assembly) {
+    let assembly = assemble(source).unwrap();
+    let emulator = Emulator::new(
+        assembly.rom.clone(),
+        "debugger".to_string(),
+        Platform::Chip8,
+    )
+    .unwrap();
+    (Debugger::new(emulator), assembly)
+}
+
+fn pc(debugger: &Debugger) -> u16 {
+    debugger.emulator().memory().read_pc()
+}
+
+fn read(debugger: &Debugger, register: Register) -> u16 {
+    register.read(debugger.emulator())
+}
+
+#[test]
+fn breakpoint() {
+    let (mut debugger, assembly) = debugger(COUNTER);
+    let store = assembly.labels["store"];
+    debugger.add_breakpoint(store, None);
+
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 100),
+        StopReason::Breakpoint { address: store }
+    );
+    assert_eq!(read(&debugger, Register::V(0)), 1);
+
+    // resuming doesn't stop at the same breakpoint again right away
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 100),
+        StopReason::Breakpoint { address: store }
+    );
+    assert_eq!(read(&debugger, Register::V(0)), 2);
+
+    assert!(debugger.remove_breakpoint(store));
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 3),
+        StopReason::Frame { frame: 3 }
+    );
+}
+
+#[test]
+fn conditional_breakpoint() {
+    let (mut debugger, assembly) = debugger(COUNTER);
+    let store = assembly.labels["store"];
+    debugger.add_breakpoint(store, Some("V0 >= 5".parse().unwrap()));
+
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 100),
+        StopReason::Breakpoint { address: store }
+    );
+    assert_eq!(read(&debugger, Register::V(0)), 5);
+
+    // a breakpoint replaces the one at the same address
+    debugger.add_breakpoint(store, Some("V0 == V1".parse().unwrap()));
+    debugger.add_breakpoint(store + 4, Some("V1 >= 0x08".parse().unwrap()));
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 100),
+        StopReason::Breakpoint { address: store + 4 }
+    );
+    assert_eq!(read(&debugger, Register::V(0)), 9);
+    assert_eq!(read(&debugger, Register::V(1)), 8);
+}
+
+#[test]
+fn watchpoint() {
+    let (mut debugger, assembly) = debugger(COUNTER);
+    let data = assembly.labels["data"];
+    debugger.add_watchpoint(data, Watch::Read);
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 3),
+        StopReason::Frame { frame: 3 }
+    );
+
+    debugger.add_watchpoint(data, Watch::Write);
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 100),
+        StopReason::Watchpoint {
+            address: data,
+            access: Access::Write
+        }
+    );
+    // the debugger stops after the instruction ran
+    assert_eq!(pc(&debugger), assembly.labels["store"] + 4);
+    let value = debugger.emulator().memory().read_u8(data as usize).unwrap();
+    assert_eq!(value as u16, read(&debugger, Register::V(0)));
+}
+
+#[test]
+fn register_watch() {
+    let (mut debugger, _) = debugger(COUNTER);
+    debugger.watch_register(Register::V(1));
+
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 100),
+        StopReason::RegisterChanged {
+            register: Register::V(1),
+            old: 0,
+            new: 1
+        }
+    );
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 100),
+        StopReason::RegisterChanged {
+            register: Register::V(1),
+            old: 1,
+            new: 2
+        }
+    );
+}
+
+#[test]
+fn step_over() {
+    let (mut debugger, assembly) = debugger(COUNTER);
+    let call = assembly.labels["main"] + 4;
+
+    assert_eq!(debugger.step(&NoInput), StopReason::Step);
+    assert_eq!(debugger.step(&NoInput), StopReason::Step);
+    assert_eq!(pc(&debugger), call);
+
+    assert_eq!(debugger.step_over(&NoInput), StopReason::Step);
+    assert_eq!(pc(&debugger), call + 2);
+    assert_eq!(read(&debugger, Register::V(1)), 1);
+    assert_eq!(read(&debugger, Register::Sp), 0);
+
+    // anything but a call is a single step
+    assert_eq!(debugger.step_over(&NoInput), StopReason::Step);
+    assert_eq!(pc(&debugger), assembly.labels["main"] + 2);
+}
+
+#[test]
+fn step_over_stops_at_breakpoints() {
+    let (mut debugger, assembly) = debugger(COUNTER);
+    let store = assembly.labels["store"];
+    debugger.step(&NoInput);
+    debugger.step(&NoInput);
+    debugger.add_breakpoint(store + 2, None);
+
+    assert_eq!(
+        debugger.step_over(&NoInput),
+        StopReason::Breakpoint { address: store + 2 }
+    );
+}
+
+#[test]
+fn step_out() {
+    let (mut debugger, assembly) = debugger(COUNTER);
+    let call = assembly.labels["main"] + 4;
+
+    // outside of subroutines it is a single step
+    assert_eq!(debugger.step_out(&NoInput), StopReason::Step);
+    assert_eq!(pc(&debugger), assembly.labels["main"] + 2);
+
+    debugger.step(&NoInput);
+    debugger.step(&NoInput);
+    debugger.step(&NoInput);
+    assert_eq!(pc(&debugger), assembly.labels["store"] + 2);
+    assert_eq!(read(&debugger, Register::Sp), 1);
+
+    assert_eq!(debugger.step_out(&NoInput), StopReason::Step);
+    assert_eq!(pc(&debugger), call + 2);
+    assert_eq!(read(&debugger, Register::Sp), 0);
+}
+
+#[test]
+fn step_over_endless_subroutine() {
+    let (mut debugger, _) = debugger(": main forever : forever loop again");
+
+    assert_eq!(
+        debugger.step_over(&NoInput),
+        StopReason::Frame {
+            frame: STEP_FRAME_LIMIT
+        }
+    );
+    assert_eq!(
+        debugger.step_out(&NoInput),
+        StopReason::Frame {
+            frame: 2 * STEP_FRAME_LIMIT
+        }
+    );
+}
+
+#[test]
+fn run_to_frame() {
+    let (mut debugger, _) = debugger(COUNTER);
+
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 5),
+        StopReason::Frame { frame: 5 }
+    );
+    assert_eq!(debugger.frame(), 5);
+
+    assert_eq!(debugger.run_frame(&NoInput), StopReason::Frame { frame: 6 });
+    assert_eq!(
+        debugger.run_to_frame(&NoInput, 2),
+        StopReason::Frame { frame: 6 }
+    );
+}
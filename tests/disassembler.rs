@@ -0,0 +1,37 @@
+//! checks how the disassembler splits roms into code and data
+
+use chip_8::disassembler::{disassemble, disassemble_with_hints, LineKind, PROGRAM_START};
+use chip_8::Platform;
+use std::collections::BTreeSet;
+
+/// an XO-CHIP rom filling the memory up to `0xFFFF` with `i := long 0xFFFE` in its last four bytes
+fn full_memory_rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x10000 - PROGRAM_START as usize];
+    let end = rom.len();
+    rom[end - 4..].copy_from_slice(&[0xF0, 0x00, 0xFF, 0xFE]);
+    rom
+}
+
+#[test]
+fn data_up_to_the_end_of_memory() {
+    let disassembly = disassemble(&full_memory_rom(), Platform::X0Chip);
+
+    let line = disassembly.line_at(0xFFFF).unwrap();
+    assert_eq!(line.kind, LineKind::Data);
+    assert_eq!(line.address as usize + line.bytes.len(), 0x10000);
+}
+
+#[test]
+fn code_up_to_the_end_of_memory() {
+    let rom = full_memory_rom();
+
+    // the second hint points into the middle of the first instruction
+    for hints in [vec![0xFFFC], vec![0xFFFC, 0xFFFE]] {
+        let hints = hints.into_iter().collect::<BTreeSet<_>>();
+        let disassembly = disassemble_with_hints(&rom, Platform::X0Chip, &hints);
+
+        let line = disassembly.line_at(0xFFFF).unwrap();
+        assert_eq!(line.address, 0xFFFC);
+        assert!(matches!(line.kind, LineKind::Code { .. }));
+    }
+}
@@ -0,0 +1,194 @@
+//! checks the escape sequences the terminal frontend draws the display with
+
+#![cfg(feature = "cli")]
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chip_8::framebuffer::Framebuffer;
+use chip_8::io::graphics::GraphicsProtocol;
+use chip_8::palette::Palette;
+use std::collections::HashMap;
+
+/// the color registers of the default palette
+const SIXEL_COLORS: &str = "#0;2;60;40;0#1;2;100;80;0#2;2;100;40;0#3;2;40;13;0";
+
+fn framebuffer(width: usize, height: usize, pixels: &[u8]) -> Framebuffer {
+    Framebuffer {
+        width,
+        height,
+        pixels: pixels.to_vec(),
+    }
+}
+
+fn sixel(framebuffer: &Framebuffer, scale: usize) -> String {
+    let bytes = GraphicsProtocol::Sixel
+        .encode(framebuffer, scale, &Palette::default())
+        .unwrap();
+    String::from_utf8(bytes).unwrap()
+}
+
+#[test]
+fn sixel_colors() {
+    // every color paints the pixels it covers in a column as bits from the top row up
+    let framebuffer = framebuffer(4, 2, &[0, 1, 1, 0, 2, 3, 0, 1]);
+    assert_eq!(
+        sixel(&framebuffer, 1),
+        format!(
+            "\x1bPq\"1;1;4;2{}#0@?A@$#1?@@A$#2A$#3?A\x1b\\",
+            SIXEL_COLORS
+        )
+    );
+}
+
+#[test]
+fn sixel_scaled_runs() {
+    let framebuffer = framebuffer(4, 1, &[1, 1, 0, 0]);
+    assert_eq!(
+        sixel(&framebuffer, 2),
+        format!("\x1bPq\"1;1;8;2{}#0!4?!4B$#1!4B\x1b\\", SIXEL_COLORS)
+    );
+}
+
+#[test]
+fn sixel_bands() {
+    // seven rows take two bands of six
+    let framebuffer = framebuffer(1, 7, &[1; 7]);
+    assert_eq!(
+        sixel(&framebuffer, 1),
+        format!("\x1bPq\"1;1;1;7{}#1~-#1@\x1b\\", SIXEL_COLORS)
+    );
+}
+
+#[test]
+fn sixel_palette() {
+    let palette = "#ff8000,#000080".parse::<Palette>().unwrap();
+    let bytes = GraphicsProtocol::Sixel
+        .encode(&framebuffer(1, 1, &[1]), 1, &palette)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        "\x1bPq\"1;1;1;1#0;2;100;50;0#1;2;0;0;50#2;2;100;40;0#3;2;40;13;0#1@\x1b\\"
+    );
+}
+
+#[test]
+fn kitty_single_chunk() {
+    let framebuffer = framebuffer(4, 2, &[0, 1, 1, 0, 2, 3, 0, 1]);
+    let palette = Palette::default();
+    let png = STANDARD.encode(framebuffer.to_png(2, &palette).unwrap());
+
+    assert_eq!(
+        GraphicsProtocol::Kitty
+            .encode(&framebuffer, 2, &palette)
+            .unwrap(),
+        format!("\x1b_Ga=T,f=100,i=1,p=1,q=2,C=1,m=0;{}\x1b\\", png).into_bytes()
+    );
+}
+
+#[test]
+fn kitty_chunks() {
+    // noise doesn't compress, so the image takes several chunks
+    let mut state = 0x2545f491u32;
+    let pixels = (0..256 * 128)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 24) as u8 & 0b11
+        })
+        .collect::<Vec<_>>();
+    let framebuffer = framebuffer(256, 128, &pixels);
+    let palette = Palette::default();
+
+    let output = GraphicsProtocol::Kitty
+        .encode(&framebuffer, 4, &palette)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let sequences = output
+        .strip_suffix("\x1b\\")
+        .unwrap()
+        .split("\x1b\\")
+        .collect::<Vec<_>>();
+    assert!(sequences.len() > 2);
+
+    let mut payload = String::new();
+    for (index, sequence) in sequences.iter().enumerate() {
+        let (keys, chunk) = sequence
+            .strip_prefix("\x1b_G")
+            .unwrap()
+            .split_once(';')
+            .unwrap();
+        let expected = match index {
+            0 => "a=T,f=100,i=1,p=1,q=2,C=1,m=1",
+            _ if index == sequences.len() - 1 => "q=2,m=0",
+            _ => "q=2,m=1",
+        };
+        assert_eq!(keys, expected);
+        assert!(chunk.len() <= 4096);
+        if index < sequences.len() - 1 {
+            assert_eq!(chunk.len(), 4096);
+        }
+        payload += chunk;
+    }
+
+    assert_eq!(
+        STANDARD.decode(payload).unwrap(),
+        framebuffer.to_png(4, &palette).unwrap()
+    );
+}
+
+#[test]
+fn kitty_too_large() {
+    let framebuffer = framebuffer(128, 64, &[0; 128 * 64]);
+    let palette = Palette::default();
+
+    // the limit is checked before the scaled image is allocated
+    for scale in [1000, usize::MAX / 64] {
+        let error = format!(
+            "a 128x64 display scaled by {} is too large for a PNG",
+            scale
+        );
+        assert_eq!(framebuffer.to_png(scale, &palette), Err(error.clone()));
+        assert_eq!(
+            GraphicsProtocol::Kitty.encode(&framebuffer, scale, &palette),
+            Err(error)
+        );
+    }
+    assert!(framebuffer.to_png(8, &palette).is_ok());
+}
+
+#[test]
+fn kitty_clear() {
+    assert_eq!(
+        GraphicsProtocol::Kitty.clear(),
+        b"\x1b_Ga=d,d=I,i=1,q=2\x1b\\"
+    );
+    assert!(GraphicsProtocol::Sixel.clear().is_empty());
+}
+
+fn detect(variables: &[(&str, &str)]) -> Option<GraphicsProtocol> {
+    let variables = variables.iter().copied().collect::<HashMap<_, _>>();
+    GraphicsProtocol::detect(|name| variables.get(name).map(|value| value.to_string()))
+}
+
+#[test]
+fn detection() {
+    assert_eq!(
+        detect(&[("TERM", "xterm-kitty"), ("KITTY_WINDOW_ID", "1")]),
+        Some(GraphicsProtocol::Kitty)
+    );
+    assert_eq!(
+        detect(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "WezTerm")]),
+        Some(GraphicsProtocol::Kitty)
+    );
+    assert_eq!(detect(&[("TERM", "foot")]), Some(GraphicsProtocol::Sixel));
+    assert_eq!(
+        detect(&[("TERM", "xterm-sixel")]),
+        Some(GraphicsProtocol::Sixel)
+    );
+    assert_eq!(detect(&[("TERM", "xterm-256color")]), None);
+    assert_eq!(
+        detect(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")]),
+        None
+    );
+}
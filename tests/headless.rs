@@ -0,0 +1,138 @@
+//! runs programs without a terminal and checks the input scripts and images they produce
+
+use chip_8::assembler::assemble;
+use chip_8::framebuffer::Framebuffer;
+use chip_8::headless::{run_headless, InputScript, ScriptedInput};
+use chip_8::io::IO;
+use chip_8::{Emulator, Platform};
+
+/// exits in the first frame key 5 is held
+const WAIT_FOR_KEY: &str = "
+    : main
+      v0 := 5
+    : wait
+      if v0 -key then jump wait
+      exit
+";
+
+#[test]
+fn parse_script() {
+    let script = "# press 5 and A\n30 5 a # both\n\n32\n40 F"
+        .parse::<InputScript>()
+        .unwrap();
+    assert_eq!(
+        script,
+        InputScript::new()
+            .with_keys(30, &[0x5, 0xA])
+            .with_keys(32, &[])
+            .with_keys(40, &[0xF])
+    );
+
+    assert!(script.keys_at(29).is_empty());
+    assert_eq!(script.keys_at(30), [0x5, 0xA]);
+    assert_eq!(script.keys_at(31), [0x5, 0xA]);
+    assert!(script.keys_at(32).is_empty());
+    assert_eq!(script.keys_at(1000), [0xF]);
+}
+
+#[test]
+fn script_errors() {
+    let error = |script: &str| script.parse::<InputScript>().unwrap_err();
+
+    assert_eq!(error("x 5"), "line 1: \"x\" is not a frame");
+    assert_eq!(error("# start\n-1 5"), "line 2: \"-1\" is not a frame");
+    assert_eq!(
+        error("10 5\n10 6"),
+        "line 2: frame 10 is not after the line before"
+    );
+    assert_eq!(
+        error("10 5\n5 6"),
+        "line 2: frame 5 is not after the line before"
+    );
+    assert_eq!(error("10 10"), "line 1: \"10\" is not a key from 0 to F");
+    assert_eq!(error("10 G"), "line 1: \"G\" is not a key from 0 to F");
+}
+
+#[test]
+fn scripted_input() {
+    let script = InputScript::new()
+        .with_keys(2, &[0x5])
+        .with_keys(3, &[0x5, 0x6])
+        .with_keys(4, &[]);
+    let mut input = ScriptedInput::new(&script);
+
+    let frames = (0..5)
+        .map(|frame| {
+            input.start_frame(frame);
+            (input.is_code_pressed(0x5), input.get_just_pressed())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        frames,
+        [
+            (false, vec![]),
+            (false, vec![]),
+            (true, vec![0x5]),
+            (true, vec![0x6]),
+            (false, vec![]),
+        ]
+    );
+}
+
+#[test]
+fn key_press_in_the_right_frame() {
+    let rom = assemble(WAIT_FOR_KEY).unwrap().rom;
+    let script = "30 5".parse::<InputScript>().unwrap();
+
+    let mut emulator = Emulator::new(rom, "wait".to_string(), Platform::SuperChip).unwrap();
+    let run = run_headless(&mut emulator, 100, &script).unwrap();
+    assert!(run.exited);
+    assert_eq!(run.frames, 31);
+}
+
+#[test]
+fn runs_every_frame_without_a_key() {
+    let rom = assemble(WAIT_FOR_KEY).unwrap().rom;
+
+    let mut emulator = Emulator::new(rom, "wait".to_string(), Platform::SuperChip).unwrap();
+    let run = run_headless(&mut emulator, 100, &InputScript::new()).unwrap();
+    assert!(!run.exited);
+    assert_eq!(run.frames, 100);
+    assert_eq!((run.framebuffer.width, run.framebuffer.height), (128, 64));
+    assert!(run.framebuffer.pixels.iter().all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn pbm() {
+    // a 10 pixel wide row takes two bytes, the bits past the width stay empty
+    let mut pixels = vec![0; 20];
+    pixels[0] = 1;
+    pixels[7] = 2;
+    pixels[8] = 3;
+    pixels[19] = 1;
+    let framebuffer = Framebuffer {
+        width: 10,
+        height: 2,
+        pixels,
+    };
+
+    assert_eq!(framebuffer.to_pbm(), b"P4\n10 2\n\x81\x80\x00\x40".to_vec());
+}
+
+#[test]
+fn hash() {
+    let framebuffer = |width: usize, height: usize, pixels: Vec<u8>| Framebuffer {
+        width,
+        height,
+        pixels,
+    };
+
+    // the hash must not change between versions, checks against baselines depend on it
+    let empty = framebuffer(64, 32, vec![0; 64 * 32]);
+    assert_eq!(empty.hash(), 0x23dd706edfd9cfe5);
+
+    let mut pixels = vec![0; 64 * 32];
+    pixels[100] = 1;
+    assert_ne!(framebuffer(64, 32, pixels).hash(), empty.hash());
+    assert_ne!(framebuffer(32, 64, vec![0; 64 * 32]).hash(), empty.hash());
+}
@@ -0,0 +1,74 @@
+//! checks the opcode tables of every platform
+
+use chip_8::instruction::Instruction;
+use chip_8::Platform;
+use strum::IntoEnumIterator;
+
+#[test]
+fn encode_reverses_decode() {
+    for platform in Platform::iter() {
+        for opcode in 0..=u16::MAX {
+            if let Some(instruction) = Instruction::decode(opcode, platform) {
+                assert_eq!(
+                    instruction.encode(),
+                    opcode,
+                    "{:04X} on {:?} decoded to {:?}",
+                    opcode,
+                    platform,
+                    instruction
+                );
+            }
+        }
+    }
+}
+
+/// opcodes and whether CHIP-8, SUPER-CHIP and XO-CHIP accept them
+const PLATFORM_OPCODES: [(u16, [bool; 3]); 32] = [
+    (0x00E0, [true, true, true]),
+    (0x00EE, [true, true, true]),
+    (0x00C4, [false, true, true]),
+    (0x00D4, [false, false, true]),
+    (0x00FB, [false, true, true]),
+    (0x00FC, [false, true, true]),
+    (0x00FD, [false, true, true]),
+    (0x00FE, [false, true, true]),
+    (0x00FF, [false, true, true]),
+    (0x00E1, [false, false, false]),
+    (0x0123, [false, false, false]),
+    (0x5120, [true, true, true]),
+    (0x5122, [false, false, true]),
+    (0x5123, [false, false, true]),
+    (0x5121, [false, false, false]),
+    (0x812F, [false, false, false]),
+    (0x9121, [false, false, false]),
+    (0xE19F, [false, false, false]),
+    (0xF000, [false, false, true]),
+    (0xF201, [false, false, true]),
+    (0xF002, [false, false, true]),
+    (0xF130, [false, true, true]),
+    (0xF13A, [false, false, true]),
+    (0xF029, [true, true, true]),
+    (0xF075, [false, true, true]),
+    (0xF785, [false, true, true]),
+    (0xF875, [false, false, true]),
+    (0xFF85, [false, false, true]),
+    (0xF0FF, [false, false, false]),
+    (0xF155, [true, true, true]),
+    (0xF165, [true, true, true]),
+    (0xF1FF, [false, false, false]),
+];
+
+#[test]
+fn platform_opcodes() {
+    for (opcode, accepted) in PLATFORM_OPCODES {
+        for (platform, accepted) in Platform::iter().zip(accepted) {
+            assert_eq!(
+                Instruction::decode(opcode, platform).is_some(),
+                accepted,
+                "{:04X} on {:?}",
+                opcode,
+                platform
+            );
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! parses themes and palettes the way `--theme` and `--palette` do
+
+use chip_8::palette::{Palette, Theme};
+
+#[test]
+fn themes() {
+    for theme in Theme::ALL {
+        assert_eq!(theme.name.parse::<Theme>(), Ok(theme));
+    }
+    assert_eq!("Green_Phosphor".parse::<Theme>(), Ok(Theme::GREEN_PHOSPHOR));
+    assert_eq!("high contrast".parse::<Theme>(), Ok(Theme::HIGH_CONTRAST));
+    assert_eq!(
+        "sepia".parse::<Theme>(),
+        Err(
+            "unknown theme: sepia, try one of octo, classic, amber, green-phosphor, high-contrast"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn colors() {
+    let palette = Theme::CLASSIC
+        .palette
+        .with_colors("#ff8000, 000080")
+        .unwrap();
+    assert_eq!(
+        palette.colors,
+        [[0xFF, 0x80, 0x00], [0x00, 0x00, 0x80], [0xAA; 3], [0x55; 3]]
+    );
+
+    let palette = "#000000,#FFFFFF,#123456,#abcdef"
+        .parse::<Palette>()
+        .unwrap();
+    assert_eq!(
+        palette.colors,
+        [[0; 3], [0xFF; 3], [0x12, 0x34, 0x56], [0xAB, 0xCD, 0xEF]]
+    );
+    assert_eq!(palette.to_string(), "#000000,#ffffff,#123456,#abcdef");
+    assert_eq!(palette.to_string().parse::<Palette>(), Ok(palette));
+
+    // missing colors keep the default ones
+    let palette = "#000000,#ffffff".parse::<Palette>().unwrap();
+    assert_eq!(palette.colors[2..], Palette::default().colors[2..]);
+}
+
+#[test]
+fn color_count() {
+    let error = |value: &str| value.parse::<Palette>().unwrap_err();
+
+    assert_eq!(
+        error("#000000"),
+        "expected 2 to 4 colors but found 1 in \"#000000\""
+    );
+    assert_eq!(
+        error("#000000,#000000,#000000,#000000,#000000"),
+        "expected 2 to 4 colors but found 5 in \"#000000,#000000,#000000,#000000,#000000\""
+    );
+}
+
+#[test]
+fn bad_colors() {
+    for color in [
+        "", "#", "#fff", "#fffffff", "#gggggg", "+fffff", "#-fffff", "#ffé0f",
+    ] {
+        assert_eq!(
+            format!("#000000,{}", color).parse::<Palette>(),
+            Err(format!("{:?} is not a color like #ff8800", color))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! profiles a small program with nested and recursive subroutines
+
+use chip_8::assembler::assemble;
+use chip_8::io::NoInput;
+use chip_8::profiler::Profiler;
+use chip_8::{Emulator, Platform};
+
+/// main calls `outer` which calls `inner` twice, then `down` calls itself until V0 reaches 0
+const SOURCE: &str = "
+    : main
+      outer
+      v0 := 2
+      down
+      exit
+    : outer
+      inner
+      inner
+      return
+    : inner
+      v1 += 1
+      return
+    : down
+      if v0 == 0 then return
+      v0 += -1
+      down
+      return
+";
+
+fn profile() -> Profiler {
+    let assembly = assemble(SOURCE).unwrap();
+    assert_eq!(assembly.labels["outer"], 0x208);
+    assert_eq!(assembly.labels["inner"], 0x20E);
+    assert_eq!(assembly.labels["down"], 0x212);
+
+    let mut emulator = Emulator::new(assembly.rom, "profile".to_string(), Platform::SuperChip)
+        .unwrap()
+        .with_speed(8);
+    let mut profiler = Profiler::new();
+    while !emulator.has_exited() {
+        emulator
+            .run_frame_inspected(&NoInput, &mut profiler)
+            .unwrap();
+    }
+    profiler
+}
+
+#[test]
+fn subroutines() {
+    let profiler = profile();
+    assert_eq!(profiler.instructions(), 21);
+
+    let subroutines = profiler
+        .subroutines()
+        .into_iter()
+        .map(|(address, profile)| (address, (profile.calls, profile.own, profile.total)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        subroutines,
+        [
+            (None, (0, 4, 21)),
+            (Some(0x208), (1, 3, 7)),
+            (Some(0x20E), (2, 4, 4)),
+            // the recursion counts once towards the total
+            (Some(0x212), (3, 10, 10)),
+        ]
+    );
+}
+
+#[test]
+fn hot_spots() {
+    let profiler = profile();
+
+    let hot_spots = profiler
+        .hot_spots()
+        .iter()
+        .map(|hot_spot| (hot_spot.address, hot_spot.count))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        hot_spots,
+        [
+            (0x212, 3),
+            (0x20E, 2),
+            (0x210, 2),
+            (0x216, 2),
+            (0x218, 2),
+            (0x21A, 2),
+            (0x200, 1),
+            (0x202, 1),
+            (0x204, 1),
+            (0x206, 1),
+            (0x208, 1),
+            (0x20A, 1),
+            (0x20C, 1),
+            (0x214, 1),
+        ]
+    );
+
+    assert_eq!(profiler.patterns()["2NNN"], 6);
+    assert_eq!(profiler.patterns()["00EE"], 6);
+    assert_eq!(profiler.frames().iter().sum::<u64>(), 21);
+}
+
+#[test]
+fn folded_stacks() {
+    let mut folded = vec![];
+    profile().write_folded(&mut folded).unwrap();
+
+    assert_eq!(
+        String::from_utf8(folded).unwrap(),
+        "main 4\n\
+         main;sub_208 3\n\
+         main;sub_208;sub_20e 4\n\
+         main;sub_212 4\n\
+         main;sub_212;sub_212 4\n\
+         main;sub_212;sub_212;sub_212 2\n"
+    );
+}
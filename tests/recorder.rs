@@ -0,0 +1,53 @@
+//! checks the headers of recordings
+
+#![cfg(feature = "cli")]
+
+use chip_8::palette::Palette;
+use chip_8::recorder::{Recorder, RecordingFormat};
+use std::io::ErrorKind;
+
+fn gif(width: usize, height: usize, scale: usize) -> std::io::Result<Vec<u8>> {
+    let recorder = Recorder::new(
+        vec![],
+        RecordingFormat::Gif,
+        width,
+        height,
+        scale,
+        &Palette::default(),
+    )?;
+    recorder.finish()
+}
+
+#[test]
+fn gif_size() {
+    let header = gif(128, 64, 511).unwrap();
+    assert_eq!(&header[..6], b"GIF89a");
+    // the logical screen size as little endian u16s
+    assert_eq!(header[6..8], (128u16 * 511).to_le_bytes());
+    assert_eq!(header[8..10], (64u16 * 511).to_le_bytes());
+}
+
+#[test]
+fn gif_too_large() {
+    for (width, height, scale) in [(128, 64, 1024), (128, 64, 512), (64, 2048, 32)] {
+        let err = gif(width, height, scale).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn y4m_header() {
+    let recorder = Recorder::new(
+        vec![],
+        RecordingFormat::Y4m,
+        128,
+        64,
+        1024,
+        &Palette::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        recorder.finish().unwrap(),
+        b"YUV4MPEG2 W131072 H65536 F60:1 Ip A1:1 C444\n"
+    );
+}
@@ -0,0 +1,121 @@
+//! checks the characters every text renderer draws the display with
+
+#![cfg(feature = "cli")]
+
+use chip_8::io::renderer::{Cell, Renderer};
+use std::collections::BTreeSet;
+
+/// a 3x5 pattern, odd in both directions so every renderer has partly filled cells
+const WIDTH: usize = 3;
+#[rustfmt::skip]
+const PIXELS: [u8; 15] = [
+    1, 0, 1,
+    0, 1, 0,
+    1, 1, 0,
+    0, 0, 1,
+    1, 0, 0,
+];
+
+#[test]
+fn blocks() {
+    assert_eq!(
+        Renderer::Blocks.render(&PIXELS, WIDTH),
+        ["██  ██", "  ██  ", "████  ", "    ██", "██    "]
+    );
+}
+
+#[test]
+fn half_blocks() {
+    assert_eq!(
+        Renderer::HalfBlocks.render(&PIXELS, WIDTH),
+        ["▀▄▀", "▀▀▄", "▀  "]
+    );
+}
+
+#[test]
+fn quadrants() {
+    assert_eq!(
+        Renderer::Quadrants.render(&PIXELS, WIDTH),
+        ["▚▘", "▀▖", "▘ "]
+    );
+}
+
+#[test]
+fn braille() {
+    assert_eq!(
+        Renderer::Braille.render(&PIXELS, WIDTH),
+        ["\u{2835}\u{2841}", "\u{2801} "]
+    );
+}
+
+#[test]
+fn every_braille_pattern() {
+    // each of the 255 lit 2x4 cells gets its own Braille character
+    let glyphs = (1..256)
+        .map(|bits: usize| {
+            let pixels = (0..8)
+                .map(|bit| (bits >> bit) as u8 & 1)
+                .collect::<Vec<_>>();
+            let lines = Renderer::Braille.render(&pixels, 2);
+            assert_eq!(lines.len(), 1);
+            let glyph = lines[0].chars().collect::<Vec<_>>();
+            assert_eq!(glyph.len(), 1);
+            glyph[0]
+        })
+        .collect::<BTreeSet<_>>();
+
+    assert_eq!(glyphs.len(), 255);
+    assert!(glyphs
+        .iter()
+        .all(|glyph| ('\u{2801}'..='\u{28FF}').contains(glyph)));
+}
+
+#[test]
+fn sizes() {
+    let sizes = Renderer::ALL
+        .iter()
+        .map(|renderer| (renderer.columns(WIDTH), renderer.rows(PIXELS.len() / WIDTH)))
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, [(6, 5), (3, 3), (2, 3), (2, 2)]);
+
+    for renderer in Renderer::ALL {
+        let lines = renderer.render(&PIXELS, WIDTH);
+        assert_eq!(lines.len(), renderer.rows(5));
+        for line in lines {
+            assert_eq!(line.chars().count(), renderer.columns(WIDTH));
+        }
+    }
+}
+
+#[test]
+fn cell_colors() {
+    // without an unlit pixel the most common plane bits draw and the next most common fill the rest
+    assert_eq!(
+        Renderer::Quadrants.cells(&[1, 2, 2, 3], 2),
+        [[Cell {
+            glyph: '▞',
+            foreground: 2,
+            background: 1,
+        }]]
+    );
+    assert_eq!(
+        Renderer::Quadrants.cells(&[0, 3, 3, 0], 2),
+        [[Cell {
+            glyph: '▞',
+            foreground: 3,
+            background: 0,
+        }]]
+    );
+}
+
+#[test]
+fn names() {
+    for renderer in Renderer::ALL {
+        assert_eq!(renderer.to_string().parse::<Renderer>(), Ok(renderer));
+    }
+    assert_eq!("half".parse::<Renderer>(), Ok(Renderer::HalfBlocks));
+    assert_eq!(
+        "ascii".parse::<Renderer>(),
+        Err("unknown renderer: ascii".to_string())
+    );
+}
@@ -0,0 +1,104 @@
+//! records running emulators and rewinds them frame by frame
+
+use chip_8::assembler::assemble;
+use chip_8::io::NoInput;
+use chip_8::rewind::Rewind;
+use chip_8::{Emulator, Platform};
+
+/// counts, draws random numbers and writes them to memory, so every frame has a different state
+const SOURCE: &str = "
+    : main
+      loop
+        v0 += 1
+        v1 := random 0xFF
+        i := 0x300
+        i += v0
+        save v1
+      again
+";
+
+/// records `frames` frames and returns the states recorded after each
+fn record(rewind: &mut Rewind, frames: usize) -> (Emulator, Vec<Vec<u8>>) {
+    let rom = assemble(SOURCE).unwrap().rom;
+    let mut emulator = Emulator::new(rom, "rewind".to_string(), Platform::Chip8)
+        .unwrap()
+        .with_seed(3);
+
+    let mut states = vec![];
+    for _ in 0..frames {
+        emulator.run_frame(&NoInput).unwrap();
+        rewind.record(&emulator);
+        states.push(emulator.save_state());
+    }
+    (emulator, states)
+}
+
+#[test]
+fn frame_by_frame() {
+    // three keyframes, the last group only partly filled
+    let mut rewind = Rewind::new(1000);
+    let (mut emulator, states) = record(&mut rewind, 150);
+    assert_eq!(rewind.len(), 150);
+
+    for frame in (0..149).rev() {
+        assert_eq!(rewind.rewind(&mut emulator, 1), 1);
+        assert!(emulator.save_state() == states[frame], "frame {}", frame);
+    }
+    assert_eq!(rewind.len(), 1);
+}
+
+#[test]
+fn across_keyframes() {
+    let mut rewind = Rewind::new(1000);
+    let (mut emulator, states) = record(&mut rewind, 150);
+
+    // from the third group into the first
+    assert_eq!(rewind.rewind(&mut emulator, 100), 100);
+    assert!(emulator.save_state() == states[49]);
+    assert_eq!(rewind.len(), 50);
+
+    // recording again after rewinding continues from there
+    emulator.run_frame(&NoInput).unwrap();
+    rewind.record(&emulator);
+    let state = emulator.save_state();
+    emulator.run_frame(&NoInput).unwrap();
+    rewind.record(&emulator);
+    assert_eq!(rewind.rewind(&mut emulator, 1), 1);
+    assert!(emulator.save_state() == state);
+}
+
+#[test]
+fn rewind_everything() {
+    let mut rewind = Rewind::new(1000);
+    let (mut emulator, states) = record(&mut rewind, 70);
+
+    // the emulator stays at the oldest frame once the buffer runs empty
+    assert_eq!(rewind.rewind(&mut emulator, 100), 70);
+    assert!(emulator.save_state() == states[0]);
+    assert!(rewind.is_empty());
+    assert_eq!(rewind.rewind(&mut emulator, 1), 0);
+}
+
+#[test]
+fn capacity() {
+    // whole groups of 60 frames are evicted once there are more than 100 frames
+    let mut rewind = Rewind::new(100);
+    let (mut emulator, states) = record(&mut rewind, 150);
+    assert_eq!(rewind.len(), 90);
+
+    assert_eq!(rewind.rewind(&mut emulator, 89), 89);
+    assert!(emulator.save_state() == states[60]);
+}
+
+#[test]
+fn compression() {
+    let mut rewind = Rewind::new(1000);
+    let (_, states) = record(&mut rewind, 120);
+
+    let raw = states.iter().map(Vec::len).sum::<usize>();
+    assert!(rewind.memory_usage() * 10 < raw);
+
+    rewind.clear();
+    assert!(rewind.is_empty());
+    assert_eq!(rewind.memory_usage(), 0);
+}
@@ -0,0 +1,136 @@
+//! saves and restores whole machines and checks that broken states are rejected without touching the emulator
+
+use chip_8::io::NoInput;
+use chip_8::programs::Program;
+use chip_8::random::RandomSource;
+use chip_8::state::{crc32, StateError, STATE_VERSION};
+use chip_8::{Emulator, Platform};
+use strum::IntoEnumIterator;
+
+fn emulator(program: Program, platform: Platform) -> Emulator {
+    Emulator::new(program.source(), format!("{:?}", program), platform)
+        .unwrap()
+        .with_seed(7)
+}
+
+fn run(emulator: &mut Emulator, frames: usize) {
+    for _ in 0..frames {
+        emulator.run_frame(&NoInput).unwrap();
+    }
+}
+
+/// replaces the checksum so only the changed content can make the state invalid
+fn with_checksum(mut state: Vec<u8>) -> Vec<u8> {
+    state.truncate(state.len() - 4);
+    let checksum = crc32(&state);
+    state.extend(checksum.to_be_bytes());
+    state
+}
+
+/// loads `state` into a running emulator and checks it is rejected with `error` and left as it was
+fn assert_rejected(state: &[u8], error: StateError) {
+    let mut target = emulator(Program::IBM, Platform::Chip8);
+    run(&mut target, 10);
+    let before = target.save_state();
+
+    assert_eq!(target.load_state(state), Err(error));
+    assert_eq!(target.save_state(), before);
+}
+
+#[test]
+fn round_trip() {
+    for platform in Platform::iter() {
+        let mut original = emulator(Program::Corax, platform);
+        run(&mut original, 30);
+        let state = original.save_state();
+
+        let mut restored = emulator(Program::IBM, Platform::Chip8).with_seed(99);
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state, "{:?}", platform);
+        assert_eq!(restored.platform(), platform);
+        assert_eq!(restored.program_name(), "Corax");
+
+        // both machines go on the same way, random numbers included
+        run(&mut original, 30);
+        run(&mut restored, 30);
+        assert_eq!(
+            restored.save_state(),
+            original.save_state(),
+            "{:?}",
+            platform
+        );
+    }
+}
+
+#[test]
+fn bad_magic() {
+    let mut state = emulator(Program::Corax, Platform::Chip8).save_state();
+    state[0] ^= 0xFF;
+    assert_rejected(&state, StateError::NotAState);
+    assert_rejected(b"C8", StateError::NotAState);
+}
+
+#[test]
+fn bad_version() {
+    let mut state = emulator(Program::Corax, Platform::Chip8).save_state();
+    let version = STATE_VERSION + 1;
+    state[4..6].copy_from_slice(&version.to_be_bytes());
+    assert_rejected(
+        &with_checksum(state),
+        StateError::UnsupportedVersion { version },
+    );
+}
+
+#[test]
+fn bad_checksum() {
+    let mut state = emulator(Program::Corax, Platform::Chip8).save_state();
+    let middle = state.len() / 2;
+    state[middle] ^= 0x01;
+    assert_rejected(&state, StateError::ChecksumMismatch);
+}
+
+#[test]
+fn truncated() {
+    let mut state = emulator(Program::Corax, Platform::Chip8).save_state();
+    state.drain(state.len() - 8..state.len() - 4);
+    assert_rejected(&with_checksum(state), StateError::Truncated);
+}
+
+#[test]
+fn bad_platform() {
+    let mut state = emulator(Program::Corax, Platform::Chip8).save_state();
+    state[6] = 0xFF;
+    assert_rejected(
+        &with_checksum(state),
+        StateError::Invalid { field: "platform" },
+    );
+}
+
+/// a source whose state is shorter than the default one
+#[derive(Debug)]
+struct Counter(u32);
+
+impl RandomSource for Counter {
+    fn next_u8(&mut self) -> u8 {
+        self.0 += 1;
+        self.0 as u8
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), String> {
+        let state = state.try_into().map_err(|_| "wrong length".to_string())?;
+        self.0 = u32::from_be_bytes(state);
+        Ok(())
+    }
+}
+
+#[test]
+fn bad_random_state() {
+    let state = emulator(Program::Corax, Platform::Chip8)
+        .with_random_source(Box::new(Counter(0)))
+        .save_state();
+    assert_rejected(&state, StateError::Invalid { field: "random" });
+}
@@ -0,0 +1,152 @@
+//! writes traces of a small program, parses them back and compares runs against them
+
+use chip_8::assembler::assemble;
+use chip_8::io::NoInput;
+use chip_8::trace::{parse_trace, TraceComparer, TraceEntry, Tracer, TRACE_HEADER};
+use chip_8::{Emulator, Platform};
+
+const SOURCE: &str = "
+    : main
+      v0 := 3
+      i := data
+      loop
+        v1 := random 0xFF
+        v0 += -1
+        if v0 != 0 then
+      again
+      delay := v1
+      sub
+      exit
+    : sub
+      return
+    : data
+";
+
+fn emulator() -> Emulator {
+    let rom = assemble(SOURCE).unwrap().rom;
+    Emulator::new(rom, "trace".to_string(), Platform::SuperChip)
+        .unwrap()
+        .with_seed(1)
+}
+
+fn trace() -> String {
+    let mut emulator = emulator();
+    let mut tracer = Tracer::new(vec![]);
+    while !emulator.has_exited() {
+        emulator.run_frame_inspected(&NoInput, &mut tracer).unwrap();
+    }
+    String::from_utf8(tracer.finish().unwrap()).unwrap()
+}
+
+/// runs the program against `reference` until it is done or differs
+fn compare(reference: Vec<TraceEntry>) -> TraceComparer {
+    let mut emulator = emulator();
+    let mut comparer = TraceComparer::new(reference);
+    while comparer.divergence().is_none() && !comparer.is_finished() && !emulator.has_exited() {
+        emulator
+            .run_frame_inspected(&NoInput, &mut comparer)
+            .unwrap();
+    }
+    comparer
+}
+
+#[test]
+fn round_trip() {
+    let trace = trace();
+    assert_eq!(trace.lines().next(), Some(TRACE_HEADER));
+
+    let entries = parse_trace(&trace).unwrap();
+    assert_eq!(entries.len(), trace.lines().count() - 1);
+    assert_eq!(entries[0].to_string(), trace.lines().nth(1).unwrap());
+    assert_eq!(entries[0].opcode, 0x6003);
+    assert_eq!(entries[0].instruction, "v0 := 0x03");
+
+    for (cycle, entry) in entries.iter().enumerate() {
+        assert_eq!(entry.cycle, cycle as u64);
+        assert_eq!(entry.to_string().parse::<TraceEntry>().unwrap(), *entry);
+    }
+
+    // the call shows up in the stack depth
+    let sub = entries.iter().find(|entry| entry.opcode == 0x00EE).unwrap();
+    assert_eq!(sub.sp, 1);
+}
+
+#[test]
+fn comments_and_blank_lines() {
+    let line = "0 0200 6003 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 0000 00 00 00";
+    let trace = format!("{}\n\n# a comment\n{}\n", TRACE_HEADER, line);
+
+    let entries = parse_trace(&trace).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].instruction, "");
+}
+
+#[test]
+fn rejects_bad_lines() {
+    let valid =
+        "0 0200 6003 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 0000 00 00 00 ; v0 := 0x03";
+    let bad = [
+        ("0 0200 6003 ; v0 := 0x03", "expected 23 fields but found 3"),
+        (
+            &format!("{} 00", valid.split(" ; ").next().unwrap()),
+            "expected 23 fields but found 24",
+        ),
+        (
+            &valid.replace("0200", "02G0"),
+            "\"02G0\" is not a hexadecimal number",
+        ),
+        (
+            &valid.replace("6003 00", "6003 100"),
+            "\"100\" is not a byte",
+        ),
+        (&valid.replacen('0', "x", 1), "\"x\" is not a cycle"),
+    ];
+
+    for (line, message) in bad {
+        assert_eq!(line.parse::<TraceEntry>(), Err(message.to_string()));
+
+        // errors name the line of the trace
+        let trace = format!("{}\n{}\n{}\n", TRACE_HEADER, valid, line);
+        assert_eq!(parse_trace(&trace), Err(format!("line 3: {}", message)));
+    }
+}
+
+#[test]
+fn matching_run() {
+    let reference = parse_trace(&trace()).unwrap();
+    let cycles = reference.len() as u64;
+
+    let comparer = compare(reference);
+    assert!(comparer.divergence().is_none());
+    assert!(comparer.is_finished());
+    assert_eq!(comparer.cycle(), cycles);
+}
+
+#[test]
+fn first_divergence() {
+    let original = parse_trace(&trace()).unwrap();
+    let mut reference = original.clone();
+    reference[5].registers[0] = 0x42;
+    reference[5].instruction = "spelled differently".to_string();
+    reference[7].i = 0x123;
+
+    let comparer = compare(reference.clone());
+    let divergence = comparer.divergence().unwrap();
+    assert_eq!(divergence.fields, ["v0"]);
+    assert_eq!(divergence.expected, reference[5]);
+    assert_eq!(divergence.actual, original[5]);
+    assert_eq!(divergence.previous.as_ref(), Some(&reference[4]));
+    assert_eq!(comparer.cycle(), 6);
+}
+
+#[test]
+fn divergence_at_the_start() {
+    let mut reference = parse_trace(&trace()).unwrap();
+    reference[0].pc = 0x300;
+    reference[0].opcode = 0x1234;
+
+    let comparer = compare(reference);
+    let divergence = comparer.divergence().unwrap();
+    assert_eq!(divergence.fields, ["pc", "opcode"]);
+    assert!(divergence.previous.is_none());
+}
@@ -0,0 +1,43 @@
+export type AudioState = { pattern: number[], pitch: number }
+
+export function useAudio() {
+    let context: AudioContext | undefined = undefined;
+    let source: AudioBufferSourceNode | undefined = undefined;
+    let playing: string | undefined = undefined;
+
+    function update(soundPlaying: boolean, audio: AudioState) {
+        let key = soundPlaying ? JSON.stringify(audio) : undefined;
+        if (key === playing)
+            return
+
+        stop()
+        playing = key;
+
+        if (!soundPlaying)
+            return
+
+        context ??= new AudioContext()
+
+        let rate = 4000 * Math.pow(2, (audio.pitch - 64) / 48);
+        let buffer = context.createBuffer(1, 128, rate);
+        let data = buffer.getChannelData(0);
+
+        for (let i = 0; i < 128; i++) {
+            let bit = (audio.pattern[i >> 3] >> (7 - (i & 7))) & 1;
+            data[i] = bit ? 0.25 : -0.25;
+        }
+
+        source = context.createBufferSource()
+        source.buffer = buffer;
+        source.loop = true;
+        source.connect(context.destination)
+        source.start()
+    }
+
+    function stop() {
+        source?.stop()
+        source = undefined;
+    }
+
+    return {update}
+}
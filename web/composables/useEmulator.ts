@@ -1,30 +1,96 @@
-import {get_render_context, init, on_key_down, on_key_up, tick} from "chip-8";
+import {get_render_context, get_speed, init, load_state, on_key_down, on_key_up, rewind, run_frame, save_state, set_speed, tick} from "chip-8";
 import {onKeyDown, onKeyUp} from "@vueuse/core";
 
 export type RenderContext = {
     title: string,
     registries: [number],
-    pixels: [boolean],
+    pixels: [number],
+    sound_playing: boolean,
+    audio: AudioState,
 }
 
-export function useEmulator(selectedProgram: Ref<string>) {
+type Rgb = [number, number, number]
+
+export type Theme = {
+    name: string,
+    palette: { colors: [Rgb, Rgb, Rgb, Rgb] },
+    foreground: Rgb,
+    border: Rgb,
+}
+
+export function useEmulator(selectedProgram: Ref<string>, selectedPlatform: Ref<string>) {
     onMounted(() => {
         reset()
     })
 
+    let error = ref<string | undefined>(undefined);
+
     function reset() {
         stopTicking()
-        init(selectedProgram.value)
+        error.value = undefined;
+
+        try {
+            init(selectedProgram.value, selectedPlatform.value)
+        } catch (e) {
+            error.value = String(e)
+            return
+        }
+
+        speed.value = get_speed()
         step()
     }
 
+    // instructions per frame, the platform default after every reset
+    let speed = ref(0);
+    watch(speed, (value) => {
+        if (value > 0)
+            set_speed(value)
+    })
+
     let renderContext = ref<RenderContext | undefined>(undefined);
+    const audio = useAudio()
+
+    function advance(run: () => void) {
+        if (error.value)
+            return
+
+        try {
+            run()
+        } catch (e) {
+            error.value = String(e)
+            stopTicking()
+            return
+        }
 
-    function step() {
-        tick()
         renderContext.value = get_render_context()
+        audio.update(renderContext.value!.sound_playing, renderContext.value!.audio)
+    }
+
+    function step() {
+        advance(tick)
+    }
+
+    let savedState = ref<Uint8Array | undefined>(undefined);
+
+    function saveState() {
+        savedState.value = save_state()
+    }
+
+    function loadState() {
+        if (!savedState.value)
+            return
+
+        error.value = undefined
+        advance(() => load_state(savedState.value!))
     }
 
+    // frames per press, holding the key repeats it
+    function rewindFrames(frames: number) {
+        if (rewind(frames) > 0)
+            error.value = undefined
+
+        renderContext.value = get_render_context()
+    }
 
     let interval = ref<number | undefined>(undefined);
 
@@ -35,23 +101,29 @@ export function useEmulator(selectedProgram: Ref<string>) {
             return
         }
 
+        // one emulated frame per 60 Hz tick
         interval.value = setInterval(() => {
-            step();
-        }, 0) as unknown as number
+            advance(run_frame);
+        }, 1000 / 60) as unknown as number
     }
 
     function stopTicking() {
         clearInterval(interval.value)
         interval.value = undefined;
+        audio.update(false, {pattern: [], pitch: 0})
     }
 
     onKeyDown(keys, (event) => {
         on_key_down(event.key)
     })
 
+    onKeyDown("Backspace", () => {
+        rewindFrames(4)
+    })
+
     onKeyUp(keys, (event) => {
         on_key_up(event.key)
     })
 
-    return {renderContext, reset, step, toggleRun, interval}
+    return {renderContext, reset, step, toggleRun, interval, error, speed, saveState, loadState, savedState, rewindFrames}
 }
\ No newline at end of file